@@ -1,9 +1,16 @@
 use anyhow::Result;
 use axum::{extract::State, routing::get, Router};
 use odata_model::model::ODataModel;
-use odata_sql_helpers::{reflect::model_with_entity, WithODataExt};
-use odata_web_helpers::{response::ODataResponse, serve_edm, ExtractODataResource, WithODataModelExt};
-use sea_orm::{DatabaseBackend, DatabaseConnection, EntityTrait, MockDatabase, ModelTrait};
+use odata_sql_helpers::{
+    expand::{expand_json, EntityList, ExpandError},
+    reflect::model_with_entity,
+    WithODataExt,
+};
+use odata_web_helpers::{
+    response::{ODataErrorResponse, ODataResponse},
+    serve_edm, ExtractServiceODataResource, WithODataModelExt,
+};
+use sea_orm::{ConnectionTrait, DatabaseBackend, DatabaseConnection, EntityTrait, MockDatabase, ModelTrait};
 use serde_json::{json, Value};
 use std::sync::Arc;
 use test_model::Model as UserModel;
@@ -32,6 +39,8 @@ impl MockedUserDB {
 struct AppState {
     db: MockedUserDB,
     model: ODataModel,
+    /// The entities that navigation paths, lambdas and $expand may lead to
+    entities: EntityList,
 }
 
 impl WithODataModelExt for AppState {
@@ -45,7 +54,8 @@ async fn main() -> Result<()> {
     let db = MockedUserDB;
     let model = ODataModel::new("/V4/UserService");
     let model = model_with_entity::<<UserModel as ModelTrait>::Entity>(model);
-    let app_state = Arc::new(AppState { db, model });
+    let entities = EntityList::new().with_entity::<test_model::Entity>();
+    let app_state = Arc::new(AppState { db, model, entities });
 
     // build our application with a single route
     // try with: curl localhost:8080/V4/UserService/Users
@@ -65,16 +75,23 @@ async fn main() -> Result<()> {
 
 async fn parse_odata_request_handler(
     State(state): State<Arc<AppState>>,
-    ExtractODataResource(resource): ExtractODataResource,
-) -> ODataResponse<Value> {
+    ExtractServiceODataResource(resource): ExtractServiceODataResource,
+) -> Result<ODataResponse<Value>, ODataErrorResponse> {
     let conn = state.db.conn();
     let query_results = test_model::Entity::find()
-        .with_odata_resource(&resource)
+        .with_odata_resource_in(&resource, conn.get_database_backend(), &state.entities)?
         .into_json()
         .all(&conn)
         .await
         .expect("Failed to execute query");
 
+    let query_results =
+        match expand_json::<test_model::Entity, _>(query_results, &resource.expand, &state.entities, &conn).await {
+            Ok(rows) => rows,
+            Err(ExpandError::Query(error)) => return Err(error.into()),
+            Err(ExpandError::Db(error)) => panic!("Failed to expand the related entities: {error}"),
+        };
+
     let body = json!(query_results);
-    ODataResponse::<serde_json::Value>::new(body, "users", &state.model)
+    Ok(ODataResponse::<serde_json::Value>::new(body, "users", &state.model))
 }
@@ -93,6 +93,10 @@ impl Schema {
 pub struct EntityType {
     #[serde(rename = "@Name")]
     pub name: String,
+    #[serde(rename = "@BaseType", skip_serializing_if = "Option::is_none")]
+    pub base_type: Option<String>,
+    #[serde(rename = "@Abstract", skip_serializing_if = "Option::is_none")]
+    pub is_abstract: Option<bool>,
     #[serde(rename = "@HasStream", skip_serializing_if = "Option::is_none")]
     pub has_stream: Option<bool>,
     #[serde(rename = "@OpenType", skip_serializing_if = "Option::is_none")]
@@ -111,6 +115,8 @@ impl EntityType {
     pub fn new(name: String) -> Self {
         Self {
             name,
+            base_type: None,
+            is_abstract: None,
             has_stream: None,
             open_type: None,
             key: None,
@@ -399,6 +405,8 @@ pub struct Null {}
 pub struct ComplexType {
     #[serde(rename = "@Name")]
     pub name: String,
+    #[serde(rename = "@BaseType", skip_serializing_if = "Option::is_none")]
+    pub base_type: Option<String>,
     #[serde(rename = "Property", skip_serializing_if = "Option::is_none")]
     pub property: Option<Vec<Property>>,
     #[serde(rename = "NavigationProperty", skip_serializing_if = "Option::is_none")]
@@ -483,9 +491,9 @@ pub struct Annotations {
 pub struct Action {
     #[serde(rename = "@Name")]
     pub name: String,
-    #[serde(rename = "IsBound", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "@IsBound", skip_serializing_if = "Option::is_none")]
     pub is_bound: Option<String>,
-    #[serde(rename = "EntitySetPath", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "@EntitySetPath", skip_serializing_if = "Option::is_none")]
     pub entity_set_path: Option<String>,
     #[serde(rename = "Parameter", skip_serializing_if = "Option::is_none")]
     pub parameter: Option<Vec<Parameter>>,
@@ -544,10 +552,12 @@ pub struct ReturnType {
 pub struct Function {
     #[serde(rename = "@Name")]
     pub name: String,
-    #[serde(rename = "IsBound", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "@IsBound", skip_serializing_if = "Option::is_none")]
     pub is_bound: Option<String>,
-    #[serde(rename = "EntitySetPath", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "@EntitySetPath", skip_serializing_if = "Option::is_none")]
     pub entity_set_path: Option<String>,
+    #[serde(rename = "@IsComposable", skip_serializing_if = "Option::is_none")]
+    pub is_composable: Option<String>,
     #[serde(rename = "Parameter", skip_serializing_if = "Option::is_none")]
     pub parameter: Option<Vec<Parameter>>,
     #[serde(rename = "ReturnType", skip_serializing_if = "Option::is_none")]
@@ -617,7 +627,7 @@ pub struct ActionImport {
     pub name: String,
     #[serde(rename = "@Action")]
     pub action: String,
-    #[serde(rename = "EntitySet", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "@EntitySet", skip_serializing_if = "Option::is_none")]
     pub entity_set: Option<String>,
     #[serde(rename = "Annotation", skip_serializing_if = "Option::is_none")]
     pub annotation: Option<Vec<Annotation>>,
@@ -630,9 +640,9 @@ pub struct FunctionImport {
     pub name: String,
     #[serde(rename = "@Function")]
     pub function: String,
-    #[serde(rename = "EntitySet", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "@EntitySet", skip_serializing_if = "Option::is_none")]
     pub entity_set: Option<String>,
-    #[serde(rename = "IncludeInServiceDocument", skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "@IncludeInServiceDocument", skip_serializing_if = "Option::is_none")]
     pub include_in_service_document: Option<String>,
     #[serde(rename = "Annotation", skip_serializing_if = "Option::is_none")]
     pub annotation: Option<Vec<Annotation>>,
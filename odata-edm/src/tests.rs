@@ -28,6 +28,22 @@ fn can_deserialize_sample() {
     let collection = first_annotation.collection.as_ref().unwrap();
     assert_eq!(collection.len(), 1);
     assert_eq!(collection[0].string.as_ref().unwrap()[0], "image/jpeg");
+
+    let flight = &schema.entity_type.as_ref().unwrap()[6];
+    assert_eq!(flight.name, "Flight");
+    assert_eq!(
+        flight.base_type.as_deref(),
+        Some("Microsoft.OData.SampleService.Models.TripPin.PublicTransportation")
+    );
+
+    let function = &schema.function.as_ref().unwrap()[0];
+    assert_eq!(function.name, "GetFavoriteAirline");
+    assert_eq!(function.is_bound.as_deref(), Some("true"));
+    assert_eq!(function.parameter.as_ref().unwrap()[0].name, "person");
+
+    let container = &schema.entity_container.as_ref().unwrap()[0];
+    let function_import = &container.function_import.as_ref().unwrap()[0];
+    assert_eq!(function_import.entity_set.as_deref(), Some("Airports"));
 }
 
 #[test]
@@ -0,0 +1,16 @@
+//! Any request URL must be rejected with an error rather than a panic or a stack overflow; run with
+//! `cargo +nightly fuzz run parse_resource` from the odata-model directory.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use odata_model::expression::parse_filter;
+use odata_model::resource::ODataResource;
+
+fuzz_target!(|input: &str| {
+    if let Ok(resource) = ODataResource::try_from(input) {
+        // a parsed resource can be printed and parsed again
+        let _ = ODataResource::try_from(resource.to_relative_url().as_str());
+    }
+    let _ = parse_filter(input);
+});
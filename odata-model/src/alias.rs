@@ -0,0 +1,346 @@
+//! Parameter aliases, e.g. `$filter=Name eq @name&@name='Milk'`, substituted into the parsed query options and keys.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use crate::apply::{Compute, Transformation};
+use crate::error::{ODataError, ODataResult, SyntaxError};
+use crate::expand::{ExpandItem, ExpandOptions};
+use crate::expression::{parse_filter, Expression};
+use crate::invocation::Invocation;
+use crate::resource::{Entity, Key, ODataResource, Value};
+
+/// How deep aliases may refer to other aliases; each level can nest the expression of its value deeper
+const MAX_NESTED_ALIASES: usize = 8;
+
+/// How many expression nodes the references to aliases may substitute in total; each alias is resolved once, but a
+/// value that refers to other aliases several times still grows with every level
+const MAX_EXPANDED_NODES: usize = 10_000;
+
+/// The raw values of the parameter aliases of a request, by their name without the `@`. The resource path and the raw
+/// values of the other query options are kept to locate the references to aliases in errors.
+#[derive(Debug, Default)]
+pub(crate) struct ParameterAliases {
+    values: HashMap<String, String>,
+    options: HashMap<String, String>,
+    path: String,
+    /// The aliases resolved so far, by their name
+    resolved: RefCell<HashMap<String, Resolved>>,
+    /// The number of expression nodes substituted so far
+    expanded: Cell<usize>,
+}
+
+/// The expression of a resolved alias, the number of its nodes, and how many levels of aliases it spans, counting
+/// itself
+#[derive(Debug)]
+struct Resolved {
+    expression: Expression,
+    nodes: usize,
+    levels: usize,
+}
+
+/// Where an alias is referred to: the query option, or none for the resource path, and the aliases being resolved,
+/// of which the last one refers to the next
+struct Scope<'a> {
+    option: Option<&'a str>,
+    resolving: Vec<String>,
+    /// The deepest level of aliases reached, including the levels of the resolved aliases that were referred to
+    deepest: usize,
+}
+
+impl<'a> Scope<'a> {
+    fn new(option: Option<&'a str>) -> Self {
+        Self {
+            option,
+            resolving: Vec::new(),
+            deepest: 0,
+        }
+    }
+}
+
+impl ParameterAliases {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, value: &str) {
+        self.values.insert(name.to_string(), value.to_string());
+    }
+
+    /// Keep the raw value of a query option other than an alias, e.g. `$filter`
+    pub fn insert_option(&mut self, name: &str, value: &str) {
+        self.options.insert(name.to_string(), value.to_string());
+    }
+
+    /// Substitute the aliases in the query options, the keys and the function parameters of the resource
+    pub fn resolve_resource(&self, resource: &mut ODataResource) -> ODataResult<()> {
+        self.resolve_entity(&mut resource.entity)?;
+        for entity in &mut resource.relationships {
+            self.resolve_entity(entity)?;
+        }
+        if let Some(invocation) = &mut resource.invocation {
+            self.resolve_invocation(invocation)?;
+        }
+
+        if let Some(filter) = &mut resource.filter {
+            self.resolve_expression(filter, &mut Scope::new(Some("$filter")))?;
+        }
+        for order_by in &mut resource.order_by {
+            self.resolve_expression(&mut order_by.expression, &mut Scope::new(Some("$orderby")))?;
+        }
+        self.resolve_computes(&mut resource.compute, "$compute")?;
+        self.resolve_transformations(&mut resource.apply)?;
+        self.resolve_expands(&mut resource.expand)
+    }
+
+    /// The error for a reference to an alias, located in the value of the alias or option that refers to it
+    fn invalid_reference(&self, scope: &Scope, name: &str, expected: impl Into<String>) -> ODataError {
+        let (option, text) = match scope.resolving.last() {
+            Some(alias) => (Some(format!("@{alias}")), self.values.get(alias)),
+            None => match scope.option {
+                Some(option) => (Some(option.to_string()), self.options.get(option)),
+                None => (None, Some(&self.path)),
+            },
+        };
+        let offset = text.and_then(|text| reference_offset(text, name)).unwrap_or_default();
+
+        ODataError::Syntax(SyntaxError {
+            option,
+            offset,
+            expected: expected.into(),
+            found: format!("@{name}"),
+        })
+    }
+
+    /// The value of an alias; the value may refer to other aliases, but not to itself. Each alias is resolved once,
+    /// and the references to it are substituted by copies of its expression.
+    fn resolve(&self, name: &str, scope: &mut Scope) -> ODataResult<Expression> {
+        if scope.resolving.iter().any(|alias| alias == name) {
+            return Err(self.invalid_reference(scope, name, "an alias that doesn't refer to itself"));
+        }
+        let level = scope.resolving.len();
+        let cached = self.resolved.borrow().get(name).map(|resolved| resolved.levels);
+        if level + cached.unwrap_or(1) > MAX_NESTED_ALIASES {
+            let expected = format!("at most {MAX_NESTED_ALIASES} nested aliases");
+            return Err(self.invalid_reference(scope, name, expected));
+        }
+        if cached.is_none() {
+            self.resolve_value(name, scope)?;
+        }
+
+        let resolved = self.resolved.borrow();
+        let Resolved {
+            expression,
+            nodes,
+            levels,
+        } = &resolved[name];
+        let expanded = self.expanded.get() + nodes;
+        if expanded > MAX_EXPANDED_NODES {
+            let expected = format!("at most {MAX_EXPANDED_NODES} expression nodes substituted for aliases");
+            return Err(self.invalid_reference(scope, name, expected));
+        }
+        self.expanded.set(expanded);
+        scope.deepest = scope.deepest.max(level + levels);
+
+        Ok(expression.clone())
+    }
+
+    /// Parse the value of an alias and resolve the aliases it refers to
+    fn resolve_value(&self, name: &str, scope: &mut Scope) -> ODataResult<()> {
+        let Some(value) = self.values.get(name) else {
+            return Err(self.invalid_reference(scope, name, "a defined parameter alias"));
+        };
+
+        // complex and collection values are passed as JSON
+        let resolved = if value.starts_with('{') || value.starts_with('[') {
+            let json = serde_json::from_str(value).map_err(|error| {
+                ODataError::from(SyntaxError::new(0, format!("valid JSON ({error})"), value))
+                    .in_option(&format!("@{name}"))
+            })?;
+            Resolved {
+                expression: Expression::Literal(Value::Json(json)),
+                nodes: 1,
+                levels: 1,
+            }
+        } else {
+            let mut expression = parse_filter(value).map_err(|error| error.in_option(&format!("@{name}")))?;
+            let level = scope.resolving.len();
+            let outer = std::mem::replace(&mut scope.deepest, level + 1);
+            scope.resolving.push(name.to_string());
+            self.resolve_expression(&mut expression, scope)?;
+            scope.resolving.pop();
+            let levels = scope.deepest - level;
+            scope.deepest = scope.deepest.max(outer);
+
+            Resolved {
+                nodes: node_count(&expression),
+                expression,
+                levels,
+            }
+        };
+        self.resolved.borrow_mut().insert(name.to_string(), resolved);
+
+        Ok(())
+    }
+
+    fn resolve_expression(&self, expression: &mut Expression, scope: &mut Scope) -> ODataResult<()> {
+        match expression {
+            Expression::Literal(Value::QueryOption(name)) => {
+                *expression = self.resolve(name, scope)?;
+            }
+            Expression::Literal(_) | Expression::Member(_) => (),
+            Expression::List(values) | Expression::Function(_, values) => {
+                for value in values {
+                    self.resolve_expression(value, scope)?;
+                }
+            }
+            Expression::Unary(_, operand) => self.resolve_expression(operand, scope)?,
+            Expression::Binary(left, _, right) => {
+                self.resolve_expression(left, scope)?;
+                self.resolve_expression(right, scope)?;
+            }
+            Expression::Logical(_, operands) => {
+                for operand in operands {
+                    self.resolve_expression(operand, scope)?;
+                }
+            }
+            Expression::Lambda(lambda) => {
+                if let Some((_, predicate)) = &mut lambda.predicate {
+                    self.resolve_expression(predicate, scope)?;
+                }
+            }
+            Expression::TypeFunction(type_function) => {
+                if let Some(operand) = &mut type_function.operand {
+                    self.resolve_expression(operand, scope)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Key values must be literals, e.g. `People(UserName=@user)&@user='russellwhyte'`
+    fn resolve_entity(&self, entity: &mut Entity) -> ODataResult<()> {
+        let Some(Key(values)) = &mut entity.key else {
+            return Ok(());
+        };
+
+        for (_, value) in values {
+            self.resolve_literal(value, "a key")?;
+        }
+
+        Ok(())
+    }
+
+    /// Function parameters must be literals, e.g. `GetNearestAirport(lat=@lat,lon=@lon)&@lat=33&@lon=-118`
+    fn resolve_invocation(&self, invocation: &mut Invocation) -> ODataResult<()> {
+        for (_, value) in &mut invocation.parameters {
+            self.resolve_literal(value, "a function parameter")?;
+        }
+
+        Ok(())
+    }
+
+    fn resolve_literal(&self, value: &mut Value, usage: &str) -> ODataResult<()> {
+        let Value::QueryOption(name) = value else {
+            return Ok(());
+        };
+
+        let name = name.clone();
+        let mut scope = Scope::new(None);
+        match self.resolve(&name, &mut scope)? {
+            Expression::Literal(literal) => *value = literal,
+            _ => return Err(self.invalid_reference(&scope, &name, format!("a literal to be used as {usage}"))),
+        }
+
+        Ok(())
+    }
+
+    fn resolve_computes(&self, computes: &mut [Compute], option: &str) -> ODataResult<()> {
+        for compute in computes {
+            self.resolve_expression(&mut compute.expression, &mut Scope::new(Some(option)))?;
+        }
+
+        Ok(())
+    }
+
+    fn resolve_transformations(&self, transformations: &mut [Transformation]) -> ODataResult<()> {
+        for transformation in transformations {
+            match transformation {
+                Transformation::Aggregate(aggregates) => {
+                    for expression in aggregates
+                        .iter_mut()
+                        .filter_map(|aggregate| aggregate.expression.as_mut())
+                    {
+                        self.resolve_expression(expression, &mut Scope::new(Some("$apply")))?;
+                    }
+                }
+                Transformation::GroupBy(_, transformations) => self.resolve_transformations(transformations)?,
+                Transformation::Filter(expression) | Transformation::TopBottom(_, _, expression) => {
+                    self.resolve_expression(expression, &mut Scope::new(Some("$apply")))?
+                }
+                Transformation::Compute(computes) => self.resolve_computes(computes, "$apply")?,
+                Transformation::Concat(sequences) => {
+                    for sequence in sequences {
+                        self.resolve_transformations(sequence)?;
+                    }
+                }
+                Transformation::Identity => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_expands(&self, expand: &mut [ExpandItem]) -> ODataResult<()> {
+        for ExpandItem { options, .. } in expand {
+            let ExpandOptions {
+                filter,
+                order_by,
+                expand,
+                ..
+            } = options;
+
+            if let Some(filter) = filter {
+                self.resolve_expression(filter, &mut Scope::new(Some("$expand")))?;
+            }
+            for order_by in order_by {
+                self.resolve_expression(&mut order_by.expression, &mut Scope::new(Some("$expand")))?;
+            }
+            self.resolve_expands(expand)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The number of nodes of an expression, including itself
+fn node_count(expression: &Expression) -> usize {
+    let children = match expression {
+        Expression::Literal(_) | Expression::Member(_) => 0,
+        Expression::List(values) | Expression::Function(_, values) | Expression::Logical(_, values) => {
+            values.iter().map(node_count).sum()
+        }
+        Expression::Unary(_, operand) => node_count(operand),
+        Expression::Binary(left, _, right) => node_count(left) + node_count(right),
+        Expression::Lambda(lambda) => lambda
+            .predicate
+            .as_ref()
+            .map_or(0, |(_, predicate)| node_count(predicate)),
+        Expression::TypeFunction(type_function) => type_function.operand.as_deref().map_or(0, node_count),
+    };
+
+    children + 1
+}
+
+/// The offset of the reference `@name` in a value, not counting longer names that start with it
+fn reference_offset(text: &str, name: &str) -> Option<usize> {
+    let reference = format!("@{name}");
+    text.match_indices(&reference).map(|(offset, _)| offset).find(|offset| {
+        let rest = &text[offset + reference.len()..];
+        !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+    })
+}
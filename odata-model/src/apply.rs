@@ -0,0 +1,334 @@
+//! The $apply query option of the Data Aggregation extension: a pipeline of transformations, separated by `/`, e.g.
+//! `filter(Amount gt 0)/groupby((Category),aggregate(Amount with sum as Total))`.
+
+use crate::error::{ODataResult, SyntaxError};
+use crate::expression::{Expression, MemberPath, Parser};
+use crate::lexer::TokenKind;
+use crate::literal::format_literal;
+use crate::printer::join;
+use crate::resource::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transformation {
+    /// `aggregate(Amount with sum as Total,$count as Count)`
+    Aggregate(Vec<Aggregate>),
+    /// `groupby((Category,Customer/Country),aggregate(...))`: the grouping properties and the transformations that
+    /// are applied to each group
+    GroupBy(Vec<MemberPath>, Vec<Transformation>),
+    /// `filter(Amount gt 100)`
+    Filter(Expression),
+    /// `topcount(2,Amount)`, `bottomsum(100,Amount)`, ...
+    TopBottom(TopBottom, Value, Expression),
+    /// `compute(Amount mul 2 as Double)`
+    Compute(Vec<Compute>),
+    /// `concat(seq1,seq2)`: the results of the transformation sequences, one after the other
+    Concat(Vec<Vec<Transformation>>),
+    /// `identity`: the input set itself
+    Identity,
+}
+
+impl std::fmt::Display for Transformation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Aggregate(aggregates) => write!(f, "aggregate({})", join(aggregates, ",")),
+            Self::GroupBy(properties, transformations) if transformations.is_empty() => {
+                write!(f, "groupby(({}))", join(properties, ","))
+            }
+            Self::GroupBy(properties, transformations) => {
+                write!(f, "groupby(({}),{})", join(properties, ","), join(transformations, "/"))
+            }
+            Self::Filter(expression) => write!(f, "filter({expression})"),
+            Self::TopBottom(method, amount, expression) => {
+                write!(f, "{}({},{expression})", method.name(), format_literal(amount))
+            }
+            Self::Compute(compute) => write!(f, "compute({})", join(compute, ",")),
+            Self::Concat(sequences) => {
+                let sequences: Vec<String> = sequences.iter().map(|sequence| join(sequence, "/")).collect();
+                write!(f, "concat({})", sequences.join(","))
+            }
+            Self::Identity => write!(f, "identity"),
+        }
+    }
+}
+
+/// An aggregated value and the name it is returned as
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    /// The aggregated expression; none for `$count`
+    pub expression: Option<Expression>,
+    pub method: AggregateMethod,
+    pub alias: String,
+}
+
+impl std::fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.expression {
+            Some(expression) => write!(f, "{expression} with {} as {}", self.method.name(), self.alias),
+            None => write!(f, "{} as {}", self.method.name(), self.alias),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AggregateMethod {
+    Sum,
+    Min,
+    Max,
+    Average,
+    CountDistinct,
+    /// `$count as Alias`: the number of entities
+    Count,
+    /// A custom aggregation method, e.g. `Ns.median`
+    Custom(String),
+}
+
+impl AggregateMethod {
+    fn from_name(name: &str) -> Option<Self> {
+        let method = match name {
+            "sum" => Self::Sum,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "average" => Self::Average,
+            "countdistinct" => Self::CountDistinct,
+            custom if custom.contains('.') => Self::Custom(custom.to_string()),
+            _ => return None,
+        };
+
+        Some(method)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Sum => "sum",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Average => "average",
+            Self::CountDistinct => "countdistinct",
+            Self::Count => "$count",
+            Self::Custom(name) => name,
+        }
+    }
+}
+
+/// The methods that keep the entities with the highest or lowest values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TopBottom {
+    TopCount,
+    TopSum,
+    TopPercent,
+    BottomCount,
+    BottomSum,
+    BottomPercent,
+}
+
+impl TopBottom {
+    fn from_name(name: &str) -> Option<Self> {
+        let method = match name {
+            "topcount" => Self::TopCount,
+            "topsum" => Self::TopSum,
+            "toppercent" => Self::TopPercent,
+            "bottomcount" => Self::BottomCount,
+            "bottomsum" => Self::BottomSum,
+            "bottompercent" => Self::BottomPercent,
+            _ => return None,
+        };
+
+        Some(method)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TopCount => "topcount",
+            Self::TopSum => "topsum",
+            Self::TopPercent => "toppercent",
+            Self::BottomCount => "bottomcount",
+            Self::BottomSum => "bottomsum",
+            Self::BottomPercent => "bottompercent",
+        }
+    }
+}
+
+/// A computed value and the name it is returned as, e.g. `Price mul Quantity as Total`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compute {
+    pub expression: Expression,
+    pub alias: String,
+}
+
+impl std::fmt::Display for Compute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} as {}", self.expression, self.alias)
+    }
+}
+
+/// Parse the value of an $apply query option
+pub fn parse_apply(value: &str) -> ODataResult<Vec<Transformation>> {
+    let mut parser = Parser::new(value)?;
+    let transformations = parse_sequence(&mut parser)?;
+    parser.expect_end()?;
+    Ok(transformations)
+}
+
+/// Parse transformations separated by `/`
+fn parse_sequence(parser: &mut Parser) -> ODataResult<Vec<Transformation>> {
+    parser.enter()?;
+    let transformations = parse_transformations(parser);
+    parser.leave();
+    transformations
+}
+
+fn parse_transformations(parser: &mut Parser) -> ODataResult<Vec<Transformation>> {
+    let mut transformations = vec![parse_transformation(parser)?];
+
+    while parser.next_if(&TokenKind::Slash) {
+        transformations.push(parse_transformation(parser)?);
+    }
+
+    Ok(transformations)
+}
+
+fn parse_transformation(parser: &mut Parser) -> ODataResult<Transformation> {
+    let (name, offset) = parser.next_identifier("a transformation")?;
+
+    if name == "identity" {
+        return Ok(Transformation::Identity);
+    }
+
+    parser.expect(TokenKind::OpenParen, "'('")?;
+
+    let transformation = match name.as_str() {
+        "aggregate" => Transformation::Aggregate(parse_list(parser, parse_aggregate)?),
+        "groupby" => {
+            parser.expect(TokenKind::OpenParen, "'('")?;
+            let properties = parse_list(parser, parse_property)?;
+            parser.expect(TokenKind::CloseParen, "')'")?;
+
+            let transformations = if parser.next_if(&TokenKind::Comma) {
+                parse_sequence(parser)?
+            } else {
+                Vec::new()
+            };
+            Transformation::GroupBy(properties, transformations)
+        }
+        "filter" => Transformation::Filter(parser.parse_expression()?),
+        "compute" => Transformation::Compute(parse_computes(parser)?),
+        "concat" => {
+            let mut sequences = vec![parse_sequence(parser)?];
+            while parser.next_if(&TokenKind::Comma) {
+                sequences.push(parse_sequence(parser)?);
+            }
+            Transformation::Concat(sequences)
+        }
+        _ => match TopBottom::from_name(&name) {
+            Some(method) => {
+                let amount_offset = parser.offset();
+                let amount = match parser.parse_expression()? {
+                    Expression::Literal(amount @ (Value::Integer(_) | Value::Decimal(_))) => amount,
+                    amount => return Err(SyntaxError::new(amount_offset, "a number", amount.to_string()).into()),
+                };
+                parser.expect(TokenKind::Comma, "','")?;
+                Transformation::TopBottom(method, amount, parser.parse_expression()?)
+            }
+            None => return Err(SyntaxError::new(offset, "a transformation", name).into()),
+        },
+    };
+
+    parser.expect(TokenKind::CloseParen, "')'")?;
+    Ok(transformation)
+}
+
+/// Parse a comma separated list of items, up to the closing bracket
+fn parse_list<T>(parser: &mut Parser, parse_item: fn(&mut Parser) -> ODataResult<T>) -> ODataResult<Vec<T>> {
+    let mut items = vec![parse_item(parser)?];
+
+    while parser.next_if(&TokenKind::Comma) {
+        items.push(parse_item(parser)?);
+    }
+
+    Ok(items)
+}
+
+/// `Amount with sum as Total` or `$count as Count`
+fn parse_aggregate(parser: &mut Parser) -> ODataResult<Aggregate> {
+    if parser.next_keyword(&["$count"]).is_some() {
+        return Ok(Aggregate {
+            expression: None,
+            method: AggregateMethod::Count,
+            alias: parse_alias(parser)?,
+        });
+    }
+
+    let expression = parser.parse_expression()?;
+    if parser.next_keyword(&["with"]).is_none() {
+        let (keyword, offset) = parser.next_identifier("'with'")?;
+        return Err(SyntaxError::new(offset, "'with'", keyword).into());
+    }
+
+    let (method, offset) = parser.next_identifier("an aggregation method")?;
+    let method =
+        AggregateMethod::from_name(&method).ok_or_else(|| SyntaxError::new(offset, "an aggregation method", method))?;
+
+    Ok(Aggregate {
+        expression: Some(expression),
+        method,
+        alias: parse_alias(parser)?,
+    })
+}
+
+/// Parse the value of a $compute query option, e.g. `Price mul Quantity as Total,year(CreatedAt) as Year`
+pub fn parse_compute(value: &str) -> ODataResult<Vec<Compute>> {
+    let mut parser = Parser::new(value)?;
+    let compute = parse_computes(&mut parser)?;
+    parser.expect_end()?;
+
+    Ok(compute)
+}
+
+/// A list of computed values with distinct aliases, also in the `compute` transformation
+fn parse_computes(parser: &mut Parser) -> ODataResult<Vec<Compute>> {
+    let mut compute: Vec<Compute> = Vec::new();
+
+    loop {
+        // `Price mul Quantity as Total`
+        let expression = parser.parse_expression()?;
+        let (alias, offset) = parse_alias_at(parser)?;
+        if compute.iter().any(|compute| compute.alias == alias) {
+            return Err(SyntaxError::new(offset, "an alias that isn't used yet", alias).into());
+        }
+        compute.push(Compute { expression, alias });
+
+        if !parser.next_if(&TokenKind::Comma) {
+            break;
+        }
+    }
+
+    Ok(compute)
+}
+
+fn parse_property(parser: &mut Parser) -> ODataResult<MemberPath> {
+    let offset = parser.offset();
+    match parser.parse_expression()? {
+        Expression::Member(path) => Ok(path),
+        expression => Err(SyntaxError::new(offset, "a property to group by", expression.to_string()).into()),
+    }
+}
+
+/// `as Alias`
+fn parse_alias(parser: &mut Parser) -> ODataResult<String> {
+    parse_alias_at(parser).map(|(alias, _)| alias)
+}
+
+/// `as Alias`, with the offset of the alias
+fn parse_alias_at(parser: &mut Parser) -> ODataResult<(String, usize)> {
+    if parser.next_keyword(&["as"]).is_none() {
+        let (keyword, offset) = parser.next_identifier("'as'")?;
+        return Err(SyntaxError::new(offset, "'as'", keyword).into());
+    }
+
+    let (alias, offset) = parser.next_identifier("an alias")?;
+    if alias.contains('.') || alias.starts_with('$') {
+        return Err(SyntaxError::new(offset, "an alias", alias).into());
+    }
+
+    Ok((alias, offset))
+}
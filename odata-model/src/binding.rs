@@ -0,0 +1,817 @@
+//! Binding of a parsed resource to the EDM of its service. Parsing only checks the syntax of an URL, so e.g.
+//! `People?$filter=Age eq 'old'` parses fine; binding checks that the entity sets, navigation paths and properties the
+//! resource refers to exist, and that the literals are values of the properties they are compared with.
+//!
+//! All problems are reported at once, as diagnostics that can be returned in a 400 response.
+
+use odata_edm::edm::{ComplexType, Edmx, EntityType};
+use rust_decimal::Decimal;
+
+use crate::apply::{Aggregate, Compute, Transformation};
+use crate::error::{ODataError, ODataResult};
+use crate::expand::{ExpandItem, ExpandOptions, ExpandPath};
+use crate::expression::{Expression, Function, Lambda, MemberPath, TypeFunction, TypeFunctionKind, UnaryOperator, IT};
+use crate::literal::format_literal;
+use crate::resource::{extract_entity, is_compatible, Entity, ODataResource, ODataResourceKind, Value};
+use crate::schema::{
+    complex_type_hierarchy, entity_set_type, find_complex_type, find_entity_type, find_enum_type,
+    find_navigation_property, find_property, is_type_definition, navigation_target, qualified_name, type_hierarchy,
+};
+use crate::select::SelectItem;
+
+/// A problem with a resource that parses, but doesn't fit the EDM of the service
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    /// The query option of the problem, e.g. `$filter`; none for the resource path
+    pub option: Option<String>,
+    /// What the problem is about, e.g. the path `Address/Town`
+    pub target: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.option {
+            Some(option) => write!(f, "{option}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiagnosticKind {
+    /// An entity set or singleton the service doesn't have
+    UnknownResource,
+    /// A property or navigation property the type doesn't have
+    UnknownProperty,
+    /// A type cast, `isof` or `cast` to a type the service doesn't have
+    UnknownType,
+    /// A key that doesn't match the key properties of the entity type
+    InvalidKey,
+    /// A path that can't be used where it is, e.g. a property of a collection
+    InvalidPath,
+    /// A literal that isn't a value of the property it is compared with
+    TypeMismatch,
+}
+
+impl DiagnosticKind {
+    /// The code of the problem in an OData error response
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::UnknownResource => "UnknownResource",
+            Self::UnknownProperty => "UnknownProperty",
+            Self::UnknownType => "UnknownType",
+            Self::InvalidKey => "InvalidKey",
+            Self::InvalidPath => "InvalidPath",
+            Self::TypeMismatch => "TypeMismatch",
+        }
+    }
+}
+
+/// A resource that fits the EDM of the service. Numeric literals have the type of the property they are compared
+/// with, e.g. `Budget gt 1000` compares with a double when `Budget` is an `Edm.Single`.
+#[derive(Debug, PartialEq)]
+pub struct BoundResource {
+    pub resource: ODataResource,
+    /// The qualified name of the entity type of the addressed entities, after type casts, e.g. `Trippin.Person`; none
+    /// when the path ends in a structural property, for the results of functions and actions, and for the resources
+    /// of the service itself, like `$metadata`
+    pub entity_type: Option<String>,
+    /// Whether the path addresses a collection, rather than a single entity or value
+    pub is_collection: bool,
+}
+
+impl ODataResource {
+    /// Resolve the entity sets, navigation paths and properties of the resource in the EDM, and check the literals
+    /// against the types of the properties. Paths are only resolved as far as the EDM describes them: dynamic
+    /// properties of open types and the results of functions and actions aren't checked.
+    pub fn bind(mut self, edm: &Edmx) -> ODataResult<BoundResource> {
+        let mut binder = Binder {
+            edm,
+            option: None,
+            diagnostics: Vec::new(),
+        };
+        let target = binder.bind_path(&self);
+
+        if let Some(element) = target.filter(|target| target.is_structured()) {
+            let mut scope = Scope::new(element.element());
+
+            binder.option = Some("$compute");
+            binder.bind_computes(&mut self.compute, &mut scope);
+            binder.option = Some("$apply");
+            binder.bind_transformations(&mut self.apply, &mut scope);
+            binder.option = Some("$filter");
+            if let Some(filter) = &mut self.filter {
+                binder.bind_expression(filter, &scope);
+            }
+            binder.option = Some("$orderby");
+            for order_by in &mut self.order_by {
+                binder.bind_expression(&mut order_by.expression, &scope);
+            }
+            binder.option = Some("$select");
+            binder.bind_select(&self.select, &scope);
+            binder.option = Some("$expand");
+            binder.bind_expand(&mut self.expand, scope.it);
+        }
+
+        if !binder.diagnostics.is_empty() {
+            return Err(ODataError::Binding(binder.diagnostics));
+        }
+
+        let entity_type = match target.map(|target| target.edm_type) {
+            Some(EdmType::Entity(entity_type)) => Some(qualified_name(edm, entity_type)),
+            _ => None,
+        };
+        Ok(BoundResource {
+            resource: self,
+            entity_type,
+            is_collection: target.is_some_and(|target| target.is_collection),
+        })
+    }
+}
+
+/// The type of a path or an expression
+#[derive(Debug, Clone, Copy)]
+enum EdmType<'e> {
+    Entity(&'e EntityType),
+    Complex(&'e ComplexType),
+    /// A primitive type like `Edm.String`, or an enum type or type definition, by its qualified name
+    Primitive(&'e str),
+}
+
+impl EdmType<'_> {
+    fn name(&self) -> &str {
+        match self {
+            Self::Entity(entity_type) => &entity_type.name,
+            Self::Complex(complex_type) => &complex_type.name,
+            Self::Primitive(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Typed<'e> {
+    edm_type: EdmType<'e>,
+    is_collection: bool,
+}
+
+impl<'e> Typed<'e> {
+    fn primitive(name: &'e str) -> Self {
+        Self {
+            edm_type: EdmType::Primitive(name),
+            is_collection: false,
+        }
+    }
+
+    fn is_structured(&self) -> bool {
+        matches!(self.edm_type, EdmType::Entity(_) | EdmType::Complex(_))
+    }
+
+    /// A single element of the collection
+    fn element(self) -> Self {
+        Self {
+            is_collection: false,
+            ..self
+        }
+    }
+}
+
+const BOOLEAN: &str = "Edm.Boolean";
+
+/// What the names in an expression refer to
+#[derive(Debug, Clone)]
+struct Scope<'e> {
+    /// `$it`, the entity or complex value the query option applies to
+    it: Typed<'e>,
+    /// The range variables of the enclosing lambda operators; without a type when their collection isn't known
+    variables: Vec<(String, Option<Typed<'e>>)>,
+    /// The properties added by $compute and $apply, whose types aren't known
+    aliases: Vec<String>,
+}
+
+impl<'e> Scope<'e> {
+    fn new(it: Typed<'e>) -> Self {
+        Self {
+            it,
+            variables: Vec::new(),
+            aliases: Vec::new(),
+        }
+    }
+
+    fn is_alias(&self, name: &str) -> bool {
+        self.aliases.iter().any(|alias| alias == name)
+    }
+}
+
+struct Binder<'e> {
+    edm: &'e Edmx,
+    /// The query option being bound; none for the resource path
+    option: Option<&'static str>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'e> Binder<'e> {
+    fn report(&mut self, kind: DiagnosticKind, target: impl Into<String>, message: String) {
+        self.diagnostics.push(Diagnostic {
+            kind,
+            option: self.option.map(str::to_string),
+            target: target.into(),
+            message,
+        });
+    }
+
+    /// The entity set or singleton, followed by the navigation and structural properties of the path. None when the
+    /// path doesn't address entities or values of a known type.
+    fn bind_path(&mut self, resource: &ODataResource) -> Option<Typed<'e>> {
+        match &resource.kind {
+            ODataResourceKind::EntitySet | ODataResourceKind::Singleton => (),
+            ODataResourceKind::CrossJoin(entity_sets) => {
+                for name in entity_sets {
+                    if entity_set_type(self.edm, name).is_none() {
+                        self.report(
+                            DiagnosticKind::UnknownResource,
+                            name,
+                            format!("'{name}' is not an entity set of the service"),
+                        );
+                    }
+                }
+                return None;
+            }
+            _ => return None,
+        }
+
+        let entity = &resource.entity;
+        let Some((entity_type, is_collection)) = entity_set_type(self.edm, &entity.name) else {
+            self.report(
+                DiagnosticKind::UnknownResource,
+                &entity.name,
+                format!("'{}' is not an entity set or singleton of the service", entity.name),
+            );
+            return None;
+        };
+
+        let root = Typed {
+            edm_type: EdmType::Entity(entity_type),
+            is_collection,
+        };
+        let mut current = self.address(root, entity)?;
+        for relationship in &resource.relationships {
+            current = self.bind_segment(current, relationship)?;
+        }
+        // the result of a bound function or action isn't described by the EDM of the entities
+        if resource.invocation.is_some() {
+            return None;
+        }
+        if let Some(property) = &resource.property {
+            // the property parsed already, like the relationships
+            let property = extract_entity(property).ok()?;
+            current = self.bind_segment(current, &property)?;
+        }
+
+        Some(current)
+    }
+
+    /// A navigation or structural property of the path, with its key and type cast
+    fn bind_segment(&mut self, current: Typed<'e>, segment: &Entity) -> Option<Typed<'e>> {
+        if current.is_collection {
+            self.report(
+                DiagnosticKind::InvalidPath,
+                segment.to_string(),
+                format!("'{}' can't follow a collection without a key", segment.name),
+            );
+            return None;
+        }
+
+        let member = self.member(current, &segment.name, &segment.to_string())?;
+        self.address(member, segment)
+    }
+
+    /// Apply the key and the type cast of a segment to the entities it addresses
+    fn address(&mut self, mut current: Typed<'e>, entity: &Entity) -> Option<Typed<'e>> {
+        if let Some(key) = &entity.key {
+            let EdmType::Entity(entity_type) = current.edm_type else {
+                self.report(
+                    DiagnosticKind::InvalidKey,
+                    entity.to_string(),
+                    format!("'{}' are no entities, so they have no key", entity.name),
+                );
+                return None;
+            };
+            if !current.is_collection {
+                self.report(
+                    DiagnosticKind::InvalidKey,
+                    entity.to_string(),
+                    format!("'{}' is a single entity, so it has no key", entity.name),
+                );
+                return None;
+            }
+
+            // derived types inherit the key
+            let keyed = type_hierarchy(self.edm, entity_type)
+                .into_iter()
+                .find(|entity_type| entity_type.key.is_some())
+                .unwrap_or(entity_type);
+            if let Err(error) = key.named_values(keyed) {
+                let message = match error {
+                    ODataError::InvalidKey(message) => message,
+                    error => error.to_string(),
+                };
+                self.report(DiagnosticKind::InvalidKey, entity.to_string(), message);
+            }
+            current.is_collection = false;
+        }
+
+        match &entity.type_cast {
+            Some(type_cast) => self.cast(current, type_cast, &entity.to_string()),
+            None => Some(current),
+        }
+    }
+
+    /// The property or navigation property of a structured type. None when there is no such property, which is
+    /// reported unless the type is open.
+    fn member(&mut self, current: Typed<'e>, name: &str, target: &str) -> Option<Typed<'e>> {
+        let edm = self.edm;
+        let (property, navigation_property, is_open) = match current.edm_type {
+            EdmType::Entity(entity_type) => (
+                find_property(edm, entity_type, name),
+                find_navigation_property(edm, entity_type, name),
+                type_hierarchy(edm, entity_type)
+                    .iter()
+                    .any(|entity_type| entity_type.open_type == Some(true)),
+            ),
+            EdmType::Complex(complex_type) => {
+                let hierarchy = complex_type_hierarchy(edm, complex_type);
+                (
+                    hierarchy
+                        .iter()
+                        .flat_map(|complex_type| complex_type.property.iter().flatten())
+                        .find(|property| property.name == name),
+                    hierarchy
+                        .iter()
+                        .flat_map(|complex_type| complex_type.navigation_property.iter().flatten())
+                        .find(|property| property.name == name),
+                    false,
+                )
+            }
+            EdmType::Primitive(_) => (None, None, false),
+        };
+
+        if let Some(property) = property {
+            return Some(self.resolve_type(&property._type));
+        }
+        if let Some((entity_type, is_collection)) =
+            navigation_property.and_then(|property| navigation_target(edm, property))
+        {
+            return Some(Typed {
+                edm_type: EdmType::Entity(entity_type),
+                is_collection,
+            });
+        }
+
+        if !is_open {
+            self.report(
+                DiagnosticKind::UnknownProperty,
+                target,
+                format!("'{name}' is not a property of '{}'", current.edm_type.name()),
+            );
+        }
+        None
+    }
+
+    /// The type of a property, e.g. `Collection(Ns.Location)`
+    fn resolve_type(&self, name: &'e str) -> Typed<'e> {
+        let (name, is_collection) = match name.strip_prefix("Collection(").and_then(|name| name.strip_suffix(')')) {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+
+        let edm_type = if name.starts_with("Edm.") {
+            EdmType::Primitive(name)
+        } else if let Some(entity_type) = find_entity_type(self.edm, name) {
+            EdmType::Entity(entity_type)
+        } else if let Some(complex_type) = find_complex_type(self.edm, name) {
+            EdmType::Complex(complex_type)
+        } else {
+            EdmType::Primitive(name)
+        };
+
+        Typed {
+            edm_type,
+            is_collection,
+        }
+    }
+
+    /// A type cast to a derived entity or complex type
+    fn cast(&mut self, current: Typed<'e>, name: &str, target: &str) -> Option<Typed<'e>> {
+        let edm_type = match current.edm_type {
+            EdmType::Entity(entity_type) => match find_entity_type(self.edm, name) {
+                Some(derived)
+                    if type_hierarchy(self.edm, derived)
+                        .iter()
+                        .any(|base| std::ptr::eq(*base, entity_type)) =>
+                {
+                    EdmType::Entity(derived)
+                }
+                Some(_) => {
+                    self.report(
+                        DiagnosticKind::InvalidPath,
+                        target,
+                        format!("'{name}' doesn't derive from '{}'", entity_type.name),
+                    );
+                    return None;
+                }
+                None => {
+                    self.report(
+                        DiagnosticKind::UnknownType,
+                        target,
+                        format!("'{name}' is not an entity type of the service"),
+                    );
+                    return None;
+                }
+            },
+            EdmType::Complex(complex_type) => match find_complex_type(self.edm, name) {
+                Some(derived)
+                    if complex_type_hierarchy(self.edm, derived)
+                        .iter()
+                        .any(|base| std::ptr::eq(*base, complex_type)) =>
+                {
+                    EdmType::Complex(derived)
+                }
+                Some(_) => {
+                    self.report(
+                        DiagnosticKind::InvalidPath,
+                        target,
+                        format!("'{name}' doesn't derive from '{}'", complex_type.name),
+                    );
+                    return None;
+                }
+                None => {
+                    self.report(
+                        DiagnosticKind::UnknownType,
+                        target,
+                        format!("'{name}' is not a complex type of the service"),
+                    );
+                    return None;
+                }
+            },
+            EdmType::Primitive(primitive) => {
+                self.report(
+                    DiagnosticKind::InvalidPath,
+                    target,
+                    format!("'{primitive}' values can't be cast to '{name}'"),
+                );
+                return None;
+            }
+        };
+
+        Some(Typed { edm_type, ..current })
+    }
+
+    /// Follow the segments of a path from a type; a collection can only be followed by a type cast or `$count`
+    fn follow(&mut self, start: Typed<'e>, segments: &[String], target: &str) -> Option<Typed<'e>> {
+        let mut current = start;
+
+        for (index, segment) in segments.iter().enumerate() {
+            if segment.contains('.') {
+                current = self.cast(current, segment, target)?;
+            } else if current.is_collection {
+                if segment == "$count" && index + 1 == segments.len() {
+                    return Some(Typed::primitive("Edm.Int32"));
+                }
+                self.report(
+                    DiagnosticKind::InvalidPath,
+                    target,
+                    format!("'{segment}' can't follow a collection; use any or all instead"),
+                );
+                return None;
+            } else {
+                current = self.member(current, segment, target)?;
+            }
+        }
+
+        Some(current)
+    }
+
+    /// A path of an expression, starting at `$it`, at a range variable, or at a computed property
+    fn bind_member(&mut self, path: &MemberPath, scope: &Scope<'e>) -> Option<Typed<'e>> {
+        let segments = path.segments();
+        let (start, rest) = match segments.split_first() {
+            Some((first, rest)) if first == IT => (scope.it, rest),
+            Some((first, rest)) => match scope.variables.iter().rev().find(|(name, _)| name == first) {
+                Some((_, variable)) => ((*variable)?, rest),
+                None if rest.is_empty() && scope.is_alias(first) => return None,
+                None => (scope.it, segments),
+            },
+            None => return None,
+        };
+
+        self.follow(start, rest, &path.to_string())
+    }
+
+    /// Bind the paths and check the literals of an expression; the type of the expression when it is known
+    fn bind_expression(&mut self, expression: &mut Expression, scope: &Scope<'e>) -> Option<Typed<'e>> {
+        match expression {
+            Expression::Literal(_) => None,
+            Expression::Member(path) => self.bind_member(path, scope),
+            Expression::List(items) => {
+                for item in items {
+                    self.bind_expression(item, scope);
+                }
+                None
+            }
+            Expression::Function(function, arguments) => {
+                for argument in arguments {
+                    self.bind_expression(argument, scope);
+                }
+                Some(Typed::primitive(result_type(*function)))
+            }
+            Expression::Unary(UnaryOperator::Not, operand) => {
+                self.bind_expression(operand, scope);
+                Some(Typed::primitive(BOOLEAN))
+            }
+            Expression::Unary(UnaryOperator::Negate, operand) => self.bind_expression(operand, scope),
+            Expression::Binary(left, operator, right) => {
+                let left_type = self.bind_expression(left, scope);
+                let right_type = self.bind_expression(right, scope);
+                if operator.is_arithmetic() {
+                    return None;
+                }
+
+                self.check_operand(right, left_type, left);
+                self.check_operand(left, right_type, right);
+                Some(Typed::primitive(BOOLEAN))
+            }
+            Expression::Logical(_, operands) => {
+                for operand in operands {
+                    self.bind_expression(operand, scope);
+                }
+                Some(Typed::primitive(BOOLEAN))
+            }
+            Expression::Lambda(lambda) => {
+                self.bind_lambda(lambda, scope);
+                Some(Typed::primitive(BOOLEAN))
+            }
+            Expression::TypeFunction(type_function) => self.bind_type_function(type_function, scope),
+        }
+    }
+
+    /// The predicate of `any` or `all`, with the range variable as an element of the collection
+    fn bind_lambda(&mut self, lambda: &mut Lambda, scope: &Scope<'e>) {
+        let collection = self.bind_member(&lambda.path, scope);
+        if collection.is_some_and(|collection| !collection.is_collection) {
+            self.report(
+                DiagnosticKind::InvalidPath,
+                lambda.path.to_string(),
+                format!("'{}' is not a collection", lambda.path),
+            );
+        }
+
+        if let Some((variable, predicate)) = &mut lambda.predicate {
+            let mut scope = scope.clone();
+            scope.variables.push((variable.clone(), collection.map(Typed::element)));
+            self.bind_expression(predicate, &scope);
+        }
+    }
+
+    fn bind_type_function(&mut self, type_function: &mut TypeFunction, scope: &Scope<'e>) -> Option<Typed<'e>> {
+        if let Some(operand) = &mut type_function.operand {
+            self.bind_expression(operand, scope);
+        }
+
+        let name = type_function.type_name.as_str();
+        let edm = self.edm;
+        let edm_type = match (find_entity_type(edm, name), find_complex_type(edm, name)) {
+            (Some(entity_type), _) => Some(EdmType::Entity(entity_type)),
+            (None, Some(complex_type)) => Some(EdmType::Complex(complex_type)),
+            (None, None) => {
+                let is_known =
+                    name.starts_with("Edm.") || find_enum_type(edm, name).is_some() || is_type_definition(edm, name);
+                if !is_known {
+                    self.report(
+                        DiagnosticKind::UnknownType,
+                        name,
+                        format!("'{name}' is not a type of the service"),
+                    );
+                }
+                None
+            }
+        };
+
+        match type_function.function {
+            TypeFunctionKind::IsOf => Some(Typed::primitive(BOOLEAN)),
+            TypeFunctionKind::Cast => edm_type.map(|edm_type| Typed {
+                edm_type,
+                is_collection: false,
+            }),
+        }
+    }
+
+    /// Check a literal, or the literals of a list, against the primitive or enum type of what it is compared with
+    fn check_operand(&mut self, operand: &mut Expression, expected: Option<Typed<'e>>, compared: &Expression) {
+        let Some(EdmType::Primitive(edm_type)) = expected.map(|expected| expected.edm_type) else {
+            return;
+        };
+
+        let target = compared.to_string();
+        match operand {
+            Expression::Literal(value) => self.check_literal(value, edm_type, &target),
+            Expression::List(items) => {
+                for item in items {
+                    if let Expression::Literal(value) = item {
+                        self.check_literal(value, edm_type, &target);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn check_literal(&mut self, value: &mut Value, edm_type: &str, target: &str) {
+        if matches!(value, Value::Null | Value::QueryOption(_) | Value::Json(_)) {
+            return;
+        }
+
+        if edm_type.starts_with("Edm.") {
+            if is_compatible(value, edm_type) {
+                promote(value, edm_type);
+            } else {
+                self.report(
+                    DiagnosticKind::TypeMismatch,
+                    target,
+                    format!("{} is not a valid {edm_type} for '{target}'", format_literal(value)),
+                );
+            }
+            return;
+        }
+
+        let Some(enum_type) = find_enum_type(self.edm, edm_type) else {
+            return;
+        };
+        let members = match value {
+            Value::Enum(type_name, members)
+                if find_enum_type(self.edm, type_name).is_some_and(|known| std::ptr::eq(known, enum_type)) =>
+            {
+                members.clone()
+            }
+            // OData 4.01 allows the members without the type
+            Value::String(members) => members.clone(),
+            _ => {
+                self.report(
+                    DiagnosticKind::TypeMismatch,
+                    target,
+                    format!("{} is not a valid {edm_type} for '{target}'", format_literal(value)),
+                );
+                return;
+            }
+        };
+
+        // flags are separated by commas, and members can be given by their value
+        for member in members.split(',').map(str::trim) {
+            let is_member = enum_type
+                .member
+                .iter()
+                .flatten()
+                .any(|known| known.name == member || known.value == member);
+            if !is_member {
+                self.report(
+                    DiagnosticKind::TypeMismatch,
+                    target,
+                    format!("'{member}' is not a member of {edm_type} for '{target}'"),
+                );
+            }
+        }
+    }
+
+    fn bind_computes(&mut self, computes: &mut [Compute], scope: &mut Scope<'e>) {
+        for compute in computes {
+            self.bind_expression(&mut compute.expression, scope);
+            scope.aliases.push(compute.alias.clone());
+        }
+    }
+
+    fn bind_aggregates(&mut self, aggregates: &mut [Aggregate], scope: &mut Scope<'e>) {
+        for aggregate in aggregates {
+            if let Some(expression) = &mut aggregate.expression {
+                self.bind_expression(expression, scope);
+            }
+            scope.aliases.push(aggregate.alias.clone());
+        }
+    }
+
+    /// The transformations in order; the aliases they introduce can be used by the ones after them
+    fn bind_transformations(&mut self, transformations: &mut [Transformation], scope: &mut Scope<'e>) {
+        for transformation in transformations {
+            match transformation {
+                Transformation::Aggregate(aggregates) => self.bind_aggregates(aggregates, scope),
+                Transformation::GroupBy(paths, transformations) => {
+                    for path in paths.iter() {
+                        self.bind_member(path, scope);
+                    }
+                    self.bind_transformations(transformations, scope);
+                }
+                Transformation::Filter(expression) | Transformation::TopBottom(_, _, expression) => {
+                    self.bind_expression(expression, scope);
+                }
+                Transformation::Compute(computes) => self.bind_computes(computes, scope),
+                Transformation::Concat(sequences) => {
+                    let mut aliases = Vec::new();
+                    for sequence in sequences {
+                        let mut sequence_scope = scope.clone();
+                        self.bind_transformations(sequence, &mut sequence_scope);
+                        aliases.extend(sequence_scope.aliases);
+                    }
+                    scope.aliases.extend(aliases);
+                }
+                Transformation::Identity => (),
+            }
+        }
+    }
+
+    fn bind_select(&mut self, items: &[SelectItem], scope: &Scope<'e>) {
+        for item in items {
+            let SelectItem::Property { path, select } = item else {
+                continue;
+            };
+            if path.as_property().is_some_and(|property| scope.is_alias(property)) {
+                continue;
+            }
+
+            let selected = self.follow(scope.it, path.segments(), &path.to_string());
+            if let (Some(selected), false) = (selected, select.is_empty()) {
+                self.bind_select(select, &Scope::new(selected.element()));
+            }
+        }
+    }
+
+    /// The navigation properties to expand, with their nested query options
+    fn bind_expand(&mut self, items: &mut [ExpandItem], it: Typed<'e>) {
+        for item in items {
+            let ExpandPath::Navigation(path) = &item.path else {
+                continue;
+            };
+
+            let target = path.to_string();
+            let Some(expanded) = self.follow(it, path.segments(), &target) else {
+                continue;
+            };
+            if !matches!(expanded.edm_type, EdmType::Entity(_)) {
+                self.report(
+                    DiagnosticKind::InvalidPath,
+                    &target,
+                    format!("'{target}' is not a navigation property"),
+                );
+                continue;
+            }
+
+            self.bind_expand_options(&mut item.options, &Scope::new(expanded.element()));
+        }
+    }
+
+    fn bind_expand_options(&mut self, options: &mut ExpandOptions, scope: &Scope<'e>) {
+        if let Some(filter) = &mut options.filter {
+            self.bind_expression(filter, scope);
+        }
+        for order_by in &mut options.order_by {
+            self.bind_expression(&mut order_by.expression, scope);
+        }
+        self.bind_select(&options.select, scope);
+        self.bind_expand(&mut options.expand, scope.it);
+    }
+}
+
+/// The type of the result of a canonical function
+fn result_type(function: Function) -> &'static str {
+    match function {
+        Function::Contains | Function::StartsWith | Function::EndsWith | Function::MatchesPattern => BOOLEAN,
+        Function::Length
+        | Function::IndexOf
+        | Function::Year
+        | Function::Month
+        | Function::Day
+        | Function::Hour
+        | Function::Minute
+        | Function::Second
+        | Function::TotalOffsetMinutes => "Edm.Int32",
+        Function::Substring | Function::ToLower | Function::ToUpper | Function::Trim | Function::Concat => "Edm.String",
+        Function::FractionalSeconds => "Edm.Decimal",
+        Function::Date => "Edm.Date",
+        Function::Time => "Edm.TimeOfDay",
+        Function::Now | Function::MaxDateTime | Function::MinDateTime => "Edm.DateTimeOffset",
+    }
+}
+
+/// Give an integer literal the type of the property it is compared with, e.g. `1000` for an `Edm.Double`. Decimals
+/// are left as they are, as they may not have an exact double.
+fn promote(value: &mut Value, edm_type: &str) {
+    let promoted = match (edm_type, &*value) {
+        ("Edm.Int64", Value::Integer(value)) => Value::Int64(i64::from(*value)),
+        ("Edm.Decimal", Value::Integer(value)) => Value::Decimal(Decimal::from(*value)),
+        ("Edm.Decimal", Value::Int64(value)) => Value::Decimal(Decimal::from(*value)),
+        ("Edm.Double" | "Edm.Single", Value::Integer(value)) => Value::Double(f64::from(*value)),
+        ("Edm.Double" | "Edm.Single", Value::Int64(value)) => Value::Double(*value as f64),
+        _ => return,
+    };
+
+    *value = promoted;
+}
@@ -1,5 +1,8 @@
 use thiserror::Error;
 
+use crate::binding::Diagnostic;
+use crate::printer::join;
+
 #[derive(Error, Debug)]
 pub enum ODataError {
     #[error("invalid Url")]
@@ -8,10 +11,97 @@ pub enum ODataError {
     IncompletePath,
     #[error("the operation is not supported")]
     InvalidOperation,
-    #[error("invalid OData query; $top and $skip must be a positive integer")]
-    InvalidQueryTopSkip,
-    #[error("invalid OData query; incompatible $orderby format")]
-    InvalidQueryOrderBy,
+    /// The key doesn't fit the key properties of the entity type, e.g. a name of another property
+    #[error("invalid OData Url; {0}")]
+    InvalidKey(String),
+    /// The request body of an action isn't a JSON object of its parameters
+    #[error("invalid OData request; {0}")]
+    InvalidInvocation(String),
+    /// The URL can't be the root of a service, e.g. `mailto:x`
+    #[error("invalid service root; {0} can't be the root of a service")]
+    InvalidServiceRoot(String),
+    /// The URL is on another host, or not below the path of the service root
+    #[error("invalid OData Url; {0} is outside the service root {1}")]
+    OutsideServiceRoot(String, String),
+    #[error("invalid OData query; {0}")]
+    Syntax(SyntaxError),
+    /// The resource doesn't fit the EDM of the service, e.g. it refers to an unknown property
+    #[error("invalid OData request; {}", join(.0, "; "))]
+    Binding(Vec<Diagnostic>),
+    /// The query refers to something the data source doesn't have, e.g. a property without a column
+    #[error("invalid OData query; {0}")]
+    InvalidQuery(String),
+    /// The query is valid, but the data source can't answer it, e.g. a `has` expression in a SQL filter
+    #[error("unsupported OData query; {0}")]
+    Unsupported(String),
+}
+
+impl ODataError {
+    /// Name the query option a syntax error is in, unless it is known already
+    pub(crate) fn in_option(self, option: &str) -> Self {
+        match self {
+            ODataError::Syntax(mut error) => {
+                error.option.get_or_insert_with(|| option.to_string());
+                ODataError::Syntax(error)
+            }
+            error => error,
+        }
+    }
+
+    /// Move the offset of a syntax error in a part of a value to the offset in the whole value
+    pub(crate) fn offset_by(self, offset: usize) -> Self {
+        match self {
+            ODataError::Syntax(mut error) => {
+                error.offset += offset;
+                ODataError::Syntax(error)
+            }
+            error => error,
+        }
+    }
+}
+
+/// Where and why a value couldn't be parsed, e.g. the `$filter` value `Name eq` at position 7, where an operand was
+/// expected but the value ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxError {
+    /// The query option of the value, e.g. `$filter`; none for the resource path or when the value is parsed by itself
+    pub option: Option<String>,
+    /// The byte offset in the value of the query option, also for the options nested in `$expand`
+    pub offset: usize,
+    /// What was expected at the offset, e.g. `')'` or `an operand`
+    pub expected: String,
+    /// The text that was found at the offset instead; empty at the end of the value
+    pub found: String,
+}
+
+impl SyntaxError {
+    pub(crate) fn new(offset: usize, expected: impl Into<String>, found: impl Into<String>) -> Self {
+        Self {
+            option: None,
+            offset,
+            expected: expected.into(),
+            found: found.into(),
+        }
+    }
+}
+
+impl From<SyntaxError> for ODataError {
+    fn from(error: SyntaxError) -> Self {
+        ODataError::Syntax(error)
+    }
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(option) = &self.option {
+            write!(f, "{option} ")?;
+        }
+        write!(f, "at position {}; expected {}, ", self.offset, self.expected)?;
+        match self.found.as_str() {
+            "" => write!(f, "found the end"),
+            found => write!(f, "found '{found}'"),
+        }
+    }
 }
 
 pub type ODataResult<T> = Result<T, ODataError>;
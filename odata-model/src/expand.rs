@@ -0,0 +1,207 @@
+//! The $expand query option, which includes related entities in the response.
+
+use crate::error::{ODataError, ODataResult, SyntaxError};
+use crate::expression::{parse_filter, Expression, MemberPath};
+use crate::lexer::{check_nesting, is_identifier, offset_in, split_nested_options, split_top_level, MAX_NESTING};
+use crate::printer::join;
+use crate::resource::{parse_sort_order, parse_top_skip, OrderBy};
+use crate::select::{parse_select_items, SelectItem};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandItem {
+    pub path: ExpandPath,
+    /// `$ref`: only include references to the related entities, e.g. `Friends/$ref`
+    pub reference: bool,
+    pub options: ExpandOptions,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpandPath {
+    /// `*`: all navigation properties
+    All,
+    /// A navigation property, optionally reached through complex properties or a type cast, e.g. `Address/Country`
+    Navigation(MemberPath),
+}
+
+/// The query options that apply to the expanded entities, e.g. `Trips($filter=Budget gt 1000;$top=2)`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExpandOptions {
+    pub select: Vec<SelectItem>,
+    pub filter: Option<Expression>,
+    pub search: Option<String>,
+    pub order_by: Vec<OrderBy>,
+    pub top: Option<u32>,
+    pub skip: Option<u32>,
+    /// `$count=true`: include the number of related entities
+    pub count: bool,
+    pub levels: Option<Levels>,
+    pub expand: Vec<ExpandItem>,
+}
+
+/// The number of levels of a recursive expansion, e.g. `Friends($levels=2)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Levels {
+    Count(u32),
+    Max,
+}
+
+impl ExpandItem {
+    /// The navigation property of the item, i.e. `Country` for `Address/Country`
+    pub fn navigation(&self) -> Option<&str> {
+        match &self.path {
+            ExpandPath::Navigation(path) => path.segments().last().map(|s| s.as_str()),
+            ExpandPath::All => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ExpandItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            ExpandPath::All => write!(f, "*")?,
+            ExpandPath::Navigation(path) => write!(f, "{path}")?,
+        }
+        if self.reference {
+            write!(f, "/$ref")?;
+        }
+        if self.options != ExpandOptions::default() {
+            write!(f, "({})", self.options)?;
+        }
+        Ok(())
+    }
+}
+
+/// The options separated by `;`, as they appear between the brackets of an expanded item
+impl std::fmt::Display for ExpandOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut options = Vec::new();
+        if !self.select.is_empty() {
+            options.push(format!("$select={}", join(&self.select, ",")));
+        }
+        if let Some(filter) = &self.filter {
+            options.push(format!("$filter={filter}"));
+        }
+        if let Some(search) = &self.search {
+            options.push(format!("$search={search}"));
+        }
+        if !self.order_by.is_empty() {
+            options.push(format!("$orderby={}", join(&self.order_by, ",")));
+        }
+        if let Some(top) = self.top {
+            options.push(format!("$top={top}"));
+        }
+        if let Some(skip) = self.skip {
+            options.push(format!("$skip={skip}"));
+        }
+        if self.count {
+            options.push("$count=true".to_string());
+        }
+        match self.levels {
+            Some(Levels::Count(levels)) => options.push(format!("$levels={levels}")),
+            Some(Levels::Max) => options.push("$levels=max".to_string()),
+            None => (),
+        }
+        if !self.expand.is_empty() {
+            options.push(format!("$expand={}", join(&self.expand, ",")));
+        }
+
+        write!(f, "{}", options.join(";"))
+    }
+}
+
+/// Parse the value of an $expand query option, e.g. `Trips($select=Name;$expand=PlanItems),Friends/$ref`
+pub fn parse_expand(value: &str) -> ODataResult<Vec<ExpandItem>> {
+    check_nesting(value, MAX_NESTING)?;
+    parse_items(value, value)
+}
+
+/// The items of the option value, or of a nested $expand; the offsets of errors are the ones in the option value
+fn parse_items(option: &str, value: &str) -> ODataResult<Vec<ExpandItem>> {
+    split_top_level(value, ',')
+        .into_iter()
+        .map(|item| parse_expand_item(option, item))
+        .collect()
+}
+
+fn parse_expand_item(option: &str, item: &str) -> ODataResult<ExpandItem> {
+    let invalid = |expected: &str| ODataError::from(SyntaxError::new(offset_in(option, item), expected, item));
+
+    let (path, options) =
+        split_nested_options(item).ok_or_else(|| invalid("a navigation property with balanced brackets"))?;
+    let mut segments: Vec<String> = path.split('/').map(|segment| segment.to_string()).collect();
+
+    let reference = segments.last().is_some_and(|segment| segment == "$ref");
+    if reference {
+        segments.pop();
+    }
+
+    let path = match segments.as_slice() {
+        [all] if all == "*" => ExpandPath::All,
+        _ if segments.iter().all(|segment| is_identifier(segment)) => ExpandPath::Navigation(MemberPath(segments)),
+        _ => return Err(invalid("a navigation property or '*'")),
+    };
+
+    let options = match options {
+        Some(options) => parse_expand_options(option, options)?,
+        None => ExpandOptions::default(),
+    };
+
+    // references have no properties to select or expand, and `*` only allows $levels
+    let has_entity_options = !options.select.is_empty() || !options.expand.is_empty() || options.levels.is_some();
+    let only_levels = ExpandOptions {
+        levels: options.levels,
+        ..Default::default()
+    };
+    if reference && has_entity_options {
+        return Err(invalid("a reference without $select, $expand or $levels"));
+    }
+    if path == ExpandPath::All && options != only_levels {
+        return Err(invalid("'*' with no other option than $levels"));
+    }
+
+    Ok(ExpandItem {
+        path,
+        reference,
+        options,
+    })
+}
+
+fn parse_expand_options(option: &str, value: &str) -> ODataResult<ExpandOptions> {
+    let mut options = ExpandOptions::default();
+
+    for nested in split_top_level(value, ';') {
+        let offset = |part: &str| offset_in(option, part);
+        let invalid = |expected: &str, part: &str| ODataError::from(SyntaxError::new(offset(part), expected, part));
+
+        let Some((name, value)) = nested.split_once('=') else {
+            return Err(invalid("a query option like $filter=...", nested));
+        };
+        // the parsers of the options give the offsets in the nested value
+        let in_value = |error: ODataError| error.offset_by(offset(value));
+
+        match name {
+            "$select" => options.select = parse_select_items(option, value)?,
+            "$filter" => options.filter = Some(parse_filter(value).map_err(in_value)?),
+            "$search" => options.search = Some(value.to_string()),
+            "$orderby" => options.order_by = parse_sort_order(value).map_err(in_value)?,
+            "$top" => options.top = Some(parse_top_skip(value).map_err(in_value)?),
+            "$skip" => options.skip = Some(parse_top_skip(value).map_err(in_value)?),
+            "$count" => options.count = value.parse().map_err(|_| invalid("true or false", value))?,
+            "$levels" => {
+                let levels = match value {
+                    "max" => Levels::Max,
+                    levels => Levels::Count(
+                        levels
+                            .parse()
+                            .map_err(|_| invalid("a number of levels or 'max'", value))?,
+                    ),
+                };
+                options.levels = Some(levels);
+            }
+            "$expand" => options.expand = parse_items(option, value)?,
+            _ => return Err(invalid("a query option like $filter=...", nested)),
+        }
+    }
+
+    Ok(options)
+}
@@ -0,0 +1,902 @@
+//! The expression tree used by $filter, and the precedence-climbing parser that produces it.
+//!
+//! The grammar follows the OData 4.01 ABNF; operators bind in this order (tightest first):
+//! `has`/`in`, `-`/`not`, `mul`/`div`/`divby`/`mod`, `add`/`sub`, `gt`/`ge`/`lt`/`le`, `eq`/`ne`, `and`, `or`.
+
+use crate::error::{ODataError, ODataResult, SyntaxError};
+use crate::lexer::{tokenize, Token, TokenKind};
+use crate::literal::{format_literal, parse_typed};
+use crate::printer::join;
+use crate::resource::{extract_value, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    /// A primitive literal, e.g. `'Milk'`, `2.55` or `null`
+    Literal(Value),
+    /// A property, optionally reached through complex or navigation properties, e.g. `Address/City`
+    Member(MemberPath),
+    /// A parenthesized list of values, used as the right-hand side of `in`
+    List(Vec<Expression>),
+    /// A call of a canonical function with its arguments, e.g. `contains(Name,'ilk')`
+    Function(Function, Vec<Expression>),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(Box<Expression>, BinaryOperator, Box<Expression>),
+    /// A chain of the same logical operator, e.g. `A or B or C`; a bracketed chain is an operand of its own
+    Logical(LogicalOperator, Vec<Expression>),
+    /// An `any` or `all` operator applied to a collection, e.g. `Emails/any(e:endswith(e,'contoso.com'))`
+    Lambda(Lambda),
+    /// A type check or conversion, e.g. `isof(Ns.Employee)` or `cast(Price,Edm.String)`
+    TypeFunction(TypeFunction),
+}
+
+impl Expression {
+    pub fn member(&self) -> Option<&MemberPath> {
+        match self {
+            Self::Member(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    pub fn literal(&self) -> Option<&Value> {
+        match self {
+            Self::Literal(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Print the expression in the syntax of $filter; brackets are only added where the precedence of the operators
+/// requires them, so parsing the result gives the same expression
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Literal(value) => write!(f, "{}", format_literal(value)),
+            Self::Member(path) => write!(f, "{path}"),
+            Self::List(values) => write!(f, "({})", join(values, ",")),
+            Self::Function(function, arguments) => write!(f, "{function}({})", join(arguments, ",")),
+            Self::Unary(UnaryOperator::Not, operand) => {
+                write!(f, "not ")?;
+                write_operand(f, operand, EQUALITY_PRECEDENCE)
+            }
+            Self::Unary(UnaryOperator::Negate, operand) => {
+                // a number right after the minus would be read as a negative number, e.g. `-5` or `-INF`
+                let printed = operand.to_string();
+                if matches!(**operand, Self::Literal(_))
+                    || printed.starts_with(|c: char| c.is_ascii_digit() || c == '-')
+                {
+                    write!(f, "-({printed})")
+                } else {
+                    write!(f, "-")?;
+                    write_operand(f, operand, PRIMARY_PRECEDENCE)
+                }
+            }
+            Self::Binary(left, operator, right) => {
+                write_infix(f, left, Operator::Binary(*operator), operator.name(), right)
+            }
+            Self::Logical(operator, operands) => {
+                let precedence = Operator::Logical(*operator).precedence();
+                for (pos, operand) in operands.iter().enumerate() {
+                    if pos > 0 {
+                        write!(f, " {} ", operator.name())?;
+                    }
+                    // a bracketed chain of the same operator stays a separate operand
+                    let is_chain = matches!(operand, Self::Logical(inner, _) if inner == operator);
+                    let min_precedence = if pos == 0 && !is_chain {
+                        precedence
+                    } else {
+                        precedence + 1
+                    };
+                    write_operand(f, operand, min_precedence)?;
+                }
+                Ok(())
+            }
+            Self::Lambda(lambda) => {
+                write!(f, "{}/{}(", lambda.path, lambda.operator.name())?;
+                if let Some((variable, predicate)) = &lambda.predicate {
+                    write!(f, "{variable}:{predicate}")?;
+                }
+                write!(f, ")")
+            }
+            Self::TypeFunction(type_function) => {
+                write!(f, "{}(", type_function.function.name())?;
+                if let Some(operand) = &type_function.operand {
+                    write!(f, "{operand},")?;
+                }
+                write!(f, "{})", type_function.type_name)
+            }
+        }
+    }
+}
+
+impl Expression {
+    /// How tightly the expression binds when it is the operand of an operator
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Binary(_, operator, _) => Operator::Binary(*operator).precedence(),
+            Self::Logical(operator, _) => Operator::Logical(*operator).precedence(),
+            // `not` applies to everything up to the next `and` or `or`
+            Self::Unary(UnaryOperator::Not, _) => AND_PRECEDENCE,
+            Self::Unary(UnaryOperator::Negate, _) => MULTIPLICATIVE_PRECEDENCE,
+            _ => PRIMARY_PRECEDENCE + 1,
+        }
+    }
+}
+
+fn write_operand(f: &mut std::fmt::Formatter<'_>, operand: &Expression, min_precedence: u8) -> std::fmt::Result {
+    if operand.precedence() < min_precedence {
+        write!(f, "({operand})")
+    } else {
+        write!(f, "{operand}")
+    }
+}
+
+/// The operators are left associative, so an operand on the right of the same precedence needs brackets
+fn write_infix(
+    f: &mut std::fmt::Formatter<'_>,
+    left: &Expression,
+    operator: Operator,
+    name: &str,
+    right: &Expression,
+) -> std::fmt::Result {
+    let precedence = operator.precedence();
+    write_operand(f, left, precedence)?;
+    write!(f, " {name} ")?;
+    write_operand(f, right, precedence + 1)
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MemberPath(pub Vec<String>);
+
+impl MemberPath {
+    pub fn segments(&self) -> &[String] {
+        &self.0
+    }
+
+    /// The property name when the path consists of a single segment
+    pub fn as_property(&self) -> Option<&str> {
+        match self.0.as_slice() {
+            [property] => Some(property),
+            _ => None,
+        }
+    }
+}
+
+/// A path starting with a reserved word is printed from `$it`, e.g. `$it/null`, as the word by itself would be read as
+/// a literal or operator
+impl std::fmt::Display for MemberPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.first().is_some_and(|first| is_reserved_word(first)) {
+            write!(f, "{IT}/")?;
+        }
+        write!(f, "{}", self.0.join("/"))
+    }
+}
+
+/// A lambda operator applied to a collection-valued path
+///
+/// Inside the predicate, member paths starting with the range variable refer to the current element of the
+/// collection, while paths starting with `$it` refer to the resource the filter is applied to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lambda {
+    pub path: MemberPath,
+    pub operator: LambdaOperator,
+    /// The range variable and the predicate; `any()` without arguments has neither
+    pub predicate: Option<(String, Box<Expression>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LambdaOperator {
+    Any,
+    All,
+}
+
+impl LambdaOperator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Any => "any",
+            Self::All => "all",
+        }
+    }
+}
+
+/// `isof` or `cast` with the qualified name of a type
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeFunction {
+    pub function: TypeFunctionKind,
+    /// The value to check or convert; the resource the filter is applied to, i.e. `$it`, when left out
+    pub operand: Option<Box<Expression>>,
+    /// The qualified name of the type, e.g. `Edm.String` or `Ns.Employee`
+    pub type_name: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeFunctionKind {
+    IsOf,
+    Cast,
+}
+
+impl TypeFunctionKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "isof" => Some(Self::IsOf),
+            "cast" => Some(Self::Cast),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::IsOf => "isof",
+            Self::Cast => "cast",
+        }
+    }
+}
+
+/// The name that refers to the resource the filter is applied to, also from within lambda predicates
+pub const IT: &str = "$it";
+
+/// The words that are read as a literal or operator where a member could be, e.g. `null` or `not`
+const RESERVED_WORDS: [&str; 6] = ["true", "false", "null", "INF", "NaN", "not"];
+
+fn is_reserved_word(name: &str) -> bool {
+    RESERVED_WORDS.contains(&name)
+}
+
+/// The canonical functions that can be used in expressions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    Contains,
+    StartsWith,
+    EndsWith,
+    Length,
+    IndexOf,
+    Substring,
+    ToLower,
+    ToUpper,
+    Trim,
+    Concat,
+    MatchesPattern,
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    FractionalSeconds,
+    Date,
+    Time,
+    TotalOffsetMinutes,
+    Now,
+    MaxDateTime,
+    MinDateTime,
+}
+
+impl Function {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let function = match name {
+            "contains" => Self::Contains,
+            "startswith" => Self::StartsWith,
+            "endswith" => Self::EndsWith,
+            "length" => Self::Length,
+            "indexof" => Self::IndexOf,
+            "substring" => Self::Substring,
+            "tolower" => Self::ToLower,
+            "toupper" => Self::ToUpper,
+            "trim" => Self::Trim,
+            "concat" => Self::Concat,
+            "matchesPattern" => Self::MatchesPattern,
+            "year" => Self::Year,
+            "month" => Self::Month,
+            "day" => Self::Day,
+            "hour" => Self::Hour,
+            "minute" => Self::Minute,
+            "second" => Self::Second,
+            "fractionalseconds" => Self::FractionalSeconds,
+            "date" => Self::Date,
+            "time" => Self::Time,
+            "totaloffsetminutes" => Self::TotalOffsetMinutes,
+            "now" => Self::Now,
+            "maxdatetime" => Self::MaxDateTime,
+            "mindatetime" => Self::MinDateTime,
+            _ => return None,
+        };
+
+        Some(function)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Contains => "contains",
+            Self::StartsWith => "startswith",
+            Self::EndsWith => "endswith",
+            Self::Length => "length",
+            Self::IndexOf => "indexof",
+            Self::Substring => "substring",
+            Self::ToLower => "tolower",
+            Self::ToUpper => "toupper",
+            Self::Trim => "trim",
+            Self::Concat => "concat",
+            Self::MatchesPattern => "matchesPattern",
+            Self::Year => "year",
+            Self::Month => "month",
+            Self::Day => "day",
+            Self::Hour => "hour",
+            Self::Minute => "minute",
+            Self::Second => "second",
+            Self::FractionalSeconds => "fractionalseconds",
+            Self::Date => "date",
+            Self::Time => "time",
+            Self::TotalOffsetMinutes => "totaloffsetminutes",
+            Self::Now => "now",
+            Self::MaxDateTime => "maxdatetime",
+            Self::MinDateTime => "mindatetime",
+        }
+    }
+
+    /// The minimum and maximum number of arguments the function accepts
+    pub fn arity(&self) -> (usize, usize) {
+        match self {
+            Self::Now | Self::MaxDateTime | Self::MinDateTime => (0, 0),
+            Self::Length
+            | Self::ToLower
+            | Self::ToUpper
+            | Self::Trim
+            | Self::Year
+            | Self::Month
+            | Self::Day
+            | Self::Hour
+            | Self::Minute
+            | Self::Second
+            | Self::FractionalSeconds
+            | Self::Date
+            | Self::Time
+            | Self::TotalOffsetMinutes => (1, 1),
+            Self::Contains
+            | Self::StartsWith
+            | Self::EndsWith
+            | Self::IndexOf
+            | Self::Concat
+            | Self::MatchesPattern => (2, 2),
+            Self::Substring => (2, 3),
+        }
+    }
+}
+
+impl std::fmt::Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Not,
+    /// Arithmetic negation, e.g. `-Price`
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Has,
+    In,
+    Add,
+    Sub,
+    Mul,
+    /// Division; integer division when both operands are integers
+    Div,
+    /// Decimal division, also for integer operands
+    DivBy,
+    Mod,
+}
+
+impl BinaryOperator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Eq => "eq",
+            Self::Ne => "ne",
+            Self::Gt => "gt",
+            Self::Ge => "ge",
+            Self::Lt => "lt",
+            Self::Le => "le",
+            Self::Has => "has",
+            Self::In => "in",
+            Self::Add => "add",
+            Self::Sub => "sub",
+            Self::Mul => "mul",
+            Self::Div => "div",
+            Self::DivBy => "divby",
+            Self::Mod => "mod",
+        }
+    }
+
+    pub fn is_arithmetic(&self) -> bool {
+        matches!(
+            self,
+            Self::Add | Self::Sub | Self::Mul | Self::Div | Self::DivBy | Self::Mod
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogicalOperator {
+    And,
+    Or,
+}
+
+impl LogicalOperator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::And => "and",
+            Self::Or => "or",
+        }
+    }
+}
+
+/// Operator precedence; higher binds tighter
+const OR_PRECEDENCE: u8 = 1;
+const AND_PRECEDENCE: u8 = 2;
+const EQUALITY_PRECEDENCE: u8 = 3;
+const RELATIONAL_PRECEDENCE: u8 = 4;
+const ADDITIVE_PRECEDENCE: u8 = 5;
+const MULTIPLICATIVE_PRECEDENCE: u8 = 6;
+const PRIMARY_PRECEDENCE: u8 = 7;
+
+#[derive(Debug, Clone, Copy)]
+enum Operator {
+    Binary(BinaryOperator),
+    Logical(LogicalOperator),
+}
+
+impl Operator {
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        let operator = match keyword {
+            "and" => Self::Logical(LogicalOperator::And),
+            "or" => Self::Logical(LogicalOperator::Or),
+            "eq" => Self::Binary(BinaryOperator::Eq),
+            "ne" => Self::Binary(BinaryOperator::Ne),
+            "gt" => Self::Binary(BinaryOperator::Gt),
+            "ge" => Self::Binary(BinaryOperator::Ge),
+            "lt" => Self::Binary(BinaryOperator::Lt),
+            "le" => Self::Binary(BinaryOperator::Le),
+            "has" => Self::Binary(BinaryOperator::Has),
+            "in" => Self::Binary(BinaryOperator::In),
+            "add" => Self::Binary(BinaryOperator::Add),
+            "sub" => Self::Binary(BinaryOperator::Sub),
+            "mul" => Self::Binary(BinaryOperator::Mul),
+            "div" => Self::Binary(BinaryOperator::Div),
+            "divby" => Self::Binary(BinaryOperator::DivBy),
+            "mod" => Self::Binary(BinaryOperator::Mod),
+            _ => return None,
+        };
+
+        Some(operator)
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Logical(LogicalOperator::Or) => OR_PRECEDENCE,
+            Self::Logical(LogicalOperator::And) => AND_PRECEDENCE,
+            Self::Binary(BinaryOperator::Eq | BinaryOperator::Ne) => EQUALITY_PRECEDENCE,
+            Self::Binary(BinaryOperator::Gt | BinaryOperator::Ge | BinaryOperator::Lt | BinaryOperator::Le) => {
+                RELATIONAL_PRECEDENCE
+            }
+            Self::Binary(BinaryOperator::Add | BinaryOperator::Sub) => ADDITIVE_PRECEDENCE,
+            Self::Binary(BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::DivBy | BinaryOperator::Mod) => {
+                MULTIPLICATIVE_PRECEDENCE
+            }
+            Self::Binary(BinaryOperator::Has | BinaryOperator::In) => PRIMARY_PRECEDENCE,
+        }
+    }
+}
+
+/// Parse the value of a $filter query option into an expression tree
+pub fn parse_filter(value: &str) -> ODataResult<Expression> {
+    let mut parser = Parser::new(value)?;
+    let expression = parser.parse_expression()?;
+    parser.expect_end()?;
+    Ok(expression)
+}
+
+/// How deep expressions may be nested, e.g. by brackets, `not` or a chain of arithmetic or comparison operators, so
+/// that crafted input can't overflow the stack while parsing or when the expression tree is processed. A chain of
+/// `and` or `or` is a single expression, however long it is.
+const MAX_DEPTH: usize = 128;
+
+pub(crate) struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// The length of the input, i.e. the offset of its end
+    end: usize,
+    /// The range variables of the enclosing lambda expressions
+    variables: Vec<String>,
+    /// The number of nested expressions being parsed
+    depth: usize,
+}
+
+impl Parser {
+    pub fn new(input: &str) -> ODataResult<Self> {
+        Ok(Self {
+            tokens: tokenize(input)?,
+            pos: 0,
+            end: input.len(),
+            variables: Vec::new(),
+            depth: 0,
+        })
+    }
+
+    /// Start parsing a nested expression or transformation; must be followed by `leave`
+    pub fn enter(&mut self) -> ODataResult<()> {
+        if self.depth >= MAX_DEPTH {
+            let found = self.peek().map(|token| token.to_string()).unwrap_or_default();
+            return Err(
+                SyntaxError::new(self.offset(), format!("at most {MAX_DEPTH} nested expressions"), found).into(),
+            );
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// The offset of the next token, or of the end of the input
+    pub fn offset(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |token| token.offset)
+    }
+
+    pub fn parse_expression(&mut self) -> ODataResult<Expression> {
+        self.parse_binary(OR_PRECEDENCE)
+    }
+
+    pub fn expect_end(&self) -> ODataResult<()> {
+        match self.tokens.get(self.pos) {
+            None => Ok(()),
+            Some(token) => Err(unexpected(token, "end of expression")),
+        }
+    }
+
+    /// Consume the next token when it is one of the given keywords
+    pub fn next_keyword(&mut self, keywords: &[&str]) -> Option<String> {
+        match self.peek() {
+            Some(TokenKind::Identifier(keyword)) if keywords.contains(&keyword.as_str()) => {
+                let keyword = keyword.clone();
+                self.pos += 1;
+                Some(keyword)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consume the next token when it matches the given kind
+    pub fn next_if(&mut self, kind: &TokenKind) -> bool {
+        if self.peek() == Some(kind) {
+            self.pos += 1;
+            return true;
+        }
+
+        false
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|token| &token.kind)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.pos + offset).map(|token| &token.kind)
+    }
+
+    /// Consume the next token, which must be an identifier, and return its name and offset
+    pub fn next_identifier(&mut self, expected: &str) -> ODataResult<(String, usize)> {
+        let token = self.next(expected)?;
+        match token.kind {
+            TokenKind::Identifier(name) => Ok((name, token.offset)),
+            _ => Err(unexpected(&token, expected)),
+        }
+    }
+
+    fn next(&mut self, expected: &str) -> ODataResult<Token> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| SyntaxError::new(self.end, expected, ""))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    pub fn expect(&mut self, kind: TokenKind, expected: &str) -> ODataResult<()> {
+        let token = self.next(expected)?;
+        if token.kind != kind {
+            return Err(unexpected(&token, expected));
+        }
+
+        Ok(())
+    }
+
+    fn peek_operator(&self) -> Option<Operator> {
+        match self.peek() {
+            Some(TokenKind::Identifier(keyword)) => Operator::from_keyword(keyword),
+            _ => None,
+        }
+    }
+
+    fn parse_binary(&mut self, min_precedence: u8) -> ODataResult<Expression> {
+        self.enter()?;
+        let expression = self.parse_operators(min_precedence);
+        self.leave();
+        expression
+    }
+
+    fn parse_operators(&mut self, min_precedence: u8) -> ODataResult<Expression> {
+        let depth = self.depth;
+        let mut left = self.parse_unary()?;
+        // the logical operator of the chain built by this loop, which the next operands of that operator are added to
+        let mut chain = None;
+
+        while let Some(operator) = self.peek_operator() {
+            let precedence = operator.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+            self.pos += 1;
+
+            left = match operator {
+                Operator::Logical(operator) => {
+                    let right = self.parse_binary(precedence + 1)?;
+                    match left {
+                        Expression::Logical(_, mut operands) if chain == Some(operator) => {
+                            operands.push(right);
+                            Expression::Logical(operator, operands)
+                        }
+                        left => {
+                            chain = Some(operator);
+                            Expression::Logical(operator, vec![left, right])
+                        }
+                    }
+                }
+                // every other operator puts the expression so far one level deeper in the tree
+                Operator::Binary(BinaryOperator::In) => {
+                    self.enter()?;
+                    chain = None;
+                    let right = self.parse_in_operand()?;
+                    Expression::Binary(Box::new(left), BinaryOperator::In, Box::new(right))
+                }
+                Operator::Binary(operator) => {
+                    self.enter()?;
+                    chain = None;
+                    let right = self.parse_binary(precedence + 1)?;
+                    Expression::Binary(Box::new(left), operator, Box::new(right))
+                }
+            };
+        }
+
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> ODataResult<Expression> {
+        if matches!(self.peek(), Some(TokenKind::Identifier(keyword)) if keyword == "not") {
+            self.pos += 1;
+            // `not Name eq 'Milk'` negates the whole comparison, so the operand extends up to `and`/`or`
+            let operand = self.parse_binary(EQUALITY_PRECEDENCE)?;
+            return Ok(Expression::Unary(UnaryOperator::Not, Box::new(operand)));
+        }
+
+        if self.peek() == Some(&TokenKind::Minus) {
+            self.pos += 1;
+            let operand = self.parse_binary(PRIMARY_PRECEDENCE)?;
+            return Ok(Expression::Unary(UnaryOperator::Negate, Box::new(operand)));
+        }
+
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> ODataResult<Expression> {
+        let token = self.next("an operand")?;
+
+        match token.kind {
+            TokenKind::OpenParen => {
+                let expression = self.parse_expression()?;
+                self.expect(TokenKind::CloseParen, "')'")?;
+                Ok(expression)
+            }
+            TokenKind::String(value) => Ok(Expression::Literal(Value::String(value))),
+            TokenKind::Number(value) => match extract_value(&value) {
+                // a number out of the range of Edm.Decimal and Edm.Double isn't taken for anything else
+                Value::String(_) => {
+                    Err(SyntaxError::new(token.offset, "a number in the range of Edm.Decimal", value).into())
+                }
+                Value::Double(number) if number.is_infinite() && !value.ends_with("INF") => {
+                    Err(SyntaxError::new(token.offset, "a number in the range of Edm.Double", value).into())
+                }
+                literal => Ok(Expression::Literal(literal)),
+            },
+            TokenKind::Alias(name) => Ok(Expression::Literal(Value::QueryOption(name))),
+            TokenKind::TypedLiteral(prefix, value) => {
+                parse_typed(&prefix, &value, token.offset).map(Expression::Literal)
+            }
+            TokenKind::Json(value) => Ok(Expression::Literal(Value::Json(value))),
+            TokenKind::Identifier(name) => match name.as_str() {
+                "true" => Ok(Expression::Literal(Value::Boolean(true))),
+                "false" => Ok(Expression::Literal(Value::Boolean(false))),
+                "null" => Ok(Expression::Literal(Value::Null)),
+                _ if self.peek() == Some(&TokenKind::OpenParen) => {
+                    self.pos += 1;
+                    self.parse_function(token.offset, &name)
+                }
+                _ => self.parse_member_path(name),
+            },
+            _ => Err(unexpected(&token, "an operand")),
+        }
+    }
+
+    fn parse_member_path(&mut self, first: String) -> ODataResult<Expression> {
+        let mut segments = vec![first];
+
+        while self.peek() == Some(&TokenKind::Slash) {
+            // after a slash, `INF` and `NaN` are the names of properties rather than numbers
+            let is_segment = match self.peek_at(1) {
+                Some(TokenKind::Identifier(_)) => true,
+                Some(TokenKind::Number(word)) => is_reserved_word(word),
+                _ => false,
+            };
+            if !is_segment {
+                break;
+            }
+            self.pos += 1;
+
+            let token = self.next("a property name")?;
+            let (TokenKind::Identifier(segment) | TokenKind::Number(segment)) = token.kind else {
+                return Err(unexpected(&token, "a property name"));
+            };
+
+            let operator = match segment.as_str() {
+                "any" => Some(LambdaOperator::Any),
+                "all" => Some(LambdaOperator::All),
+                _ => None,
+            };
+            if let Some(operator) = operator.filter(|_| self.peek() == Some(&TokenKind::OpenParen)) {
+                self.pos += 1;
+                return self.parse_lambda(member_path(segments), operator, token.offset);
+            }
+
+            segments.push(segment);
+        }
+
+        Ok(Expression::Member(member_path(segments)))
+    }
+
+    /// Parse the arguments of `any` or `all` after the opening bracket, i.e. `v:predicate)` or `)` for `any()`
+    fn parse_lambda(&mut self, path: MemberPath, operator: LambdaOperator, offset: usize) -> ODataResult<Expression> {
+        if self.next_if(&TokenKind::CloseParen) {
+            if operator == LambdaOperator::All {
+                return Err(SyntaxError::new(offset, "a predicate for 'all'", format!("{path}/all()")).into());
+            }
+            return Ok(Expression::Lambda(Lambda {
+                path,
+                operator,
+                predicate: None,
+            }));
+        }
+
+        let token = self.next("a range variable")?;
+        let TokenKind::Identifier(variable) = token.kind else {
+            return Err(unexpected(&token, "a range variable"));
+        };
+        if variable == IT || is_reserved_word(&variable) || variable.contains('.') || self.variables.contains(&variable)
+        {
+            return Err(SyntaxError::new(token.offset, "an unused range variable", variable).into());
+        }
+        self.expect(TokenKind::Colon, "':'")?;
+
+        self.variables.push(variable.clone());
+        let predicate = self.parse_expression();
+        self.variables.pop();
+        let predicate = predicate?;
+        self.expect(TokenKind::CloseParen, "')'")?;
+
+        Ok(Expression::Lambda(Lambda {
+            path,
+            operator,
+            predicate: Some((variable, Box::new(predicate))),
+        }))
+    }
+
+    fn parse_function(&mut self, offset: usize, name: &str) -> ODataResult<Expression> {
+        if let Some(function) = TypeFunctionKind::from_name(name) {
+            return self.parse_type_function(function);
+        }
+
+        let function =
+            Function::from_name(name).ok_or_else(|| SyntaxError::new(offset, "a canonical function", name))?;
+
+        let arguments = self.parse_arguments()?;
+        let (min, max) = function.arity();
+        if arguments.len() < min || arguments.len() > max {
+            let expected = match min == max {
+                true => format!("{min} arguments for '{name}'"),
+                false => format!("{min} to {max} arguments for '{name}'"),
+            };
+            return Err(SyntaxError::new(offset, expected, format!("{} arguments", arguments.len())).into());
+        }
+
+        Ok(Expression::Function(function, arguments))
+    }
+
+    /// Parse the arguments of `isof` or `cast` after the opening bracket, i.e. `Type)` or `operand,Type)`
+    fn parse_type_function(&mut self, function: TypeFunctionKind) -> ODataResult<Expression> {
+        let operand = match (self.peek(), self.peek_at(1)) {
+            (Some(TokenKind::Identifier(name)), Some(TokenKind::CloseParen)) if name.contains('.') => None,
+            _ => {
+                let operand = self.parse_expression()?;
+                self.expect(TokenKind::Comma, "','")?;
+                Some(Box::new(operand))
+            }
+        };
+
+        let (type_name, offset) = self.next_identifier("a qualified type name")?;
+        if !type_name.contains('.') {
+            return Err(SyntaxError::new(offset, "a qualified type name", type_name).into());
+        }
+        self.expect(TokenKind::CloseParen, "')'")?;
+
+        Ok(Expression::TypeFunction(TypeFunction {
+            function,
+            operand,
+            type_name,
+        }))
+    }
+
+    /// Parse a comma separated list of expressions up to and including the closing bracket
+    fn parse_arguments(&mut self) -> ODataResult<Vec<Expression>> {
+        let mut arguments = Vec::new();
+
+        if self.peek() == Some(&TokenKind::CloseParen) {
+            self.pos += 1;
+            return Ok(arguments);
+        }
+
+        loop {
+            arguments.push(self.parse_expression()?);
+
+            let token = self.next("',' or ')'")?;
+            match token.kind {
+                TokenKind::Comma => continue,
+                TokenKind::CloseParen => return Ok(arguments),
+                _ => return Err(unexpected(&token, "',' or ')'")),
+            }
+        }
+    }
+
+    fn parse_in_operand(&mut self) -> ODataResult<Expression> {
+        if self.peek() == Some(&TokenKind::OpenParen) {
+            self.pos += 1;
+            return Ok(Expression::List(self.parse_arguments()?));
+        }
+
+        self.parse_primary()
+    }
+}
+
+/// `$it/null` is the member `null`, which is printed that way as `null` by itself is the literal
+fn member_path(mut segments: Vec<String>) -> MemberPath {
+    if segments.len() > 1 && segments[0] == IT && is_reserved_word(&segments[1]) {
+        segments.remove(0);
+    }
+    MemberPath(segments)
+}
+
+fn unexpected(token: &Token, expected: &str) -> ODataError {
+    SyntaxError::new(token.offset, expected, token.kind.to_string()).into()
+}
@@ -0,0 +1,89 @@
+//! The invocation of functions and actions in a resource path, e.g. `GetNearestAirport(lat=33,lon=-118)` or
+//! `People('russellwhyte')/Ns.ShareTrip`.
+
+use crate::error::{ODataError, ODataResult, SyntaxError};
+use crate::expression::{parse_filter, Expression};
+use crate::lexer::{is_identifier, offset_in, split_top_level};
+use crate::resource::{extract_value, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    /// The name of the function or action import, or the qualified name of a bound function or action
+    pub name: String,
+    pub kind: InvocationKind,
+    /// Whether the operation is bound to the resource addressed by the preceding segments
+    pub is_bound: bool,
+    /// The parameters by their name, without the binding parameter. The parameters of a function are given in the
+    /// path, the ones of an action in the request body.
+    pub parameters: Vec<(String, Value)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InvocationKind {
+    Function,
+    Action,
+}
+
+impl Invocation {
+    /// Set the parameters of an action from the JSON object of the request body, e.g. `{"userName":"scottketchum"}`
+    pub fn set_body(&mut self, body: &str) -> ODataResult<()> {
+        let invalid = |message: String| ODataError::InvalidInvocation(format!("{message} for '{}'", self.name));
+
+        if self.kind != InvocationKind::Action {
+            return Err(invalid("a request body is not allowed".to_string()));
+        }
+        let body: serde_json::Value =
+            serde_json::from_str(body).map_err(|error| invalid(format!("invalid request body; {error}")))?;
+        let serde_json::Value::Object(members) = body else {
+            return Err(invalid("the request body must be a JSON object".to_string()));
+        };
+
+        self.parameters = members
+            .into_iter()
+            .filter(|(name, _)| !name.starts_with('@'))
+            .map(|(name, value)| (name, json_value(value)))
+            .collect();
+
+        Ok(())
+    }
+}
+
+/// Parse the parameters of a function between the brackets, e.g. `lat=33,lon=-118`; the values are literals,
+/// parameter aliases or JSON. Syntax errors are located in the value.
+pub(crate) fn parse_parameters(value: &str) -> ODataResult<Vec<(String, Value)>> {
+    if value.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut parameters: Vec<(String, Value)> = Vec::new();
+    for part in split_top_level(value, ',') {
+        let Some((parameter, literal)) = part.split_once('=') else {
+            return Err(SyntaxError::new(offset_in(value, part), "a parameter as name=value", part).into());
+        };
+        let parameter = parameter.trim();
+        if !is_identifier(parameter) {
+            return Err(SyntaxError::new(offset_in(value, part), "a parameter name", parameter).into());
+        }
+        if parameters.iter().any(|(name, _)| name == parameter) {
+            return Err(SyntaxError::new(offset_in(value, part), "a parameter given once", parameter).into());
+        }
+        let offset = offset_in(value, literal);
+        match parse_filter(literal).map_err(|error| error.offset_by(offset))? {
+            Expression::Literal(literal) => parameters.push((parameter.to_string(), literal)),
+            _ => return Err(SyntaxError::new(offset, "a literal, parameter alias or JSON", literal.trim()).into()),
+        }
+    }
+
+    Ok(parameters)
+}
+
+/// Primitive JSON values become the matching literal, complex and collection values are kept as JSON
+fn json_value(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(value) => Value::Boolean(value),
+        serde_json::Value::String(value) => Value::String(value),
+        serde_json::Value::Number(number) => extract_value(&number.to_string()),
+        value => Value::Json(value),
+    }
+}
@@ -0,0 +1,310 @@
+//! Tokenizer for OData common expressions, as used by $filter and friends.
+
+use crate::error::{ODataResult, SyntaxError};
+use crate::literal::{literal_word, parse_unquoted};
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+    /// An identifier or keyword; qualified names keep their dots, e.g. `Ns.Color`
+    Identifier(String),
+    /// A single-quoted string with the `''` escapes already removed
+    String(String),
+    /// The raw text of a numeric, date, time or guid literal, e.g. `2.55`, `-1e10`, `2024-01-15` or `INF`
+    Number(String),
+    /// A parameter alias without the leading `@`
+    Alias(String),
+    /// A prefixed string literal like `Ns.Color'Red'`; holds the prefix and the unescaped string
+    TypedLiteral(String, String),
+    /// A JSON array or object, e.g. `["Milk","Bread"]`
+    Json(serde_json::Value),
+    OpenParen,
+    CloseParen,
+    Comma,
+    Slash,
+    Colon,
+    /// A `-` that is not part of a numeric literal, i.e. arithmetic negation
+    Minus,
+}
+
+/// The token as it is written, e.g. in an error message
+impl std::fmt::Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Identifier(name) | Self::Number(name) => write!(f, "{name}"),
+            Self::String(value) => write!(f, "'{}'", value.replace('\'', "''")),
+            Self::Alias(name) => write!(f, "@{name}"),
+            Self::TypedLiteral(prefix, value) => write!(f, "{prefix}'{}'", value.replace('\'', "''")),
+            Self::Json(value) => write!(f, "{value}"),
+            Self::OpenParen => write!(f, "("),
+            Self::CloseParen => write!(f, ")"),
+            Self::Comma => write!(f, ","),
+            Self::Slash => write!(f, "/"),
+            Self::Colon => write!(f, ":"),
+            Self::Minus => write!(f, "-"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Token {
+    pub kind: TokenKind,
+    /// Byte offset of the first character of the token in the input
+    pub offset: usize,
+}
+
+/// Split an expression into tokens; whitespace between tokens is ignored.
+pub(crate) fn tokenize(input: &str) -> ODataResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(offset, c)) = chars.peek() {
+        let kind = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' => single(&mut chars, TokenKind::OpenParen),
+            ')' => single(&mut chars, TokenKind::CloseParen),
+            ',' => single(&mut chars, TokenKind::Comma),
+            '/' => single(&mut chars, TokenKind::Slash),
+            ':' => single(&mut chars, TokenKind::Colon),
+            '\'' => TokenKind::String(read_string(input, &mut chars)?),
+            '@' => {
+                chars.next();
+                let name = read_while(input, &mut chars, is_identifier_char);
+                if name.is_empty() {
+                    return Err(SyntaxError::new(offset, "a parameter alias name", "@").into());
+                }
+                TokenKind::Alias(name.to_string())
+            }
+            '[' | '{' => TokenKind::Json(read_json(input, &mut chars)?),
+            c if (c.is_ascii_alphanumeric() || c == '-')
+                && parse_unquoted(literal_word(&input[offset..])).is_some() =>
+            {
+                let word = literal_word(&input[offset..]);
+                // the word consists of ASCII characters only
+                for _ in 0..word.len() {
+                    chars.next();
+                }
+                TokenKind::Number(word.to_string())
+            }
+            c if c.is_ascii_digit() || (c == '-' && starts_with_digit(&input[offset + 1..])) => {
+                TokenKind::Number(read_number(input, &mut chars).to_string())
+            }
+            '-' => single(&mut chars, TokenKind::Minus),
+            c if is_identifier_start(c) => {
+                // the first character may be a `$`, which isn't allowed further on, e.g. `$it`
+                chars.next();
+                let name = format!("{c}{}", read_while(input, &mut chars, is_identifier_char));
+                if let Some(&(_, '\'')) = chars.peek() {
+                    TokenKind::TypedLiteral(name, read_string(input, &mut chars)?)
+                } else {
+                    TokenKind::Identifier(name)
+                }
+            }
+            c => return Err(SyntaxError::new(offset, "an operand or operator", c).into()),
+        };
+
+        tokens.push(Token { kind, offset });
+    }
+
+    Ok(tokens)
+}
+
+type CharIndices<'i> = std::iter::Peekable<std::str::CharIndices<'i>>;
+
+fn single(chars: &mut CharIndices<'_>, kind: TokenKind) -> TokenKind {
+    chars.next();
+    kind
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Whether the value is a single, possibly qualified, identifier, e.g. `Name` or `Ns.Person`
+pub(crate) fn is_identifier(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with('.')
+        && !value.ends_with('.')
+        && value.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && value.chars().all(is_identifier_char)
+}
+
+fn starts_with_digit(value: &str) -> bool {
+    value.chars().next().is_some_and(|c| c.is_ascii_digit())
+}
+
+/// Consume characters while the predicate holds and return the consumed slice
+fn read_while<'i>(input: &'i str, chars: &mut CharIndices<'_>, predicate: impl Fn(char) -> bool) -> &'i str {
+    let start = chars.peek().map(|(offset, _)| *offset).unwrap_or(input.len());
+    let mut end = start;
+
+    while let Some(&(offset, c)) = chars.peek() {
+        if !predicate(c) {
+            break;
+        }
+        end = offset + c.len_utf8();
+        chars.next();
+    }
+
+    &input[start..end]
+}
+
+fn read_number<'i>(input: &'i str, chars: &mut CharIndices<'_>) -> &'i str {
+    let (start, _) = chars.next().expect("number token must start with a character");
+    let mut end = start + 1;
+    let mut previous = ' ';
+
+    while let Some(&(offset, c)) = chars.peek() {
+        let is_exponent_sign = (c == '+' || c == '-') && (previous == 'e' || previous == 'E');
+        if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || is_exponent_sign) {
+            break;
+        }
+        previous = c;
+        end = offset + 1;
+        chars.next();
+    }
+
+    &input[start..end]
+}
+
+/// Read a single-quoted string, where a quote is escaped by doubling it, e.g. 'O''Neil'
+fn read_string(input: &str, chars: &mut CharIndices<'_>) -> ODataResult<String> {
+    let (start, _) = chars.next().expect("string token must start with a quote");
+    let mut value = String::new();
+
+    while let Some((_, c)) = chars.next() {
+        if c == '\'' {
+            if let Some(&(_, '\'')) = chars.peek() {
+                chars.next();
+                value.push('\'');
+                continue;
+            }
+
+            return Ok(value);
+        }
+
+        value.push(c);
+    }
+
+    Err(SyntaxError::new(start, "a string with a closing quote", &input[start..]).into())
+}
+
+/// Read a JSON array or object up to its matching bracket; brackets within JSON strings don't count
+fn read_json(input: &str, chars: &mut CharIndices<'_>) -> ODataResult<serde_json::Value> {
+    let (start, _) = *chars.peek().expect("JSON token must start with a bracket");
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (offset, c) in chars.by_ref() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '[' | '{' if !in_string => depth += 1,
+            ']' | '}' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return serde_json::from_str(&input[start..=offset]).map_err(|error| {
+                        SyntaxError::new(start, format!("valid JSON ({error})"), &input[start..=offset]).into()
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Err(SyntaxError::new(start, "JSON with a closing bracket", &input[start..]).into())
+}
+
+/// Split a query option value at the separators that are outside of brackets and string literals, e.g.
+/// `Trips($select=Name,Budget),Friends` splits at the second comma only. The parts are trimmed.
+pub(crate) fn split_top_level(input: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut start = 0;
+
+    for (offset, c) in input.char_indices() {
+        match c {
+            '\'' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth = depth.saturating_sub(1),
+            c if c == separator && !in_string && depth == 0 => {
+                parts.push(input[start..offset].trim());
+                start = offset + c.len_utf8();
+            }
+            _ => (),
+        }
+    }
+
+    parts.push(input[start..].trim());
+    parts
+}
+
+/// The offset of a part of a value, as returned by the split functions, in the value it was split from; a part of
+/// a part has its offset in the original value as well
+pub(crate) fn offset_in(value: &str, part: &str) -> usize {
+    (part.as_ptr() as usize).saturating_sub(value.as_ptr() as usize)
+}
+
+/// How deep the brackets of `$select` and `$expand` may be nested
+pub(crate) const MAX_NESTING: usize = 32;
+
+/// Check that the brackets of a value aren't nested deeper than the parser can handle, e.g. for `$expand`
+pub(crate) fn check_nesting(value: &str, max_depth: usize) -> ODataResult<()> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+
+    for (offset, c) in value.char_indices() {
+        match c {
+            '\'' => in_string = !in_string,
+            '(' if !in_string => {
+                depth += 1;
+                if depth > max_depth {
+                    return Err(SyntaxError::new(offset, format!("at most {max_depth} nested brackets"), "(").into());
+                }
+            }
+            ')' if !in_string => depth = depth.saturating_sub(1),
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Split an item with nested query options into its path and the options between the brackets, e.g.
+/// `Trips($top=2)` -> (`Trips`, Some(`$top=2`)); returns None when the brackets are unbalanced
+pub(crate) fn split_nested_options(item: &str) -> Option<(&str, Option<&str>)> {
+    let Some(open) = item.find('(') else {
+        return (!item.contains(')')).then_some((item, None));
+    };
+
+    let options = item[open + 1..].strip_suffix(')')?;
+    is_balanced(options).then_some((item[..open].trim(), Some(options)))
+}
+
+fn is_balanced(input: &str) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+
+    for c in input.chars() {
+        match c {
+            '\'' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => match depth.checked_sub(1) {
+                Some(remaining) => depth = remaining,
+                None => return false,
+            },
+            _ => (),
+        }
+    }
+
+    depth == 0 && !in_string
+}
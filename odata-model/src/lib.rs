@@ -1,5 +1,18 @@
+mod alias;
+pub mod apply;
+pub mod binding;
 pub mod error;
+pub mod expand;
+pub mod expression;
+pub mod invocation;
+mod lexer;
+mod literal;
+mod printer;
+pub mod query;
 pub mod resource;
+mod schema;
+pub mod select;
+pub mod service_root;
 
 pub mod model;
 #[cfg(test)]
@@ -9,7 +22,8 @@ use error::{ODataError, ODataResult};
 use percent_encoding::percent_decode_str;
 use resource::*;
 use serde::{Deserialize, Serialize};
-use std::str::{FromStr, Split};
+use service_root::ServiceRoot;
+use std::str::FromStr;
 use url::Url;
 
 pub struct ODataEndpoint {
@@ -36,10 +50,20 @@ impl ODataEndpoint {
         self.resources = service_document.value.into_iter().map(|value| value.into()).collect();
     }
 
+    /// The root of the service, e.g. `http://services.odata.org/V4/TripPinService/`
+    pub fn service_root(&self) -> ODataResult<ServiceRoot> {
+        ServiceRoot::new(&self.to_string())
+    }
+
+    /// Parse an URL of the service, either absolute or relative to its root, into a resource
     pub fn parse_resource(&self, url: &str) -> ODataResult<ODataResource> {
-        let url_path = url.trim_start_matches(self.to_string().as_str());
-        let resource = ODataResource::try_from(url_path)?;
-        Ok(resource)
+        let service_root = self.service_root()?;
+        ODataResource::parse(url, &ParseOptions::default().with_service_root(&service_root))
+    }
+
+    /// The canonical URL of a resource of the service, which `parse_resource` parses back into the resource
+    pub fn url_for(&self, resource: &ODataResource) -> String {
+        format!("{self}{}", resource.to_relative_url())
     }
 }
 
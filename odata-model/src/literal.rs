@@ -0,0 +1,181 @@
+//! Primitive literals beyond strings and numbers: dates and times, guids, durations, binary values and the
+//! floating point specials.
+
+use base64::alphabet::URL_SAFE;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, SecondsFormat};
+
+use crate::error::{ODataError, ODataResult, SyntaxError};
+use crate::resource::Value;
+
+/// base64url, with or without padding
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &URL_SAFE,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Longer than any unquoted literal, e.g. a DateTimeOffset with nanoseconds and an offset
+const MAX_UNQUOTED_LENGTH: usize = 64;
+
+/// The characters that can make up an unquoted literal, e.g. `2024-01-15T10:30:00.5+01:00`. The scan stops after
+/// `MAX_UNQUOTED_LENGTH` characters, so a long run of them isn't scanned again at every position.
+pub(crate) fn literal_word(input: &str) -> &str {
+    // the characters are ASCII, so the end is always at a character boundary
+    let end = input
+        .bytes()
+        .take(MAX_UNQUOTED_LENGTH + 1)
+        .position(|c| !(c.is_ascii_alphanumeric() || matches!(c, b'.' | b':' | b'+' | b'-')))
+        .unwrap_or(input.len().min(MAX_UNQUOTED_LENGTH + 1));
+    &input[..end]
+}
+
+/// Parse the unquoted literals that aren't plain numbers: Guid, Date, DateTimeOffset, TimeOfDay, `INF`, `-INF` and
+/// `NaN`
+pub(crate) fn parse_unquoted(value: &str) -> Option<Value> {
+    if value.len() > MAX_UNQUOTED_LENGTH {
+        return None;
+    }
+
+    let parsed = match value {
+        "INF" => Value::Double(f64::INFINITY),
+        "-INF" => Value::Double(f64::NEG_INFINITY),
+        "NaN" => Value::Double(f64::NAN),
+        // the hyphenated form only, so plain numbers aren't mistaken for guids
+        _ if value.len() == 36 && value.contains('-') => Value::Guid(uuid::Uuid::parse_str(value).ok()?),
+        _ if value.contains('T') => Value::DateTimeOffset(parse_date_time_offset(value)?),
+        _ if value.contains(':') => Value::TimeOfDay(
+            NaiveTime::parse_from_str(value, "%H:%M:%S%.f")
+                .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+                .ok()?,
+        ),
+        _ => Value::Date(NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?),
+    };
+
+    Some(parsed)
+}
+
+/// Seconds are optional, and the offset is either `Z` or `+hh:mm`
+fn parse_date_time_offset(value: &str) -> Option<DateTime<chrono::FixedOffset>> {
+    DateTime::parse_from_rfc3339(value).ok().or_else(|| {
+        let value = match value.strip_suffix('Z') {
+            Some(value) => format!("{value}+00:00"),
+            None => value.to_string(),
+        };
+        DateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M%:z").ok()
+    })
+}
+
+/// The literal of a value as written in an URL; like `Display`, but strings are quoted, with their quotes doubled
+pub(crate) fn format_literal(value: &Value) -> String {
+    match value {
+        Value::String(value) => format!("'{}'", value.replace('\'', "''")),
+        // without a decimal point, the number would be read as an integer
+        Value::Decimal(value) if value.scale() == 0 => format!("{value}.0"),
+        value => value.to_string(),
+    }
+}
+
+pub(crate) fn format_date_time_offset(value: &DateTime<chrono::FixedOffset>) -> String {
+    value.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// A literal with a type prefix, e.g. `duration'P1D'`, `binary'T0RhdGE'` or the enum literal `Ns.Color'Red'`
+pub(crate) fn parse_typed(prefix: &str, value: &str, offset: usize) -> ODataResult<Value> {
+    let invalid = || {
+        ODataError::from(SyntaxError::new(
+            offset,
+            format!("a valid {prefix} literal"),
+            format!("{prefix}'{value}'"),
+        ))
+    };
+
+    match prefix {
+        "duration" => parse_duration(value).map(Value::Duration).ok_or_else(invalid),
+        "binary" => BASE64.decode(value).map(Value::Binary).map_err(|_| invalid()),
+        _ if prefix.contains('.') => Ok(Value::Enum(prefix.to_string(), value.to_string())),
+        // geography and geometry literals are kept as they are
+        _ => Ok(Value::String(format!("{prefix}'{}'", value.replace('\'', "''")))),
+    }
+}
+
+pub(crate) fn format_binary(value: &[u8]) -> String {
+    BASE64.encode(value)
+}
+
+/// Parse an ISO 8601 duration with days, hours, minutes and seconds, e.g. `P1DT2H30M` or `-PT0.5S`. The duration is
+/// summed up in milliseconds with overflow checks, as it can't exceed `i64::MAX` milliseconds.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value),
+    };
+    let value = value.strip_prefix('P')?;
+    let (days, time) = value.split_once('T').unwrap_or((value, ""));
+    if value.is_empty() || value.ends_with('T') {
+        return None;
+    }
+
+    let mut millis: i64 = 0;
+    if !days.is_empty() {
+        let days: i64 = days.strip_suffix('D')?.parse().ok()?;
+        millis = days.checked_mul(86_400_000)?;
+    }
+
+    let mut rest = time;
+    for (designator, unit_ms) in [('H', 3_600_000.0), ('M', 60_000.0), ('S', 1_000.0)] {
+        if let Some((amount, remaining)) = rest.split_once(designator) {
+            // only seconds can have a fraction
+            if designator != 'S' && amount.contains('.') {
+                return None;
+            }
+            let amount: f64 = amount.parse().ok()?;
+            let amount = (amount * unit_ms).round();
+            // i64::MAX isn't exact as f64, so the bound itself is out of range
+            if !amount.is_finite() || amount.abs() >= i64::MAX as f64 {
+                return None;
+            }
+            millis = millis.checked_add(amount as i64)?;
+            rest = remaining;
+        }
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+
+    let millis = if negative { millis.checked_neg()? } else { millis };
+    Some(Duration::milliseconds(millis))
+}
+
+pub(crate) fn format_duration(value: &Duration) -> String {
+    let sign = if *value < Duration::zero() { "-" } else { "" };
+    let total = value.num_milliseconds().abs();
+    let (days, rest) = (total / 86_400_000, total % 86_400_000);
+    let (hours, rest) = (rest / 3_600_000, rest % 3_600_000);
+    let (minutes, rest) = (rest / 60_000, rest % 60_000);
+    let (seconds, millis) = (rest / 1_000, rest % 1_000);
+
+    let mut formatted = format!("{sign}P");
+    if days > 0 {
+        formatted.push_str(&format!("{days}D"));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || millis > 0 || days == 0 {
+        formatted.push('T');
+        if hours > 0 {
+            formatted.push_str(&format!("{hours}H"));
+        }
+        if minutes > 0 {
+            formatted.push_str(&format!("{minutes}M"));
+        }
+        if millis > 0 {
+            formatted.push_str(&format!("{seconds}.{}S", format!("{millis:03}").trim_end_matches('0')));
+        } else if seconds > 0 || (days == 0 && hours == 0 && minutes == 0) {
+            formatted.push_str(&format!("{seconds}S"));
+        }
+    }
+
+    formatted
+}
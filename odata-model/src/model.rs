@@ -1,12 +1,16 @@
 use odata_edm::edm::{Edmx, EntityType};
 use std::collections::HashMap;
 
-use crate::resource::{Entity, ODataResource};
+use crate::binding::BoundResource;
+use crate::error::ODataResult;
+use crate::resource::{Entity, ODataResource, ParseOptions};
+use crate::service_root::ServiceRoot;
 
 pub struct ODataModel {
     base_url: String,
     resources: HashMap<String, ODataResource>,
     edm: Edmx,
+    key_as_segment: bool,
 }
 
 impl ODataModel {
@@ -15,6 +19,7 @@ impl ODataModel {
             base_url: base_url.into(),
             resources: HashMap::new(),
             edm: Edmx::default(),
+            key_as_segment: false,
         }
     }
 
@@ -32,7 +37,7 @@ impl ODataModel {
     }
 
     fn get_entity_type_by_name(&self, name: &str) -> Option<&EntityType> {
-        if let Some(schema) = self.edm.data_services.schema.get(0) {
+        if let Some(schema) = self.edm.data_services.schema.first() {
             if let Some(entity_type) = schema.entity_type.as_ref() {
                 return entity_type.iter().find(|et| et.name == *name);
             }
@@ -59,6 +64,40 @@ impl ODataModel {
         self
     }
 
+    /// Use the EDM of the service, e.g. as read from its `$metadata`, instead of building it from entity types
+    pub fn with_edm(mut self, edm: Edmx) -> Self {
+        self.edm = edm;
+        self
+    }
+
+    /// Accept keys as a separate segment, e.g. `People/russellwhyte`, besides keys in parentheses
+    pub fn with_key_as_segment(mut self, key_as_segment: bool) -> Self {
+        self.key_as_segment = key_as_segment;
+        self
+    }
+
+    /// The root of the service, from the base URL of the model
+    pub fn service_root(&self) -> ODataResult<ServiceRoot> {
+        ServiceRoot::new(&self.base_url)
+    }
+
+    /// Parse an URL, either absolute or relative to the service root, into a resource, using the EDM of the model to
+    /// interpret the path
+    pub fn parse_resource(&self, url: &str) -> ODataResult<ODataResource> {
+        let service_root = self.service_root()?;
+        let options = ParseOptions::default()
+            .with_edm(&self.edm)
+            .with_key_as_segment(self.key_as_segment)
+            .with_service_root(&service_root);
+        ODataResource::parse(url, &options)
+    }
+
+    /// Check a parsed resource against the EDM of the model, e.g. to answer a request for an unknown property with a
+    /// 400 response
+    pub fn bind(&self, resource: ODataResource) -> ODataResult<BoundResource> {
+        resource.bind(&self.edm)
+    }
+
     pub fn edm(&self) -> &Edmx {
         &self.edm
     }
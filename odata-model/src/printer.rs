@@ -0,0 +1,173 @@
+//! Print a resource as the canonical URL it can be parsed from, e.g.
+//! `People('russellwhyte')/Trips?$filter=Budget%20gt%201000&$top=2`.
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+use crate::invocation::{Invocation, InvocationKind};
+use crate::literal::format_literal;
+use crate::resource::{Entity, Key, ODataFormat, ODataResource, ODataResourceKind, Value};
+
+/// The characters that can't be used as such in a path segment; `/` separates the segments, `\` is read as `/`
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// The characters that can't be used as such in the value of a query option; `+` would be read as a space
+const QUERY_VALUE: &AsciiSet = &SEGMENT.remove(b'/').remove(b'?').add(b'&').add(b'+');
+
+impl ODataResource {
+    /// The canonical URL of the resource, relative to the service root: the path followed by the query options, with
+    /// the parameter aliases replaced by their values. Parsing it gives the same resource.
+    ///
+    /// Without an EDM, a bound action can't be told from a type cast, so it is only parsed back as an action with
+    /// the EDM of the service.
+    pub fn to_relative_url(&self) -> String {
+        let mut url = self.path_segments().join("/");
+
+        let query = self.query_options();
+        if !query.is_empty() {
+            let query: Vec<String> = query
+                .into_iter()
+                .map(|(name, value)| format!("{name}={}", utf8_percent_encode(&value, QUERY_VALUE)))
+                .collect();
+            url.push('?');
+            url.push_str(&query.join("&"));
+        }
+
+        url
+    }
+
+    fn path_segments(&self) -> Vec<String> {
+        let mut segments = Vec::new();
+
+        match &self.kind {
+            ODataResourceKind::ServiceDocument => return segments,
+            ODataResourceKind::CrossJoin(entity_sets) => {
+                segments.push(format!("$crossjoin({})", entity_sets.join(",")))
+            }
+            ODataResourceKind::FunctionImport | ODataResourceKind::ActionImport => {
+                if let Some(import) = &self.invocation {
+                    segments.push(invocation_segment(import));
+                }
+            }
+            _ => entity_segments(&self.entity, &mut segments),
+        }
+
+        for relationship in &self.relationships {
+            entity_segments(relationship, &mut segments);
+        }
+        if let Some(bound) = self.invocation.as_ref().filter(|invocation| invocation.is_bound) {
+            segments.push(invocation_segment(bound));
+        }
+        segments.extend(self.property.clone());
+        if let Some(operation) = &self.operation {
+            segments.push(operation.name().to_string());
+        }
+
+        segments
+            .iter()
+            .map(|segment| utf8_percent_encode(segment, SEGMENT).to_string())
+            .collect()
+    }
+
+    /// The query options in canonical order
+    fn query_options(&self) -> Vec<(&'static str, String)> {
+        let mut options = Vec::new();
+
+        if let Some(filter) = &self.filter {
+            options.push(("$filter", filter.to_string()));
+        }
+        if let Some(search) = &self.search {
+            options.push(("$search", search.clone()));
+        }
+        if !self.order_by.is_empty() {
+            options.push(("$orderby", join(&self.order_by, ",")));
+        }
+        if !self.select.is_empty() {
+            options.push(("$select", join(&self.select, ",")));
+        }
+        if !self.expand.is_empty() {
+            options.push(("$expand", join(&self.expand, ",")));
+        }
+        if !self.apply.is_empty() {
+            options.push(("$apply", join(&self.apply, "/")));
+        }
+        if !self.compute.is_empty() {
+            options.push(("$compute", join(&self.compute, ",")));
+        }
+        if let Some(top) = self.top {
+            options.push(("$top", top.to_string()));
+        }
+        if let Some(skip) = self.skip {
+            options.push(("$skip", skip.to_string()));
+        }
+        if self.count {
+            options.push(("$count", "true".to_string()));
+        }
+        if let Some(id) = &self.id {
+            options.push(("$id", id.clone()));
+        }
+        if self.requested_format != ODataFormat::default() {
+            options.push(("$format", self.requested_format.to_string()));
+        }
+
+        options
+    }
+}
+
+/// The entity with its key, followed by its type cast, e.g. `People('russellwhyte')` and `Ns.Employee`
+fn entity_segments(entity: &Entity, segments: &mut Vec<String>) {
+    match &entity.key {
+        Some(key) => segments.push(format!("{}({})", entity.name, format_key(key))),
+        None => segments.push(entity.name.clone()),
+    }
+    segments.extend(entity.type_cast.clone());
+}
+
+pub(crate) fn format_key(key: &Key) -> String {
+    let values: Vec<String> = key
+        .0
+        .iter()
+        .map(|(name, value)| match name {
+            Some(name) => format!("{name}={}", format_literal(value)),
+            None => format_literal(value),
+        })
+        .collect();
+    values.join(",")
+}
+
+/// Functions are followed by their parameters, actions get theirs from the request body
+fn invocation_segment(invocation: &Invocation) -> String {
+    match invocation.kind {
+        InvocationKind::Function => format!("{}({})", invocation.name, format_named_values(&invocation.parameters)),
+        InvocationKind::Action => invocation.name.clone(),
+    }
+}
+
+fn format_named_values(values: &[(String, Value)]) -> String {
+    let values: Vec<String> = values
+        .iter()
+        .map(|(name, value)| format!("{name}={}", format_literal(value)))
+        .collect();
+    values.join(",")
+}
+
+/// Print the items with the separator in between
+pub(crate) fn join<T: std::fmt::Display>(items: &[T], separator: &str) -> String {
+    let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+    items.join(separator)
+}
@@ -0,0 +1,382 @@
+//! A builder for resources, as an alternative to parsing an URL, e.g.
+//!
+//! ```
+//! use odata_model::query::{prop, ODataQuery};
+//!
+//! let query = ODataQuery::entity_set("People")
+//!     .filter(prop("Age").gt(30).and(prop("FirstName").starts_with("S")))
+//!     .order_by_desc("LastName")
+//!     .top(10)
+//!     .select(["FirstName", "LastName"]);
+//!
+//! assert_eq!(
+//!     query.to_relative_url(),
+//!     "People?$filter=Age%20gt%2030%20and%20startswith(FirstName,'S')&$orderby=LastName%20desc&$select=FirstName,LastName&$top=10"
+//! );
+//! ```
+//!
+//! The resource is the one the parser gives for its URL, so both can be handled the same way.
+
+use crate::expand::{ExpandItem, ExpandOptions, ExpandPath};
+use crate::expression::{BinaryOperator, Expression, Function, LogicalOperator, MemberPath, UnaryOperator};
+use crate::printer::format_key;
+use crate::resource::{extract_entity, Entity, Key, ODataResource, Operation, OrderBy, OrderByDirection, Value};
+use crate::select::SelectItem;
+
+/// A property, optionally reached through complex or navigation properties, e.g. `prop("Address/City")`
+pub fn prop(path: &str) -> Expression {
+    Expression::Member(member_path(path))
+}
+
+/// A literal value, e.g. `lit(30)` or `lit("Milk")`
+pub fn lit(value: impl Into<Value>) -> Expression {
+    Expression::Literal(value.into())
+}
+
+fn member_path(path: &str) -> MemberPath {
+    MemberPath(path.split('/').map(str::to_string).collect())
+}
+
+#[derive(Debug)]
+pub struct ODataQuery {
+    resource: ODataResource,
+}
+
+impl ODataQuery {
+    pub fn entity_set(name: &str) -> Self {
+        Self {
+            resource: ODataResource {
+                entity: entity(name),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Address a single entity of the entity set or of the navigation property before it
+    pub fn key(mut self, key: impl Into<Key>) -> Self {
+        let key = key.into();
+        match &mut self.resource.property {
+            // like the parser, a navigation property at the end of the path is kept as it is written
+            Some(property) => *property = format!("{property}({})", format_key(&key)),
+            None => self.resource.entity.key = Some(key),
+        }
+        self
+    }
+
+    /// Follow a navigation property, or address a property of the entity when it is the last segment
+    pub fn navigate(mut self, property: &str) -> Self {
+        if let Some(previous) = self.resource.property.replace(property.to_string()) {
+            // the parser does the same with a segment that turns out not to be the last one
+            let previous = extract_entity(&previous).unwrap_or_else(|_| entity(&previous));
+            self.resource.relationships.push(previous);
+        }
+        self
+    }
+
+    /// `/$count`: only the number of entities
+    pub fn count(mut self) -> Self {
+        self.resource.operation = Some(Operation::Count);
+        self
+    }
+
+    /// `/$value`: the raw value of the property
+    pub fn value(mut self) -> Self {
+        self.resource.operation = Some(Operation::Value);
+        self
+    }
+
+    /// `$count=true`: the total number of entities along with the entities
+    pub fn inline_count(mut self) -> Self {
+        self.resource.count = true;
+        self
+    }
+
+    /// Restrict the entities to the ones matching the expression; several filters must all match
+    pub fn filter(mut self, expression: Expression) -> Self {
+        self.resource.filter = Some(match self.resource.filter.take() {
+            Some(filter) => filter.and(expression),
+            None => expression,
+        });
+        self
+    }
+
+    pub fn search(mut self, search: &str) -> Self {
+        self.resource.search = Some(search.to_string());
+        self
+    }
+
+    /// Sort ascending on a property, after the sort orders that were added before
+    pub fn order_by(self, property: &str) -> Self {
+        self.order_by_expression(prop(property), OrderByDirection::Asc)
+    }
+
+    /// Sort descending on a property, after the sort orders that were added before
+    pub fn order_by_desc(self, property: &str) -> Self {
+        self.order_by_expression(prop(property), OrderByDirection::Desc)
+    }
+
+    /// Sort on a computed value, e.g. `prop("Price") * prop("Quantity")`
+    pub fn order_by_expression(mut self, expression: Expression, direction: OrderByDirection) -> Self {
+        self.resource.order_by.push(OrderBy { expression, direction });
+        self
+    }
+
+    pub fn top(mut self, top: u32) -> Self {
+        self.resource.top = Some(top);
+        self
+    }
+
+    pub fn skip(mut self, skip: u32) -> Self {
+        self.resource.skip = Some(skip);
+        self
+    }
+
+    /// The properties to return, e.g. `["FirstName", "Address/City"]`; `*` selects all of them
+    pub fn select<S: AsRef<str>>(mut self, properties: impl IntoIterator<Item = S>) -> Self {
+        self.resource
+            .select
+            .extend(properties.into_iter().map(|property| match property.as_ref() {
+                "*" => SelectItem::All,
+                path => SelectItem::Property {
+                    path: member_path(path),
+                    select: Vec::new(),
+                },
+            }));
+        self
+    }
+
+    /// The navigation properties to include the related entities of; `*` includes all of them
+    pub fn expand<S: AsRef<str>>(mut self, properties: impl IntoIterator<Item = S>) -> Self {
+        self.resource
+            .expand
+            .extend(properties.into_iter().map(|property| ExpandItem {
+                path: match property.as_ref() {
+                    "*" => ExpandPath::All,
+                    path => ExpandPath::Navigation(member_path(path)),
+                },
+                reference: false,
+                options: ExpandOptions::default(),
+            }));
+        self
+    }
+
+    /// The canonical URL of the query, relative to the service root
+    pub fn to_relative_url(&self) -> String {
+        self.resource.to_relative_url()
+    }
+
+    pub fn build(self) -> ODataResource {
+        self.resource
+    }
+}
+
+impl From<ODataQuery> for ODataResource {
+    fn from(query: ODataQuery) -> Self {
+        query.build()
+    }
+}
+
+fn entity(name: &str) -> Entity {
+    Entity {
+        name: name.to_string(),
+        key: None,
+        type_cast: None,
+    }
+}
+
+/// Comparisons and functions of expressions, to build filters like
+/// `prop("Age").gt(30).and(prop("Name").equals("Scott"))`. The comparisons are named after the OData operators,
+/// except for `equals` and `not_equals`, as `eq` and `ne` would shadow the ones of `PartialEq`.
+impl Expression {
+    pub fn equals(self, other: impl Into<Expression>) -> Expression {
+        binary(self, BinaryOperator::Eq, other)
+    }
+
+    pub fn not_equals(self, other: impl Into<Expression>) -> Expression {
+        binary(self, BinaryOperator::Ne, other)
+    }
+
+    pub fn gt(self, other: impl Into<Expression>) -> Expression {
+        binary(self, BinaryOperator::Gt, other)
+    }
+
+    pub fn ge(self, other: impl Into<Expression>) -> Expression {
+        binary(self, BinaryOperator::Ge, other)
+    }
+
+    pub fn lt(self, other: impl Into<Expression>) -> Expression {
+        binary(self, BinaryOperator::Lt, other)
+    }
+
+    pub fn le(self, other: impl Into<Expression>) -> Expression {
+        binary(self, BinaryOperator::Le, other)
+    }
+
+    /// Whether the enum value has the flag, e.g. `prop("Style").has(Value::Enum(..))`
+    pub fn has(self, other: impl Into<Expression>) -> Expression {
+        binary(self, BinaryOperator::Has, other)
+    }
+
+    /// Whether the value is one of the values, e.g. `prop("City").is_in(["Berlin", "Paris"])`
+    pub fn is_in<T: Into<Expression>>(self, values: impl IntoIterator<Item = T>) -> Expression {
+        let values = Expression::List(values.into_iter().map(Into::into).collect());
+        binary(self, BinaryOperator::In, values)
+    }
+
+    pub fn and(self, other: Expression) -> Expression {
+        self.chain(LogicalOperator::And, other)
+    }
+
+    pub fn or(self, other: Expression) -> Expression {
+        self.chain(LogicalOperator::Or, other)
+    }
+
+    /// Add the operand to a chain of the same logical operator, e.g. `A and B` followed by `and C`
+    fn chain(self, operator: LogicalOperator, other: Expression) -> Expression {
+        match self {
+            Expression::Logical(chained, mut operands) if chained == operator => {
+                operands.push(other);
+                Expression::Logical(operator, operands)
+            }
+            left => Expression::Logical(operator, vec![left, other]),
+        }
+    }
+
+    pub fn contains(self, other: impl Into<Expression>) -> Expression {
+        Expression::Function(Function::Contains, vec![self, other.into()])
+    }
+
+    pub fn starts_with(self, other: impl Into<Expression>) -> Expression {
+        Expression::Function(Function::StartsWith, vec![self, other.into()])
+    }
+
+    pub fn ends_with(self, other: impl Into<Expression>) -> Expression {
+        Expression::Function(Function::EndsWith, vec![self, other.into()])
+    }
+}
+
+fn binary(left: Expression, operator: BinaryOperator, right: impl Into<Expression>) -> Expression {
+    Expression::Binary(Box::new(left), operator, Box::new(right.into()))
+}
+
+macro_rules! arithmetic_operator {
+    ($trait:ident, $method:ident, $operator:expr) => {
+        impl<T: Into<Expression>> std::ops::$trait<T> for Expression {
+            type Output = Expression;
+
+            fn $method(self, other: T) -> Expression {
+                binary(self, $operator, other)
+            }
+        }
+    };
+}
+
+arithmetic_operator!(Add, add, BinaryOperator::Add);
+arithmetic_operator!(Sub, sub, BinaryOperator::Sub);
+arithmetic_operator!(Mul, mul, BinaryOperator::Mul);
+arithmetic_operator!(Div, div, BinaryOperator::Div);
+arithmetic_operator!(Rem, rem, BinaryOperator::Mod);
+
+impl std::ops::Neg for Expression {
+    type Output = Expression;
+
+    fn neg(self) -> Expression {
+        Expression::Unary(UnaryOperator::Negate, Box::new(self))
+    }
+}
+
+impl std::ops::Not for Expression {
+    type Output = Expression;
+
+    fn not(self) -> Expression {
+        Expression::Unary(UnaryOperator::Not, Box::new(self))
+    }
+}
+
+macro_rules! literal_conversions {
+    ($($type:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl From<$type> for Value {
+                fn from(value: $type) -> Self {
+                    Value::$variant(value.into())
+                }
+            }
+
+            impl From<$type> for Expression {
+                fn from(value: $type) -> Self {
+                    Expression::Literal(Value::from(value))
+                }
+            }
+        )*
+    };
+}
+
+literal_conversions!(
+    &str => String,
+    String => String,
+    i32 => Integer,
+    bool => Boolean,
+    f64 => Double,
+    rust_decimal::Decimal => Decimal,
+    uuid::Uuid => Guid,
+    chrono::NaiveDate => Date,
+);
+
+/// Like the parser, integers that fit into an Edm.Int32 are `Integer` values, and only larger ones `Int64` values
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        match i32::try_from(value) {
+            Ok(value) => Value::Integer(value),
+            Err(_) => Value::Int64(value),
+        }
+    }
+}
+
+impl From<i64> for Expression {
+    fn from(value: i64) -> Self {
+        Expression::Literal(Value::from(value))
+    }
+}
+
+impl From<Value> for Expression {
+    fn from(value: Value) -> Self {
+        Expression::Literal(value)
+    }
+}
+
+impl From<&str> for Key {
+    fn from(value: &str) -> Self {
+        Key::single(value)
+    }
+}
+
+impl From<String> for Key {
+    fn from(value: String) -> Self {
+        Key::single(value)
+    }
+}
+
+impl From<i32> for Key {
+    fn from(value: i32) -> Self {
+        Key::single(value)
+    }
+}
+
+impl From<i64> for Key {
+    fn from(value: i64) -> Self {
+        Key::single(value)
+    }
+}
+
+impl From<Value> for Key {
+    fn from(value: Value) -> Self {
+        Key::single(value)
+    }
+}
+
+/// A key of several properties, e.g. `[("OrderId", 1), ("ItemNo", 2)]`
+impl<N: Into<String>, V: Into<Value>, const L: usize> From<[(N, V); L]> for Key {
+    fn from(values: [(N, V); L]) -> Self {
+        Key::named(values)
+    }
+}
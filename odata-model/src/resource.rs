@@ -1,11 +1,23 @@
-use std::borrow::Cow;
-
 use http::Uri;
-use odata_edm::edm::EntityType;
+use odata_edm::edm::{Edmx, EntityType};
 
 use super::*;
+use crate::alias::ParameterAliases;
+use crate::apply::{parse_apply, parse_compute, Compute, Transformation};
+use crate::error::SyntaxError;
+use crate::expand::{parse_expand, ExpandItem};
+use crate::expression::{parse_filter, Expression, Parser};
+use crate::invocation::{parse_parameters, Invocation, InvocationKind};
+use crate::lexer::{is_identifier, offset_in, split_nested_options, split_top_level, TokenKind};
+use crate::literal::{format_binary, format_date_time_offset, format_duration, parse_unquoted};
+use crate::schema::{
+    entity_set_type, find_entity_type, find_navigation_property, find_operation, find_operation_import, find_property,
+    key_properties, navigation_target,
+};
+use crate::select::{parse_select, SelectItem};
+use crate::service_root::ServiceRoot;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ODataResource {
     pub entity: Entity,
     pub kind: ODataResourceKind,
@@ -14,16 +26,39 @@ pub struct ODataResource {
     pub property: Option<String>,
     pub operation: Option<Operation>,
     pub relationships: Vec<Entity>,
+    /// The function or action invoked on the resource, or the function or action import the resource consists of
+    /// Example: People('russellwhyte')/Ns.GetFriendsTrips(userName='scottketchum')
+    pub invocation: Option<Invocation>,
+    /// The id of the entity addressed by `$entity`, or of the reference to remove with `$ref`
+    /// Example: $entity?$id=People('russellwhyte')
+    pub id: Option<String>,
     pub search: Option<String>,
-    pub filters: Filters,
+    /// The parsed $filter expression
+    /// Example: $filter=Name eq 'Milk' and Price lt 2.55
+    pub filter: Option<Expression>,
     /// The requested format; defaults to application/json when not set
     pub requested_format: ODataFormat,
     pub top: Option<u32>,
     pub skip: Option<u32>,
+    /// Whether the total number of entities is requested along with the entities, i.e. `$count=true`; unlike the
+    /// `/$count` path segment, which returns only the number
+    pub count: bool,
     /// The sort order; defaults to ascending
     /// Example: $orderby=Name desc,Price asc
     /// Note: the order of the sort order is important; the first field is the primary sort order, the second field is the secondary sort order, etc.
     pub order_by: Vec<OrderBy>,
+    /// The properties to return; all structural properties when empty
+    /// Example: $select=Name,Address/City
+    pub select: Vec<SelectItem>,
+    /// The related entities to include, each with their own query options
+    /// Example: $expand=Trips($select=Name;$top=2),Friends/$ref
+    pub expand: Vec<ExpandItem>,
+    /// The transformations of the Data Aggregation extension, applied in order
+    /// Example: $apply=filter(Amount gt 0)/groupby((Category),aggregate(Amount with sum as Total))
+    pub apply: Vec<Transformation>,
+    /// Computed properties, which can be used in $select, $filter and $orderby by their alias
+    /// Example: $compute=Price mul Quantity as Total
+    pub compute: Vec<Compute>,
 }
 
 impl Default for ODataResource {
@@ -32,6 +67,7 @@ impl Default for ODataResource {
             entity: Entity {
                 name: String::new(),
                 key: None,
+                type_cast: None,
             },
             kind: ODataResourceKind::EntitySet,
             url: String::new(),
@@ -39,70 +75,185 @@ impl Default for ODataResource {
             property: None,
             operation: None,
             relationships: Vec::new(),
+            invocation: None,
+            id: None,
             search: None,
-            filters: Filters::default(),
+            filter: None,
             requested_format: ODataFormat::default(),
             top: None,
             skip: None,
+            count: false,
             order_by: Vec::new(),
+            select: Vec::new(),
+            expand: Vec::new(),
+            apply: Vec::new(),
+            compute: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct OrderBy {
-    pub field: String,
+    /// The expression to sort on; usually a property, but computed values like `Price mul Quantity` are allowed
+    pub expression: Expression,
     pub direction: OrderByDirection,
 }
 
-#[derive(Debug, PartialEq, Default)]
+impl std::fmt::Display for OrderBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.direction {
+            OrderByDirection::Asc => write!(f, "{}", self.expression),
+            OrderByDirection::Desc => write!(f, "{} desc", self.expression),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum OrderByDirection {
     #[default]
     Asc,
     Desc,
 }
 
-#[derive(Debug, Default)]
-pub struct Filters(pub Vec<(FieldFilter, Option<Chain>)>);
+#[derive(Debug, Default, PartialEq)]
+pub enum ODataResourceKind {
+    #[default]
+    EntitySet,
+    Singleton,
+    FunctionImport,
+    ActionImport,
+    ServiceDocument,
+    /// `$metadata`, the EDM of the service
+    Metadata,
+    /// `$batch`, several requests combined in a single request
+    Batch,
+    /// `$all`, the entities of all entity sets, usually restricted by `$search` or `$filter`
+    All,
+    /// `$entity`, the entity identified by the `$id` query option
+    EntityId,
+    /// `$crossjoin(Products,Sales)`, the combinations of the entities of several entity sets
+    CrossJoin(Vec<String>),
+}
 
-impl Filters {
-    pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+/// The key of an entity: its values by the names of the key properties, e.g. `OrderItems(OrderId=1,ItemNo=2)`. A
+/// single value may leave out the name, e.g. `People('russellwhyte')` or `Orders(3000000000)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key(pub Vec<(Option<String>, Value)>);
+
+impl Key {
+    /// A key of a single value without the name of its property, e.g. `'russellwhyte'`
+    pub fn single(value: impl Into<Value>) -> Self {
+        Self(vec![(None, value.into())])
     }
 
-    pub fn len(&self) -> usize {
-        self.0.len()
+    /// A key of values by the names of their properties, e.g. `[("OrderId", 1), ("ItemNo", 2)]`
+    pub fn named<N: Into<String>, V: Into<Value>>(values: impl IntoIterator<Item = (N, V)>) -> Self {
+        Self(
+            values
+                .into_iter()
+                .map(|(name, value)| (Some(name.into()), value.into()))
+                .collect(),
+        )
     }
 
-    pub fn contents(&self) -> &[(FieldFilter, Option<Chain>)] {
-        &self.0
+    /// The value of a key of a single value, with or without its name
+    pub fn single_value(&self) -> Option<&Value> {
+        match self.0.as_slice() {
+            [(_, value)] => Some(value),
+            _ => None,
+        }
     }
 
-    pub fn iter(&self) -> std::slice::Iter<'_, (FieldFilter, Option<Chain>)> {
-        self.0.iter()
+    /// The key values by the names of the key properties of the entity type, in the order of its `Key`. The names and
+    /// the types of the values must match the key properties; a key without a name is allowed for a single key property.
+    pub fn named_values(&self, entity_type: &EntityType) -> ODataResult<Vec<(String, Value)>> {
+        let key_properties: Vec<&str> = entity_type
+            .key
+            .iter()
+            .flatten()
+            .flat_map(|key| key.property_ref.iter().flatten())
+            .map(|property_ref| property_ref.name.as_str())
+            .collect();
+        let invalid = |message: String| ODataError::InvalidKey(format!("{message} for '{}'", entity_type.name));
+
+        let values: Vec<(String, Value)> = match self.0.as_slice() {
+            [(None, value)] => {
+                let [property] = key_properties.as_slice() else {
+                    return Err(invalid(format!(
+                        "expected the key properties {}",
+                        key_properties.join(",")
+                    )));
+                };
+                vec![(property.to_string(), value.clone())]
+            }
+            values => values
+                .iter()
+                .map(|(name, value)| match name {
+                    Some(name) => Ok((name.clone(), value.clone())),
+                    None => Err(invalid(format!("expected the name of the key property for '{value}'"))),
+                })
+                .collect::<ODataResult<_>>()?,
+        };
+
+        let mut named_values = Vec::new();
+        for property in &key_properties {
+            let Some((name, value)) = values.iter().find(|(name, _)| name == property) else {
+                return Err(invalid(format!("missing key property '{property}'")));
+            };
+
+            let edm_type = entity_type
+                .property
+                .iter()
+                .flatten()
+                .find(|p| p.name == *name)
+                .map(|p| p._type.as_str());
+            if let Some(edm_type) = edm_type.filter(|edm_type| !is_compatible(value, edm_type)) {
+                return Err(invalid(format!("'{value}' is not a valid {edm_type} for '{name}'")));
+            }
+
+            named_values.push((name.clone(), value.clone()));
+        }
+
+        if let Some((name, _)) = values.iter().find(|(name, _)| !key_properties.contains(&name.as_str())) {
+            return Err(invalid(format!("'{name}' is not a key property")));
+        }
+        if named_values.len() != values.len() {
+            return Err(invalid("duplicate key properties".to_string()));
+        }
+
+        Ok(named_values)
     }
 }
 
-#[derive(Debug, Default)]
-pub enum ODataResourceKind {
-    #[default]
-    EntitySet,
-    Singleton,
-    FunctionImport,
-    ServiceDocument,
+/// Whether a literal can be the value of a property of the given type; types other than the primitive types, e.g.
+/// type definitions, aren't checked
+pub(crate) fn is_compatible(value: &Value, edm_type: &str) -> bool {
+    match edm_type {
+        "Edm.String" => matches!(value, Value::String(_)),
+        "Edm.Byte" | "Edm.SByte" | "Edm.Int16" | "Edm.Int32" => matches!(value, Value::Integer(_)),
+        "Edm.Int64" => matches!(value, Value::Integer(_) | Value::Int64(_)),
+        "Edm.Decimal" => matches!(value, Value::Integer(_) | Value::Int64(_) | Value::Decimal(_)),
+        "Edm.Double" | "Edm.Single" => matches!(
+            value,
+            Value::Integer(_) | Value::Int64(_) | Value::Decimal(_) | Value::Double(_)
+        ),
+        "Edm.Boolean" => matches!(value, Value::Boolean(_)),
+        "Edm.Guid" => matches!(value, Value::Guid(_)),
+        "Edm.Date" => matches!(value, Value::Date(_)),
+        "Edm.DateTimeOffset" => matches!(value, Value::DateTimeOffset(_)),
+        "Edm.TimeOfDay" => matches!(value, Value::TimeOfDay(_)),
+        "Edm.Duration" => matches!(value, Value::Duration(_)),
+        "Edm.Binary" => matches!(value, Value::Binary(_)),
+        _ => true,
+    }
 }
 
 #[derive(Debug, PartialEq)]
-pub enum Key {
-    String(String),
-    Number(i32),
-    KeyValue((String, Value)),
-}
-
-#[derive(Debug)]
 pub struct Entity {
     pub name: String,
     pub key: Option<Key>,
+    /// The qualified name of a derived type the entities are restricted to, e.g. `People/Ns.Employee`
+    pub type_cast: Option<String>,
 }
 
 impl From<&EntityType> for Entity {
@@ -110,6 +261,7 @@ impl From<&EntityType> for Entity {
         Self {
             name: value.name.clone(),
             key: None,
+            type_cast: None,
         }
     }
 }
@@ -117,44 +269,18 @@ impl From<&EntityType> for Entity {
 impl std::fmt::Display for Entity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self.key {
-            Some(key) => write!(f, "{}({})", self.name, key),
-            None => write!(f, "{}", self.name),
-        }
-    }
-}
-
-#[derive(Debug)]
-pub enum FieldFilter {
-    // The field and the operation
-    Contents(FieldFilterContents),
-    // A nested filter; the bool indicates whether the filter is negated
-    Nested((bool, Filters)),
-}
-
-#[derive(Debug)]
-pub struct FieldFilterContents {
-    pub not: bool,
-    pub field: String,
-    pub operation: FilterOperation,
-}
-
-impl FieldFilter {
-    pub fn contents(&self) -> Option<&FieldFilterContents> {
-        match &self {
-            Self::Contents(operation) => Some(operation),
-            _ => None,
+            Some(key) => write!(f, "{}({})", self.name, key)?,
+            None => write!(f, "{}", self.name)?,
         }
-    }
 
-    pub fn nested(&self) -> Option<&(bool, Filters)> {
-        match &self {
-            Self::Nested(nested) => Some(nested),
-            _ => None,
+        match &self.type_cast {
+            Some(type_cast) => write!(f, "/{}", type_cast),
+            None => Ok(()),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ODataFormat {
     pub format: String,
     pub metadata: ODataMetaData,
@@ -176,13 +302,16 @@ impl From<&str> for ODataFormat {
         let mut result = Self::default();
         let parts = value.split(';');
         for part in parts {
-            if let Some((key, value)) = part.split_once('=') {
-                match key {
-                    "metadata" | "odata.metadata" => result.metadata = ODataMetaData::from(value),
-                    "streaming" | "odata.streaming" => result.streaming = bool::from_str(value).unwrap_or(false),
-                    "format" => result.format = value.to_string(),
-                    _ => (),
+            match part.split_once('=') {
+                Some(("metadata" | "odata.metadata", value)) => result.metadata = ODataMetaData::from(value),
+                Some(("streaming" | "odata.streaming", value)) => {
+                    result.streaming = bool::from_str(value).unwrap_or(false)
                 }
+                Some(("format", value)) => result.format = value.to_string(),
+                Some(_) => (),
+                // the media type itself, e.g. `application/xml;odata.metadata=full`
+                None if !part.trim().is_empty() => result.format = part.trim().to_string(),
+                None => (),
             }
         }
 
@@ -190,6 +319,20 @@ impl From<&str> for ODataFormat {
     }
 }
 
+/// The value of the $format query option, with the parameters that differ from the defaults
+impl std::fmt::Display for ODataFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.format)?;
+        if self.metadata != ODataMetaData::default() {
+            write!(f, ";odata.metadata={}", self.metadata.name())?;
+        }
+        if self.streaming {
+            write!(f, ";odata.streaming=true")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq, Default)]
 pub enum ODataMetaData {
     None,
@@ -198,6 +341,16 @@ pub enum ODataMetaData {
     Full,
 }
 
+impl ODataMetaData {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Minimal => "minimal",
+            Self::Full => "full",
+        }
+    }
+}
+
 impl From<&str> for ODataMetaData {
     fn from(value: &str) -> Self {
         match value {
@@ -209,30 +362,25 @@ impl From<&str> for ODataMetaData {
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum FilterOperation {
-    Eq(Value),
-    Ne(Value),
-    Gt(Value),
-    Ge(Value),
-    Lt(Value),
-    Le(Value),
-    In(Vec<Value>),
-    Has(String),
-    Function(String),
-}
-
-#[derive(Debug, PartialEq)]
-pub enum Chain {
-    And,
-    Or,
-}
-
 #[derive(Debug, PartialEq)]
 pub enum Operation {
     Count,
     Value,
     All,
+    /// The references to the entities instead of the entities, e.g. `People('russellwhyte')/Friends/$ref`
+    Ref,
+}
+
+impl Operation {
+    /// The path segment of the operation, e.g. `$count`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Count => "$count",
+            Self::Value => "$value",
+            Self::All => "$all",
+            Self::Ref => "$ref",
+        }
+    }
 }
 
 impl TryFrom<&str> for Operation {
@@ -243,6 +391,7 @@ impl TryFrom<&str> for Operation {
             "$count" => Ok(Self::Count),
             "$value" => Ok(Self::Value),
             "$all" => Ok(Self::All),
+            "$ref" => Ok(Self::Ref),
             _ => Err(ODataError::InvalidOperation),
         }
     }
@@ -250,22 +399,43 @@ impl TryFrom<&str> for Operation {
 
 impl std::fmt::Display for Key {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Key::String(value) => write!(f, "{}", value),
-            Key::Number(value) => write!(f, "{}", value),
-            Key::KeyValue((name, value)) => write!(f, "{}={}", name, value),
-        }
+        let values: Vec<String> = self
+            .0
+            .iter()
+            .map(|(name, value)| match name {
+                Some(name) => format!("{name}={value}"),
+                None => value.to_string(),
+            })
+            .collect();
+        write!(f, "{}", values.join(","))
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Null,
     String(String),
     Integer(i32),
+    /// An integer that doesn't fit in 32 bits
+    Int64(i64),
     Boolean(bool),
     Decimal(rust_decimal::Decimal),
+    /// A number in exponent notation, e.g. `1.5e10`, or one of `INF`, `-INF` and `NaN`
+    Double(f64),
+    Guid(uuid::Uuid),
+    Date(chrono::NaiveDate),
+    DateTimeOffset(chrono::DateTime<chrono::FixedOffset>),
+    TimeOfDay(chrono::NaiveTime),
+    /// e.g. `duration'P1DT2H'`
+    Duration(chrono::Duration),
+    /// e.g. `binary'T0RhdGE'`, where the value is base64url encoded
+    Binary(Vec<u8>),
+    /// The qualified name of the enum type and the member(s), e.g. `Ns.Color'Red'` or `Ns.Color'Red,Blue'`
+    Enum(String, String),
+    /// A parameter alias without the leading `@`, until it is replaced by its value
     QueryOption(String),
+    /// A complex or collection value, e.g. `["Milk","Bread"]`
+    Json(serde_json::Value),
 }
 
 impl std::fmt::Display for Value {
@@ -274,95 +444,155 @@ impl std::fmt::Display for Value {
             Value::Null => write!(f, "null"),
             Value::String(value) => write!(f, "{}", value),
             Value::Integer(value) => write!(f, "{}", value),
+            Value::Int64(value) => write!(f, "{}", value),
             Value::Boolean(value) => write!(f, "{}", value),
             Value::QueryOption(value) => write!(f, "@{}", value),
             Value::Decimal(value) => write!(f, "{}", value),
+            Value::Double(value) if value.is_nan() => write!(f, "NaN"),
+            Value::Double(value) if value.is_infinite() => write!(f, "{}INF", if *value < 0.0 { "-" } else { "" }),
+            Value::Double(value) => write!(f, "{:E}", value),
+            Value::Guid(value) => write!(f, "{}", value),
+            Value::Date(value) => write!(f, "{}", value),
+            Value::DateTimeOffset(value) => write!(f, "{}", format_date_time_offset(value)),
+            Value::TimeOfDay(value) => write!(f, "{}", value),
+            Value::Duration(value) => write!(f, "duration'{}'", format_duration(value)),
+            Value::Binary(value) => write!(f, "binary'{}'", format_binary(value)),
+            Value::Enum(type_name, value) => write!(f, "{}'{}'", type_name, value.replace('\'', "''")),
+            Value::Json(value) => write!(f, "{}", value),
         }
     }
 }
 
-const PARSE_PREFIX: &str = "http://services.odata.org/V4/TripPinService/";
+/// Options for interpreting the path of a resource
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions<'e> {
+    /// Whether entities can be addressed with their key as a separate segment, e.g. `People/russellwhyte` instead of
+    /// `People('russellwhyte')`. Needs the EDM to tell a key from a property.
+    pub key_as_segment: bool,
+    /// The EDM of the service
+    pub edm: Option<&'e Edmx>,
+    /// The root of the service, which the URL is resolved against. Without it, the URL is taken relative to the root
+    /// of the TripPin sample service, even when it starts with a `/`.
+    pub service_root: Option<&'e ServiceRoot>,
+}
 
-/// Try to create a resource from the path of an URL. The path is expected to start with the name of the resource.
-/// For example: People('russellwhyte')/FirstName
-impl TryFrom<&str> for ODataResource {
-    type Error = ODataError;
+impl<'e> ParseOptions<'e> {
+    pub fn with_edm(mut self, edm: &'e Edmx) -> Self {
+        self.edm = Some(edm);
+        self
+    }
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let value = value.trim_start_matches('/');
-        let value = format!("{PARSE_PREFIX}{value}");
-        let url = Url::parse(&value)?;
-        let mut result = match parse_path(&url, value) {
-            Ok(value) => value,
-            Err(err) => return Err(err),
+    pub fn with_key_as_segment(mut self, key_as_segment: bool) -> Self {
+        self.key_as_segment = key_as_segment;
+        self
+    }
+
+    pub fn with_service_root(mut self, service_root: &'e ServiceRoot) -> Self {
+        self.service_root = Some(service_root);
+        self
+    }
+}
+
+impl ODataResource {
+    /// Create a resource from an URL, like `try_from`, with the given options.
+    /// For example: People/russellwhyte/FirstName, with the key as a segment, or
+    /// /api/odata/v4/tenant/People('russellwhyte') with the service root `/api/odata/v4/tenant`
+    pub fn parse(value: &str, options: &ParseOptions) -> ODataResult<Self> {
+        let (url, path) = match options.service_root {
+            Some(service_root) => service_root.resolve(value)?,
+            // a path from the host is below the root of the TripPin service, otherwise it starts with the resource
+            None => {
+                let service_root = ServiceRoot::default();
+                match service_root.resolve(value) {
+                    Ok(resolved) => resolved,
+                    Err(_) => service_root.resolve(value.trim_start_matches('/'))?,
+                }
+            }
         };
+        let mut result = parse_path(&path, url.to_string(), options)?;
+        let decoded_path = percent_decode_str(&path).decode_utf8_lossy();
+        let mut aliases = ParameterAliases::new(&decoded_path);
 
         for (key, value) in url.query_pairs() {
-            if key == "$search" {
-                result.search = Some(value.to_string());
+            if let Some(name) = key.strip_prefix('@') {
+                aliases.insert(name, value.as_ref());
                 continue;
             }
+            aliases.insert_option(&key, &value);
 
-            if key == "$filter" {
-                result.filters = parse_filter(value.as_ref())?;
-                continue;
-            }
+            result
+                .apply_query_option(&key, &value)
+                .map_err(|error| error.in_option(&key))?;
+        }
 
-            if key == "$format" {
-                result.requested_format = value.as_ref().into();
-                continue;
-            }
+        if result.kind == ODataResourceKind::EntityId && result.id.is_none() {
+            return Err(SyntaxError::new(decoded_path.len(), "the $id query option for $entity", "").into());
+        }
 
-            if key == "$top" {
-                result.top = Some(
-                    value
-                        .as_ref()
-                        .parse::<u32>()
-                        .map_err(|_| ODataError::InvalidQueryTopSkip)?,
-                );
-                continue;
-            }
+        aliases.resolve_resource(&mut result)?;
 
-            if key == "$skip" {
-                result.skip = Some(
-                    value
-                        .as_ref()
-                        .parse::<u32>()
-                        .map_err(|_| ODataError::InvalidQueryTopSkip)?,
-                );
-                continue;
-            }
+        Ok(result)
+    }
+}
 
-            if key == "$orderby" {
-                result.order_by = parse_sort_order(value.as_ref())?;
-            }
+impl ODataResource {
+    /// Set the query option; unknown options are ignored
+    fn apply_query_option(&mut self, key: &str, value: &str) -> ODataResult<()> {
+        match key {
+            "$id" => self.id = Some(value.to_string()),
+            "$search" => self.search = Some(value.to_string()),
+            "$filter" => self.filter = Some(parse_filter(value)?),
+            "$format" => self.requested_format = value.into(),
+            "$top" => self.top = Some(parse_top_skip(value)?),
+            "$skip" => self.skip = Some(parse_top_skip(value)?),
+            "$count" => self.count = value.parse().map_err(|_| SyntaxError::new(0, "true or false", value))?,
+            "$orderby" => self.order_by = parse_sort_order(value)?,
+            "$select" => self.select = parse_select(value)?,
+            "$expand" => self.expand = parse_expand(value)?,
+            "$apply" => self.apply = parse_apply(value)?,
+            "$compute" => self.compute = parse_compute(value)?,
+            _ => (),
         }
 
-        Ok(result)
+        Ok(())
     }
 }
 
-/// parse the OData 4 $orderby query option
-fn parse_sort_order(value: &str) -> ODataResult<Vec<OrderBy>> {
-    let mut order_by = Vec::new();
-    let parts = value.split(',');
+/// Try to create a resource from the path of an URL. The path is expected to start with the name of the resource, or
+/// be below the root of the TripPin sample service. For example: People('russellwhyte')/FirstName
+impl TryFrom<&str> for ODataResource {
+    type Error = ODataError;
 
-    for part in parts {
-        let mut part = part.split(' ');
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::parse(value, &ParseOptions::default())
+    }
+}
 
-        let field = part.next().ok_or(error::ODataError::IncompletePath)?;
-        let direction = part.next().unwrap_or("asc");
+pub(crate) fn parse_top_skip(value: &str) -> ODataResult<u32> {
+    value
+        .parse::<u32>()
+        .map_err(|_| SyntaxError::new(0, "a non-negative integer", value).into())
+}
 
-        let direction = match direction {
-            "asc" => OrderByDirection::Asc,
-            "desc" => OrderByDirection::Desc,
-            _ => return Err(error::ODataError::InvalidQueryOrderBy),
+/// parse the OData 4 $orderby query option, e.g. `Rating desc,Price mul Quantity`
+pub(crate) fn parse_sort_order(value: &str) -> ODataResult<Vec<OrderBy>> {
+    let mut order_by = Vec::new();
+    let mut parser = Parser::new(value)?;
+
+    loop {
+        let expression = parser.parse_expression()?;
+        let direction = match parser.next_keyword(&["asc", "desc"]).as_deref() {
+            Some("desc") => OrderByDirection::Desc,
+            _ => OrderByDirection::Asc,
         };
 
-        order_by.push(OrderBy {
-            field: field.to_string(),
-            direction,
-        });
+        order_by.push(OrderBy { expression, direction });
+
+        if parser.is_at_end() {
+            break;
+        }
+
+        parser.expect(TokenKind::Comma, "',' or the end")?;
     }
 
     Ok(order_by)
@@ -378,332 +608,367 @@ impl TryFrom<&Uri> for ODataResource {
     }
 }
 
-fn parse_filter_field(parts: &mut Split<'_, char>) -> ODataResult<FieldFilter> {
-    let mut field = parts.next().ok_or(error::ODataError::IncompletePath)?;
-
-    let not = field.starts_with("not");
-    if not {
-        field = field.trim_start_matches("not").trim();
+/// Parse the path of a resource relative to the service root, e.g. `People('russellwhyte')/Trips`. Syntax errors are
+/// located by their offset in the decoded path.
+fn parse_path(path: &str, value: String, options: &ParseOptions) -> ODataResult<ODataResource> {
+    // the decoded segments with their offset in the path
+    let mut segments: Vec<(usize, String)> = Vec::new();
+    let mut offset = 0;
+    for part in path.split('/') {
+        let segment = percent_decode_str(part).decode_utf8_lossy().to_string();
+        let next = offset + segment.len() + 1;
+        segments.push((offset, segment));
+        offset = next;
+    }
+    if let (true, Some(edm)) = (options.key_as_segment, options.edm) {
+        segments = keys_in_parentheses(segments, edm);
     }
 
-    let is_grouped = field.starts_with('(');
-    if is_grouped {
-        let nested_filter = find_closing_bracket(field, parts)?;
-        let nested_filters = parse_filter(&nested_filter)?;
-        let nested_filter = collapse_filters((not, nested_filters));
-        return Ok(nested_filter);
+    let Some(((_, name), parts)) = segments.split_first() else {
+        return Err(ODataError::IncompletePath);
+    };
+    let extract_entity_at = |offset: usize, segment: &str| extract_entity(segment).map_err(|e| e.offset_by(offset));
+
+    if let Some((kind, entity)) = parse_service_resource(name, parts)? {
+        return Ok(ODataResource {
+            entity,
+            kind,
+            url: value,
+            ..Default::default()
+        });
     }
 
-    let part = if field.is_empty() {
-        // handle constructs like "not Name eq 'Milk'"
-        parts.next().ok_or(error::ODataError::IncompletePath)?
-    } else {
-        field
+    let mut kind = ODataResourceKind::EntitySet;
+    let mut invocation = parse_invocation(name, false, options.edm)?;
+    let mut entity = match &invocation {
+        Some(import) => {
+            kind = match import.kind {
+                InvocationKind::Function => ODataResourceKind::FunctionImport,
+                InvocationKind::Action => ODataResourceKind::ActionImport,
+            };
+            Entity {
+                name: import.name.clone(),
+                key: None,
+                type_cast: None,
+            }
+        }
+        None => extract_entity(name)?,
     };
+    let mut relationships = vec![];
+    // the last segment with its offset, which is the property of the resource unless more segments follow
+    let mut property: Option<(usize, String)> = None;
+    let mut operation = None;
+
+    for (offset, part) in parts {
+        let offset = *offset;
+        if let Ok(part) = Operation::try_from(part.as_str()) {
+            operation = Some(part);
+            continue;
+        }
 
-    let is_function = part.contains('(') && part.contains(')');
-
-    if is_function {
-        let function = part;
-        Ok(FieldFilter::Contents(FieldFilterContents {
-            not,
-            field: field.to_string(),
-            operation: FilterOperation::Function(function.to_string()),
-        }))
-    } else {
-        let operation = parts.next().ok_or(error::ODataError::IncompletePath)?;
-        let mut value = parts.next().ok_or(error::ODataError::IncompletePath)?.to_string();
-
-        // Check if the part contains an opening quote, but not a closing quote
-        if value.chars().filter(|c| c == &'\'').count() % 2 != 0 {
-            // keep eating parts until there is a closing quote
-            loop {
-                if let Some(next_part) = parts.next() {
-                    value = format!("{} {}", value, next_part);
-
-                    if next_part.contains('\'') {
-                        // todo: find position of the quote and leave the rest
-                        break;
-                    }
-                } else {
-                    return Err(error::ODataError::IncompletePath);
-                }
+        if let Some(invocation) = &invocation {
+            // a function can be followed by a property of its result, an action by nothing at all
+            if invocation.kind == InvocationKind::Action || property.is_some() {
+                let expected = match invocation.kind {
+                    InvocationKind::Action => "the end of the path after an action",
+                    InvocationKind::Function => "the end of the path after a property of the function result",
+                };
+                return Err(SyntaxError::new(offset, expected, part.as_str()).into());
             }
+            property = Some((offset, part.to_string()));
+            continue;
         }
 
-        Ok(FieldFilter::Contents(FieldFilterContents {
-            not,
-            field: field.to_string(),
-            operation: match operation.to_lowercase().as_str() {
-                "eq" => FilterOperation::Eq(extract_value(&value)),
-                "ne" => FilterOperation::Ne(extract_value(&value)),
-                "gt" => FilterOperation::Gt(extract_value(&value)),
-                "ge" => FilterOperation::Ge(extract_value(&value)),
-                "lt" => FilterOperation::Lt(extract_value(&value)),
-                "le" => FilterOperation::Le(extract_value(&value)),
-                "in" => FilterOperation::In(eat_in_list_parts(Some(&value), parts)),
-                "has" => FilterOperation::Has(value),
-                _ => FilterOperation::Function(value),
-            },
-        }))
-    }
-}
+        if let Some(bound) = parse_invocation(part, true, options.edm).map_err(|e| e.offset_by(offset))? {
+            // the operation is bound to the entity set, entity or navigation property before it
+            if let Some((offset, property)) = property.take() {
+                relationships.push(extract_entity_at(offset, &property)?);
+            }
+            invocation = Some(bound);
+        } else if is_type_segment(part) {
+            // a type cast applies to the entity set, entity or navigation property before it
+            if let Some((offset, property)) = property.take() {
+                relationships.push(extract_entity_at(offset, &property)?);
+            }
+            let target = relationships.last_mut().unwrap_or(&mut entity);
+            apply_type_cast(target, part).map_err(|e| e.offset_by(offset))?;
+        } else {
+            if let Some((offset, property)) = property.take() {
+                // there was more to parse, so this isn't the end of the resource, i.e. not a property
+                relationships.push(extract_entity_at(offset, &property)?);
+            }
 
-fn collapse_filters(nested_filters: (bool, Filters)) -> FieldFilter {
-    // Collapse a nested vec of filters with a single element into a FieldFilter with a single contents element
-    let (not, mut nested_filters) = nested_filters;
-    if nested_filters.0.len() == 1 {
-        let (filter, _chain) = nested_filters.0.pop().unwrap();
-        let contents = match filter {
-            FieldFilter::Contents(contents) => contents,
-            _ => panic!("Invalid filter"),
-        };
-        return FieldFilter::Contents(FieldFilterContents {
-            not,
-            field: contents.field,
-            operation: contents.operation,
-        });
+            property = Some((offset, part.to_string()));
+        }
     }
 
-    FieldFilter::Nested((not, nested_filters))
+    Ok(ODataResource {
+        entity,
+        kind,
+        url: value.to_string(),
+        property: property.map(|(_, property)| property),
+        operation,
+        relationships,
+        invocation,
+        ..Default::default()
+    })
 }
 
-fn find_closing_bracket<'f>(field: &'f str, parts: &'f mut Split<'_, char>) -> ODataResult<Cow<'f, str>> {
-    // first check if the field is already closed by counting the number of opening and closing brackets; if they match, we're done
-    // i.e. handle something like: (not(contains(FirstName,'Q')))
-    let mut open_brackets = field.matches('(').count();
-    let mut close_brackets = field.matches(')').count();
-
-    // we know that field starts with an opening bracket, so we can skip the first one
-    if open_brackets == close_brackets && field.ends_with(')') {
-        return Ok(Cow::from(&field[1..field.len() - 1]));
-    }
-
-    if open_brackets == close_brackets {
-        todo!("Handle this case");
-    }
-
-    // if we get here, we know that the field is not closed yet, so we need to find the closing bracket
-    let mut field = field.to_string();
-    for part in parts {
-        open_brackets += part.matches('(').count();
-        close_brackets += part.matches(')').count();
+/// Interpret the resources of the service rather than of an entity set, e.g. `$metadata` or `$crossjoin(A,B)`.
+/// Only `$all` and `$entity` may be followed by a segment, which restricts them to a type, e.g. `$all/Ns.Person`.
+/// The segment is the first one of the path, the rest are given with their offset in it.
+fn parse_service_resource(segment: &str, rest: &[(usize, String)]) -> ODataResult<Option<(ODataResourceKind, Entity)>> {
+    let Some((name, arguments)) = split_nested_options(segment).filter(|(name, _)| name.starts_with('$')) else {
+        return Ok(None);
+    };
 
-        field = format!("{} {}", field, part);
+    let kind = match (name, arguments) {
+        ("$metadata", None) => ODataResourceKind::Metadata,
+        ("$batch", None) => ODataResourceKind::Batch,
+        ("$all", None) => ODataResourceKind::All,
+        ("$entity", None) => ODataResourceKind::EntityId,
+        ("$crossjoin", Some(arguments)) => {
+            let entity_sets = split_top_level(arguments, ',');
+            if let Some(entity_set) = entity_sets.iter().find(|entity_set| !is_identifier(entity_set)) {
+                let offset = offset_in(segment, entity_set);
+                return Err(SyntaxError::new(offset, "the name of an entity set", *entity_set).into());
+            }
+            ODataResourceKind::CrossJoin(entity_sets.into_iter().map(str::to_string).collect())
+        }
+        _ => return Err(SyntaxError::new(0, "a resource of the service, e.g. $metadata", segment).into()),
+    };
 
-        if open_brackets == close_brackets {
-            return Ok(Cow::from(field[1..field.len() - 1].to_string()));
+    let is_type_cast = |segment: &str| is_type_segment(segment) && !segment.contains('(');
+    let type_cast = match (&kind, rest) {
+        (_, []) => None,
+        (ODataResourceKind::All | ODataResourceKind::EntityId, [(_, type_cast)]) if is_type_cast(type_cast) => {
+            Some(type_cast.clone())
         }
-    }
+        (ODataResourceKind::All | ODataResourceKind::EntityId, [(offset, segment), ..]) if !is_type_cast(segment) => {
+            return Err(SyntaxError::new(*offset, "a type cast or the end of the path", segment.as_str()).into())
+        }
+        (ODataResourceKind::All | ODataResourceKind::EntityId, [_, (offset, segment), ..])
+        | (_, [(offset, segment), ..]) => {
+            return Err(SyntaxError::new(*offset, format!("the end of the path after {name}"), segment.as_str()).into())
+        }
+    };
 
-    Err(ODataError::IncompletePath)
+    Ok(Some((
+        kind,
+        Entity {
+            name: name.to_string(),
+            key: None,
+            type_cast,
+        },
+    )))
 }
 
-fn eat_in_list_parts(mut first_value: Option<&str>, parts: &mut Split<'_, char>) -> Vec<Value> {
-    let mut values = Vec::new();
-
-    if let Some(first_value) = first_value.take().map(|v| v.trim_start_matches('(')) {
-        let inner_parts = first_value.split(',');
-        let inner_parts = eat_inner_list_parts(&mut inner_parts.into_iter());
-        values.extend(inner_parts);
-    }
-
-    for value in parts.by_ref() {
-        let inner_parts = value.split(',');
-        let inner_parts = eat_inner_list_parts(&mut inner_parts.into_iter());
-        values.extend(inner_parts);
-    }
+/// Interpret a segment as the invocation of a function or action, e.g. `Ns.GetFriendsTrips(userName='x')`. With an
+/// EDM, the segment must name a function or action of it, or a function or action import when unbound. Without one,
+/// only bound functions are recognized, by their qualified name followed by brackets with named parameters or none.
+/// Syntax errors are located in the segment.
+fn parse_invocation(segment: &str, is_bound: bool, edm: Option<&Edmx>) -> ODataResult<Option<Invocation>> {
+    let Some((name, arguments)) = split_nested_options(segment) else {
+        return Ok(None);
+    };
 
-    values
-}
+    let Some(edm) = edm else {
+        // brackets with a single unnamed value are the key following a type cast
+        let (true, Some(arguments)) = (is_bound && is_type_segment(name), arguments) else {
+            return Ok(None);
+        };
+        let Ok(parameters) = parse_parameters(arguments) else {
+            return Ok(None);
+        };
+        return Ok(Some(Invocation {
+            name: name.to_string(),
+            kind: InvocationKind::Function,
+            is_bound,
+            parameters,
+        }));
+    };
 
-fn eat_inner_list_parts(parts: &mut Split<'_, char>) -> Vec<Value> {
-    let mut values = Vec::new();
+    let definition = match is_bound {
+        true => find_operation(edm, name, true),
+        false => find_operation_import(edm, name),
+    };
+    let Some(definition) = definition else {
+        return Ok(None);
+    };
 
-    for value in parts.by_ref() {
-        if value.is_empty() {
-            continue;
+    let parameters = match (definition.kind, arguments) {
+        (InvocationKind::Action, None) => Vec::new(),
+        (InvocationKind::Action, Some(arguments)) => {
+            // the parameters of an action are passed in the request body
+            let open = offset_in(segment, arguments) - 1;
+            return Err(SyntaxError::new(open, format!("the end of the action '{name}'"), &segment[open..]).into());
         }
-
-        if value.ends_with(')') {
-            let value = value.trim_end_matches(')');
-            values.push(extract_value(value));
-            break;
+        (InvocationKind::Function, None) => Vec::new(),
+        (InvocationKind::Function, Some(arguments)) => {
+            parse_parameters(arguments).map_err(|error| error.offset_by(offset_in(segment, arguments)))?
         }
+    };
 
-        values.push(extract_value(value));
+    let declared = definition.invocation_parameters();
+    if let Some((parameter, _)) = parameters
+        .iter()
+        .find(|(parameter, _)| !declared.iter().any(|declared| declared.name == *parameter))
+    {
+        let offset = arguments
+            .into_iter()
+            .flat_map(|arguments| split_top_level(arguments, ','))
+            .find(|part| part.split_once('=').is_some_and(|(name, _)| name.trim() == parameter))
+            .map_or(0, |part| offset_in(segment, part));
+        return Err(SyntaxError::new(offset, format!("a parameter of '{name}'"), parameter.as_str()).into());
     }
-
-    values
-}
-
-/// Extract filters from a query string. It supports these patterns:
-/// - Name eq 'Milk'
-/// - Name ne 'Milk'
-/// - Name gt 'Milk'
-/// - Name ge 'Milk'
-/// - Name lt 'Milk'
-/// - Name le 'Milk'
-/// - Name eq 'Milk' and Price lt 2.55
-/// - Name eq 'Milk' or Price lt 2.55
-/// - not endswith(Name,'ilk')
-/// - style has Sales.Pattern'Yellow'
-/// - Name in ('Milk', 'Cheese')
-fn parse_filter(filter_value: &str) -> ODataResult<Filters> {
-    let mut filters = Vec::new();
-    let mut parts = filter_value.split(' ');
-
-    loop {
-        let filter = parse_filter_field(&mut parts)?;
-        if let Some(chain) = parts.next() {
-            let chain = match chain {
-                "and" => Chain::And,
-                "or" => Chain::Or,
-                _ => panic!("Invalid chain"),
-            };
-
-            filters.push((filter, Some(chain)));
-        } else {
-            filters.push((filter, None));
-            break;
+    if definition.kind == InvocationKind::Function {
+        if let Some(missing) = declared.iter().find(|declared| {
+            declared.nullable.as_deref() == Some("false") && !parameters.iter().any(|(name, _)| *name == declared.name)
+        }) {
+            let close = segment.rfind(')').unwrap_or(segment.len());
+            let expected = format!("the parameter '{}'", missing.name);
+            return Err(SyntaxError::new(close, expected, &segment[close..]).into());
         }
     }
 
-    Ok(Filters(filters))
+    Ok(Some(Invocation {
+        name: name.to_string(),
+        kind: definition.kind,
+        is_bound,
+        parameters,
+    }))
 }
 
-fn parse_path(url: &Url, value: String) -> ODataResult<ODataResource> {
-    match url.path_segments() {
-        None => Err(ODataError::IncompletePath),
-        Some(mut parts) => {
-            let Some(name) = parts.nth(2) else {
-                return Err(ODataError::IncompletePath);
-            };
-
-            let name = percent_decode_str(name).decode_utf8_lossy();
-            let entity = extract_entity(&name);
-
-            let mut part;
-            let mut relationships = vec![];
-            let mut property: Option<String> = None;
-            let mut operation = None;
-
-            loop {
-                part = interpret_next_part(&mut parts);
-
-                match part {
-                    Some(NextPart::Part(part)) => {
-                        if let Some(property) = property.take() {
-                            // there was more to parse, so this isn't the end of the resource, i.e. not a property
-                            relationships.push(extract_entity(&property));
-                        }
-
-                        property = Some(part.to_string());
-                    }
-                    Some(NextPart::Operation(part)) => {
-                        operation = Some(part);
-                    }
-                    None => break,
-                };
-            }
+/// Whether the segment is the qualified name of a type, optionally followed by a key, e.g. `Ns.Employee('x')`
+fn is_type_segment(segment: &str) -> bool {
+    let name = segment.split_once('(').map_or(segment, |(name, _)| name);
+    name.contains('.') && is_identifier(name)
+}
 
-            Ok(ODataResource {
-                entity,
-                kind: ODataResourceKind::EntitySet,
-                url: value.to_string(),
-                property,
-                operation,
-                relationships,
-                ..Default::default()
-            })
+/// Restrict the entity to the type of the cast segment, which may be followed by the key, e.g.
+/// `People/Ns.Employee('x')`. Syntax errors are located in the segment.
+fn apply_type_cast(entity: &mut Entity, segment: &str) -> ODataResult<()> {
+    let cast = extract_entity(segment)?;
+    if entity.type_cast.is_some() {
+        return Err(SyntaxError::new(0, format!("a single type cast for '{}'", entity.name), segment).into());
+    }
+    if let Some(key) = cast.key {
+        if entity.key.is_some() {
+            let open = segment.find('(').unwrap_or_default();
+            let expected = format!("a single key for '{}'", entity.name);
+            return Err(SyntaxError::new(open, expected, &segment[open..]).into());
         }
+        entity.key = Some(key);
     }
-}
 
-enum NextPart<'s> {
-    Part(&'s str),
-    Operation(Operation),
+    entity.type_cast = Some(cast.name);
+    Ok(())
 }
 
-fn interpret_next_part<'p>(parts: &'p mut Split<'_, char>) -> Option<NextPart<'p>> {
-    let part = parts.next();
+/// Rewrite the keys given as a segment into the parentheses of the preceding segment, e.g.
+/// `People/russellwhyte/Friends` -> `People('russellwhyte')/Friends`. A segment following a collection of entities is a
+/// key when it isn't a property of the entity type, and the entity type has a single key property. The segments are
+/// given with their offset, which a rewritten segment keeps.
+fn keys_in_parentheses(segments: Vec<(usize, String)>, edm: &Edmx) -> Vec<(usize, String)> {
+    let mut result: Vec<(usize, String)> = Vec::with_capacity(segments.len());
+    // the entity type of the last segment, and whether it addresses a collection of entities
+    let mut current: Option<(&EntityType, bool)> = None;
+
+    for (offset, segment) in segments {
+        let name = segment.split_once('(').map_or(segment.as_str(), |(name, _)| name);
+
+        // the result of a bound operation isn't followed
+        if is_type_segment(&segment) && find_operation(edm, name, true).is_some() {
+            current = None;
+            result.push((offset, segment));
+            continue;
+        }
 
-    part.map(|part| match Operation::try_from(part) {
-        Ok(operation) => NextPart::Operation(operation),
-        _ => NextPart::Part(part),
-    })
-}
+        // a type cast keeps addressing a collection or a single entity, now of the derived type
+        let derived_type = is_type_segment(&segment).then(|| find_entity_type(edm, name)).flatten();
+        if let (Some(derived_type), Some((_, is_collection))) = (derived_type, current) {
+            current = Some((derived_type, is_collection && name == segment));
+            result.push((offset, segment));
+            continue;
+        }
 
-/// Extract the name and key from a resource name, e.g. People('O''Neil') -> (People, Some(O'Neil))
-fn extract_entity(name: &str) -> Entity {
-    if name.contains("('") && name.contains("')") {
-        let mut parts = name.split("('");
-        let name = parts.next().unwrap();
-        let key = parts.next().unwrap();
-        let key = key.trim_end_matches("')");
-        let key = key.replace("''", "'");
+        if let (Some((entity_type, true)), Some((_, last))) = (current, result.last_mut()) {
+            let is_key = !segment.is_empty()
+                && !segment.starts_with('$')
+                && find_property(edm, entity_type, name).is_none()
+                && find_navigation_property(edm, entity_type, name).is_none();
+            if let (true, [key_property]) = (is_key, key_properties(edm, entity_type).as_slice()) {
+                last.push_str(&format!("({})", key_literal(&segment, &key_property._type)));
+                current = Some((entity_type, false));
+                continue;
+            }
+        }
 
-        return Entity {
-            name: name.to_string(),
-            key: Some(Key::String(key)),
+        let target = match current {
+            None if result.is_empty() => entity_set_type(edm, name),
+            None => None,
+            Some((entity_type, _)) => {
+                find_navigation_property(edm, entity_type, name).and_then(|property| navigation_target(edm, property))
+            }
         };
+        // a segment with a key addresses a single entity
+        current = target.map(|(entity_type, is_collection)| (entity_type, is_collection && name == segment));
+        result.push((offset, segment));
     }
 
-    if name.contains('(') && name.contains(')') {
-        let mut parts = name.split('(');
-        let name = parts.next().unwrap();
-        let key = parts.next().unwrap();
-        let key = key.trim_end_matches(')');
-
-        if key.contains('=') {
-            let mut parts = key.split('=');
-            let key = parts.next().unwrap();
-            let value = parts.next().unwrap();
+    result
+}
 
-            if value.starts_with('\'') && value.ends_with('\'') {
-                let value = value.trim_start_matches('\'').trim_end_matches('\'');
-                return Entity {
-                    name: name.to_string(),
-                    key: Some(Key::KeyValue((key.to_string(), Value::String(value.to_string())))),
-                };
-            }
+/// The key literal of a key given as a segment; strings are not quoted in a segment
+fn key_literal(segment: &str, edm_type: &str) -> String {
+    match edm_type {
+        "Edm.String" => format!("'{}'", segment.replace('\'', "''")),
+        _ => segment.to_string(),
+    }
+}
 
-            if value.starts_with('@') {
-                let value = value.trim_start_matches('@');
-                return Entity {
-                    name: name.to_string(),
-                    key: Some(Key::KeyValue((key.to_string(), Value::QueryOption(value.to_string())))),
-                };
-            }
+/// Extract the name and key from a resource name, e.g. People('O''Neil') -> (People, Some(O'Neil)) or
+/// OrderItems(OrderId=1,ItemNo=2) -> (OrderItems, Some(OrderId=1,ItemNo=2)). Syntax errors are located in the name.
+pub(crate) fn extract_entity(name: &str) -> ODataResult<Entity> {
+    let Some((entity_name, key)) = split_nested_options(name) else {
+        let offset = name.find(['(', ')']).unwrap_or_default();
+        return Err(SyntaxError::new(offset, "a key in balanced brackets", &name[offset..]).into());
+    };
+    let Some(key) = key.filter(|key| !key.trim().is_empty()) else {
+        return Ok(Entity {
+            name: entity_name.to_string(),
+            key: None,
+            type_cast: None,
+        });
+    };
 
-            if let Ok(num) = value.parse::<i32>() {
-                return Entity {
-                    name: name.to_string(),
-                    key: Some(Key::KeyValue((key.to_string(), Value::Integer(num)))),
-                };
+    let parts = split_top_level(key, ',');
+    let mut values = Vec::new();
+    for part in &parts {
+        let (property, value) = match part.split_once('=') {
+            Some((property, value)) if is_identifier(property.trim()) => (Some(property.trim().to_string()), value),
+            // only a single key value can be given without the name of its property
+            _ if parts.len() > 1 => {
+                return Err(SyntaxError::new(offset_in(name, part), "the name of a key property", *part).into())
             }
-
-            return Entity {
-                name: name.to_string(),
-                key: None,
-            };
-        }
-
-        if let Ok(num) = key.parse::<i32>() {
-            return Entity {
-                name: name.to_string(),
-                key: Some(Key::Number(num)),
-            };
+            _ => (None, *part),
+        };
+        let offset = offset_in(name, value);
+        match parse_filter(value).map_err(|error| error.offset_by(offset))? {
+            Expression::Literal(value) => values.push((property, value)),
+            _ => return Err(SyntaxError::new(offset, "a key literal", value.trim()).into()),
         }
     }
+    let key = Key(values);
 
-    Entity {
-        name: name.to_string(),
-        key: None,
-    }
+    Ok(Entity {
+        name: entity_name.to_string(),
+        key: Some(key),
+        type_cast: None,
+    })
 }
 
-fn extract_value(value: &str) -> Value {
+pub(crate) fn extract_value(value: &str) -> Value {
     if value.starts_with('\'') && value.ends_with('\'') {
         let value = value.trim_start_matches('\'').trim_end_matches('\'');
         return Value::String(value.to_string());
@@ -718,10 +983,25 @@ fn extract_value(value: &str) -> Value {
         return Value::Integer(num);
     }
 
+    if let Ok(num) = value.parse::<i64>() {
+        return Value::Int64(num);
+    }
+
     if let Ok(b) = value.parse::<bool>() {
         return Value::Boolean(b);
     }
 
+    if let Some(literal) = parse_unquoted(value) {
+        return literal;
+    }
+
+    // numbers in exponent notation are doubles; only check those, as Rust also accepts e.g. `inf`
+    if value.contains(['e', 'E']) && value.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        if let Ok(num) = value.parse::<f64>() {
+            return Value::Double(num);
+        }
+    }
+
     if let Ok(num) = rust_decimal::Decimal::from_str(value) {
         return Value::Decimal(num);
     }
@@ -736,11 +1016,16 @@ fn extract_value(value: &str) -> Value {
 impl From<ServiceDocumentValue> for ODataResource {
     fn from(value: ServiceDocumentValue) -> Self {
         Self {
-            entity: extract_entity(&value.name),
+            entity: Entity {
+                name: value.name,
+                key: None,
+                type_cast: None,
+            },
             kind: match value.kind {
                 Some(kind) => match kind.as_str() {
                     "Singleton" => ODataResourceKind::Singleton,
                     "FunctionImport" => ODataResourceKind::FunctionImport,
+                    "ActionImport" => ODataResourceKind::ActionImport,
                     "ServiceDocument" => ODataResourceKind::ServiceDocument,
                     _ => ODataResourceKind::EntitySet,
                 },
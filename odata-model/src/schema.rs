@@ -0,0 +1,282 @@
+//! Lookups in the EDM of a service, used to interpret the segments of a resource path.
+
+use odata_edm::edm::{
+    ComplexType, Edmx, EntityContainer, EntityType, EnumType, NavigationProperty, Parameter, Property, Schema,
+};
+
+use crate::invocation::InvocationKind;
+
+/// A function or action of the EDM
+pub(crate) struct OperationDefinition<'e> {
+    pub kind: InvocationKind,
+    pub is_bound: bool,
+    pub parameters: &'e [Parameter],
+}
+
+impl OperationDefinition<'_> {
+    /// The parameters that are given in the invocation, i.e. without the binding parameter
+    pub fn invocation_parameters(&self) -> &[Parameter] {
+        match self.parameters.split_first() {
+            Some((_, parameters)) if self.is_bound => parameters,
+            _ => self.parameters,
+        }
+    }
+}
+
+fn containers(edm: &Edmx) -> impl Iterator<Item = &EntityContainer> {
+    edm.data_services
+        .schema
+        .iter()
+        .flat_map(|schema| schema.entity_container.iter().flatten())
+}
+
+/// The entity type of an entity set or singleton of the entity container. As the entity sets of an `ODataModel` are
+/// named after their entity type, an entity type of the same name is used when there is no such entity set.
+pub(crate) fn entity_set_type<'e>(edm: &'e Edmx, name: &str) -> Option<(&'e EntityType, bool)> {
+    for container in containers(edm) {
+        if let Some(set) = container.entity_set.iter().flatten().find(|set| set.name == name) {
+            return find_entity_type(edm, &set.entity_type).map(|entity_type| (entity_type, true));
+        }
+        if let Some(singleton) = container
+            .singleton
+            .iter()
+            .flatten()
+            .find(|singleton| singleton.name == name)
+        {
+            return find_entity_type(edm, &singleton._type).map(|entity_type| (entity_type, false));
+        }
+    }
+
+    find_entity_type(edm, name).map(|entity_type| (entity_type, true))
+}
+
+/// The schemas a qualified or unqualified name can be in, and the name without its namespace
+fn schemas_for<'e, 'n>(edm: &'e Edmx, name: &'n str) -> (impl Iterator<Item = &'e Schema> + 'n, &'n str)
+where
+    'e: 'n,
+{
+    let (namespace, name) = match name.rsplit_once('.') {
+        Some((namespace, name)) => (Some(namespace), name),
+        None => (None, name),
+    };
+    let schemas = edm
+        .data_services
+        .schema
+        .iter()
+        .filter(move |schema| namespace.is_none_or(|namespace| schema.namespace == namespace));
+
+    (schemas, name)
+}
+
+/// The entity type by its qualified or unqualified name
+pub(crate) fn find_entity_type<'e>(edm: &'e Edmx, name: &str) -> Option<&'e EntityType> {
+    let (mut schemas, name) = schemas_for(edm, name);
+    schemas.find_map(|schema| {
+        schema
+            .entity_type
+            .iter()
+            .flatten()
+            .find(|entity_type| entity_type.name == name)
+    })
+}
+
+/// The complex type by its qualified or unqualified name
+pub(crate) fn find_complex_type<'e>(edm: &'e Edmx, name: &str) -> Option<&'e ComplexType> {
+    let (mut schemas, name) = schemas_for(edm, name);
+    schemas.find_map(|schema| {
+        schema
+            .complex_type
+            .iter()
+            .flatten()
+            .find(|complex_type| complex_type.name == name)
+    })
+}
+
+/// The enum type by its qualified or unqualified name
+pub(crate) fn find_enum_type<'e>(edm: &'e Edmx, name: &str) -> Option<&'e EnumType> {
+    let (mut schemas, name) = schemas_for(edm, name);
+    schemas.find_map(|schema| {
+        schema
+            .enum_type
+            .iter()
+            .flatten()
+            .find(|enum_type| enum_type.name == name)
+    })
+}
+
+/// Whether the EDM defines a type of the name, other than an entity, complex or enum type, i.e. a type definition
+pub(crate) fn is_type_definition(edm: &Edmx, name: &str) -> bool {
+    let (mut schemas, name) = schemas_for(edm, name);
+    schemas.any(|schema| {
+        schema
+            .type_definition
+            .iter()
+            .flatten()
+            .any(|definition| definition.name == name)
+    })
+}
+
+/// The name of the entity type, qualified by the namespace of its schema
+pub(crate) fn qualified_name(edm: &Edmx, entity_type: &EntityType) -> String {
+    edm.data_services
+        .schema
+        .iter()
+        .find(|schema| {
+            schema
+                .entity_type
+                .iter()
+                .flatten()
+                .any(|known| std::ptr::eq(known, entity_type))
+        })
+        .map(|schema| format!("{}.{}", schema.namespace, entity_type.name))
+        .unwrap_or_else(|| entity_type.name.clone())
+}
+
+/// The entity type followed by the types it derives from
+pub(crate) fn type_hierarchy<'e>(edm: &'e Edmx, entity_type: &'e EntityType) -> Vec<&'e EntityType> {
+    let mut hierarchy = vec![entity_type];
+    let mut current = entity_type;
+
+    while let Some(base_type) = current
+        .base_type
+        .as_deref()
+        .and_then(|name| find_entity_type(edm, name))
+    {
+        // guard against a cycle in an invalid EDM
+        if hierarchy.iter().any(|known| std::ptr::eq(*known, base_type)) {
+            break;
+        }
+        hierarchy.push(base_type);
+        current = base_type;
+    }
+
+    hierarchy
+}
+
+/// The complex type followed by the types it derives from
+pub(crate) fn complex_type_hierarchy<'e>(edm: &'e Edmx, complex_type: &'e ComplexType) -> Vec<&'e ComplexType> {
+    let mut hierarchy = vec![complex_type];
+    let mut current = complex_type;
+
+    while let Some(base_type) = current
+        .base_type
+        .as_deref()
+        .and_then(|name| find_complex_type(edm, name))
+    {
+        // guard against a cycle in an invalid EDM
+        if hierarchy.iter().any(|known| std::ptr::eq(*known, base_type)) {
+            break;
+        }
+        hierarchy.push(base_type);
+        current = base_type;
+    }
+
+    hierarchy
+}
+
+/// A structural property of the entity type, including the ones it inherits
+pub(crate) fn find_property<'e>(edm: &'e Edmx, entity_type: &'e EntityType, name: &str) -> Option<&'e Property> {
+    type_hierarchy(edm, entity_type)
+        .into_iter()
+        .flat_map(|entity_type| entity_type.property.iter().flatten())
+        .find(|property| property.name == name)
+}
+
+/// A navigation property of the entity type, including the ones it inherits
+pub(crate) fn find_navigation_property<'e>(
+    edm: &'e Edmx,
+    entity_type: &'e EntityType,
+    name: &str,
+) -> Option<&'e NavigationProperty> {
+    type_hierarchy(edm, entity_type)
+        .into_iter()
+        .flat_map(|entity_type| entity_type.navigation_property.iter().flatten())
+        .find(|property| property.name == name)
+}
+
+/// The entity type a navigation property refers to, and whether it refers to a collection of entities
+pub(crate) fn navigation_target<'e>(edm: &'e Edmx, property: &NavigationProperty) -> Option<(&'e EntityType, bool)> {
+    match property
+        ._type
+        .strip_prefix("Collection(")
+        .and_then(|name| name.strip_suffix(')'))
+    {
+        Some(name) => find_entity_type(edm, name).map(|entity_type| (entity_type, true)),
+        None => find_entity_type(edm, &property._type).map(|entity_type| (entity_type, false)),
+    }
+}
+
+/// The properties of the key of an entity type, in the order of the key; derived types inherit the key
+pub(crate) fn key_properties<'e>(edm: &'e Edmx, entity_type: &'e EntityType) -> Vec<&'e Property> {
+    let Some(keyed) = type_hierarchy(edm, entity_type)
+        .into_iter()
+        .find(|entity_type| entity_type.key.is_some())
+    else {
+        return Vec::new();
+    };
+
+    keyed
+        .key
+        .iter()
+        .flatten()
+        .flat_map(|key| key.property_ref.iter().flatten())
+        .filter_map(|property_ref| find_property(edm, entity_type, &property_ref.name))
+        .collect()
+}
+
+/// The bound or unbound function or action by its qualified name
+pub(crate) fn find_operation<'e>(edm: &'e Edmx, name: &str, is_bound: bool) -> Option<OperationDefinition<'e>> {
+    let (namespace, name) = name.rsplit_once('.')?;
+    let is_true = |value: &Option<String>| value.as_deref() == Some("true");
+
+    edm.data_services
+        .schema
+        .iter()
+        .filter(|schema| schema.namespace == namespace)
+        .find_map(|schema| {
+            let function = schema
+                .function
+                .iter()
+                .flatten()
+                .find(|function| function.name == name && is_true(&function.is_bound) == is_bound)
+                .map(|function| (InvocationKind::Function, &function.parameter));
+            let action = || {
+                schema
+                    .action
+                    .iter()
+                    .flatten()
+                    .find(|action| action.name == name && is_true(&action.is_bound) == is_bound)
+                    .map(|action| (InvocationKind::Action, &action.parameter))
+            };
+
+            function.or_else(action).map(|(kind, parameters)| OperationDefinition {
+                kind,
+                is_bound,
+                parameters: parameters.as_deref().unwrap_or_default(),
+            })
+        })
+}
+
+/// The unbound function or action of a function or action import of the entity container
+pub(crate) fn find_operation_import<'e>(edm: &'e Edmx, name: &str) -> Option<OperationDefinition<'e>> {
+    containers(edm).find_map(|container| {
+        let function = container
+            .function_import
+            .iter()
+            .flatten()
+            .find(|import| import.name == name)
+            .map(|import| import.function.as_str());
+        let action = || {
+            container
+                .action_import
+                .iter()
+                .flatten()
+                .find(|import| import.name == name)
+                .map(|import| import.action.as_str())
+        };
+
+        function
+            .or_else(action)
+            .and_then(|operation| find_operation(edm, operation, false))
+    })
+}
@@ -0,0 +1,98 @@
+//! The $select query option, which limits the properties returned for each entity.
+
+use crate::error::{ODataError, ODataResult, SyntaxError};
+use crate::expression::MemberPath;
+use crate::lexer::{check_nesting, is_identifier, offset_in, split_nested_options, split_top_level, MAX_NESTING};
+use crate::printer::join;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    /// `*`: all structural properties
+    All,
+    /// `Namespace.*`: all actions and functions of the namespace
+    AllOperations(String),
+    /// A property, optionally reached through complex properties, e.g. `Address/City`. Complex properties may
+    /// restrict their own properties with a nested $select, e.g. `Address($select=City)`
+    Property { path: MemberPath, select: Vec<SelectItem> },
+}
+
+impl SelectItem {
+    /// The top-level property of the item, i.e. `Address` for `Address/City`
+    pub fn property(&self) -> Option<&str> {
+        match self {
+            Self::Property { path, .. } => path.segments().first().map(|s| s.as_str()),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SelectItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::All => write!(f, "*"),
+            Self::AllOperations(namespace) => write!(f, "{namespace}.*"),
+            Self::Property { path, select } if select.is_empty() => write!(f, "{path}"),
+            Self::Property { path, select } => write!(f, "{path}($select={})", join(select, ",")),
+        }
+    }
+}
+
+/// Whether the $select items select all structural properties, which is the case when there aren't any
+pub fn selects_all(select: &[SelectItem]) -> bool {
+    select.is_empty() || select.contains(&SelectItem::All)
+}
+
+/// Parse the value of a $select query option, e.g. `Name,Address/City,Ns.*`
+pub fn parse_select(value: &str) -> ODataResult<Vec<SelectItem>> {
+    check_nesting(value, MAX_NESTING)?;
+    parse_select_items(value, value)
+}
+
+/// The items of a $select value, or of a nested one, e.g. in `$expand`; the offsets of errors are the ones in the
+/// value of the query option
+pub(crate) fn parse_select_items(option: &str, value: &str) -> ODataResult<Vec<SelectItem>> {
+    split_top_level(value, ',')
+        .into_iter()
+        .map(|item| parse_select_item(option, item))
+        .collect()
+}
+
+fn parse_select_item(option: &str, item: &str) -> ODataResult<SelectItem> {
+    let invalid = || {
+        ODataError::from(SyntaxError::new(
+            offset_in(option, item),
+            "a property, '*' or 'Namespace.*'",
+            item,
+        ))
+    };
+
+    if item == "*" {
+        return Ok(SelectItem::All);
+    }
+
+    if let Some(namespace) = item.strip_suffix(".*") {
+        if !is_identifier(namespace) {
+            return Err(invalid());
+        }
+        return Ok(SelectItem::AllOperations(namespace.to_string()));
+    }
+
+    let (path, options) = split_nested_options(item).ok_or_else(invalid)?;
+    let segments: Vec<String> = path.split('/').map(|segment| segment.to_string()).collect();
+    if !segments.iter().all(|segment| is_identifier(segment)) {
+        return Err(invalid());
+    }
+
+    let mut select = Vec::new();
+    for nested in options.map(|options| split_top_level(options, ';')).unwrap_or_default() {
+        match nested.split_once('=') {
+            Some(("$select", value)) => select = parse_select_items(option, value)?,
+            _ => return Err(SyntaxError::new(offset_in(option, nested), "a nested $select", nested).into()),
+        }
+    }
+
+    Ok(SelectItem::Property {
+        path: MemberPath(segments),
+        select,
+    })
+}
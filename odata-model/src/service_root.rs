@@ -0,0 +1,88 @@
+use url::Url;
+
+use crate::error::{ODataError, ODataResult};
+
+/// Stands in for the scheme and host of a service root that is only a path, e.g. `/V4/UserService`
+const PLACEHOLDER_ORIGIN: &str = "http://localhost/";
+
+/// The URL of an OData service, which the paths of its resources are relative to, e.g.
+/// `https://example.com/api/odata/v4/tenant/`. The root can be only a path, like `/V4/UserService`, in which case the
+/// scheme and host of absolute URLs aren't checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceRoot {
+    url: Url,
+    has_origin: bool,
+}
+
+impl ServiceRoot {
+    pub fn new(root: &str) -> ODataResult<Self> {
+        let (mut url, has_origin) = match Url::parse(root) {
+            Ok(url) => (url, true),
+            Err(url::ParseError::RelativeUrlWithoutBase) => {
+                let path = format!("/{}", root.trim_start_matches('/'));
+                (Url::parse(PLACEHOLDER_ORIGIN)?.join(&path)?, false)
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        if url.cannot_be_a_base() {
+            return Err(ODataError::InvalidServiceRoot(root.to_string()));
+        }
+
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        url.set_query(None);
+        url.set_fragment(None);
+
+        Ok(Self { url, has_origin })
+    }
+
+    /// The path of the root, ending in a `/`
+    pub fn path(&self) -> &str {
+        self.url.path()
+    }
+
+    /// Resolve an URL of a resource, which is either absolute, a path from the host, or relative to the root, e.g.
+    /// `https://example.com/api/odata/v4/tenant/People`, `/api/odata/v4/tenant/People` or `People`. Returns the
+    /// absolute URL and its path relative to the root, or an error when the URL is outside the service.
+    pub fn resolve(&self, value: &str) -> ODataResult<(Url, String)> {
+        let url = self.url.join(value)?;
+
+        let has_other_origin = url.scheme() != self.url.scheme()
+            || url.host() != self.url.host()
+            || url.port_or_known_default() != self.url.port_or_known_default();
+        if self.has_origin && has_other_origin {
+            return Err(ODataError::OutsideServiceRoot(value.to_string(), self.to_string()));
+        }
+
+        let path = url.path();
+        let relative = match path.strip_prefix(self.path()) {
+            Some(relative) => relative,
+            // the service document may be requested without the trailing slash
+            None if path == self.path().trim_end_matches('/') => "",
+            None => return Err(ODataError::OutsideServiceRoot(value.to_string(), self.to_string())),
+        };
+        let relative = relative.to_string();
+
+        Ok((url, relative))
+    }
+}
+
+impl Default for ServiceRoot {
+    /// The root of the TripPin sample service, which paths are relative to when no root is given
+    fn default() -> Self {
+        Self::new("http://services.odata.org/V4/TripPinService/").expect("the TripPin root is a valid URL")
+    }
+}
+
+impl std::fmt::Display for ServiceRoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.has_origin {
+            write!(f, "{}", self.url)
+        } else {
+            write!(f, "{}", self.url.path())
+        }
+    }
+}
@@ -1,5 +1,14 @@
 use super::*;
+use apply::*;
+use binding::*;
+use error::SyntaxError;
+use expand::*;
+use expression::*;
+use invocation::*;
+use model::ODataModel;
+use odata_edm::edm::{Edmx, EntityContainer, EntitySet, EntityType, NavigationProperty, Singleton};
 use rust_decimal_macros::dec;
+use select::*;
 
 #[test]
 fn can_construct_an_url_from_and_endpoint() {
@@ -97,11 +106,8 @@ fn can_create_a_resource_from_a_url_with_a_query_option() {
     let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
     assert_eq!(resource.entity.name, "ProductsByColor");
     let key = resource.entity.key.unwrap();
-    assert_eq!(key.to_string(), "color=@color");
-    if let Key::KeyValue((key, value)) = key {
-        assert_eq!(key, "color");
-        assert_eq!(value.to_string(), "@color");
-    }
+    assert_eq!(key.to_string(), "color=red");
+    assert_eq!(key, Key::named([("color", "red")]));
 }
 
 #[test]
@@ -130,6 +136,20 @@ fn can_create_a_resource_from_a_url_with_a_count_operation() {
     let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
     assert_eq!(resource.entity.name, "People");
     assert_eq!(resource.operation.unwrap(), Operation::Count);
+    assert!(!resource.count);
+}
+
+#[test]
+fn can_create_a_resource_from_a_url_with_an_inline_count() {
+    let url = "People?$count=true&$top=5";
+    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
+    assert!(resource.count);
+    assert_eq!(resource.operation, None);
+
+    let resource = ODataResource::try_from("People?$count=false").expect("Failed to create a resource from the URL");
+    assert!(!resource.count);
+
+    assert!(ODataResource::try_from("People?$count=yes").is_err());
 }
 
 #[test]
@@ -152,18 +172,43 @@ fn can_create_a_resource_from_a_url_with_a_search_operation() {
     assert_eq!(resource.search.unwrap(), "russellwhyte");
 }
 
+fn member(name: &str) -> Expression {
+    Expression::Member(MemberPath(name.split('/').map(|s| s.to_string()).collect()))
+}
+
+fn string(value: &str) -> Expression {
+    Expression::Literal(Value::String(value.to_string()))
+}
+
+fn enum_member(type_name: &str, member: &str) -> Expression {
+    Expression::Literal(Value::Enum(type_name.to_string(), member.to_string()))
+}
+
+fn binary(left: Expression, operator: BinaryOperator, right: Expression) -> Expression {
+    Expression::Binary(Box::new(left), operator, Box::new(right))
+}
+
+fn logical(left: Expression, operator: LogicalOperator, right: Expression) -> Expression {
+    Expression::Logical(operator, vec![left, right])
+}
+
+fn not(operand: Expression) -> Expression {
+    Expression::Unary(UnaryOperator::Not, Box::new(operand))
+}
+
+fn parse_filter_of(url: &str) -> Expression {
+    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
+    resource.filter.expect("Missing filter")
+}
+
 #[test]
 fn can_create_a_resource_from_a_url_with_a_filter_eq_operation() {
     let url = "Products?$filter=Name eq 'Milk'";
     let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
     assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 1);
-    let (filter, _) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Name");
     assert_eq!(
-        contents.operation,
-        FilterOperation::Eq(Value::String("Milk".to_string()))
+        resource.filter.unwrap(),
+        binary(member("Name"), BinaryOperator::Eq, string("Milk"))
     );
 }
 
@@ -172,13 +217,9 @@ fn can_create_a_resource_from_a_url_with_a_filter_ne_operation() {
     let url = "Products?$filter=Name ne 'Milk'";
     let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
     assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 1);
-    let (filter, _) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Name");
     assert_eq!(
-        contents.operation,
-        FilterOperation::Ne(Value::String("Milk".to_string()))
+        resource.filter.unwrap(),
+        binary(member("Name"), BinaryOperator::Ne, string("Milk"))
     );
 }
 
@@ -187,19 +228,18 @@ fn can_create_a_resource_from_a_url_with_a_filter_eq_and_lt_operation() {
     let url = "Products?$filter=Name eq 'Milk' and Price lt 2.55";
     let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
     assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 2);
-    let (filter, chain) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Name");
     assert_eq!(
-        contents.operation,
-        FilterOperation::Eq(Value::String("Milk".to_string()))
+        resource.filter.unwrap(),
+        logical(
+            binary(member("Name"), BinaryOperator::Eq, string("Milk")),
+            LogicalOperator::And,
+            binary(
+                member("Price"),
+                BinaryOperator::Lt,
+                Expression::Literal(Value::Decimal(dec!(2.55)))
+            )
+        )
     );
-    assert_eq!(chain, &Some(Chain::And));
-    let (filter, _) = &resource.filters.contents()[1];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Price");
-    assert_eq!(contents.operation, FilterOperation::Lt(Value::Decimal(dec!(2.55))));
 }
 
 #[test]
@@ -207,177 +247,206 @@ fn can_create_a_resource_from_a_url_with_a_filter_eq_or_lt_operation() {
     let url = "Products?$filter=Name eq 'Milk' or Price lt 2.55";
     let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
     assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 2);
-    let (filter, chain) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Name");
-    assert_eq!(chain, &Some(Chain::Or));
     assert_eq!(
-        contents.operation,
-        FilterOperation::Eq(Value::String("Milk".to_string()))
+        resource.filter.unwrap(),
+        logical(
+            binary(member("Name"), BinaryOperator::Eq, string("Milk")),
+            LogicalOperator::Or,
+            binary(
+                member("Price"),
+                BinaryOperator::Lt,
+                Expression::Literal(Value::Decimal(dec!(2.55)))
+            )
+        )
     );
-    let (filter, _chain) = &resource.filters.contents()[1];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Price");
-    assert_eq!(contents.operation, FilterOperation::Lt(Value::Decimal(dec!(2.55))));
 }
 
 #[test]
 fn can_create_a_resource_from_a_url_with_a_space_in_value() {
-    let url = "Products?$filter=Name eq 'Chocolate Milk'";
-    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
-    assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 1);
-    let (filter, _) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Name");
+    let filter = parse_filter_of("Products?$filter=Name eq 'Chocolate Milk'");
     assert_eq!(
-        contents.operation,
-        FilterOperation::Eq(Value::String("Chocolate Milk".to_string()))
+        filter,
+        binary(member("Name"), BinaryOperator::Eq, string("Chocolate Milk"))
     );
 }
 
 #[test]
 fn can_create_a_resource_from_a_url_with_multiple_spaces_in_value() {
-    let url = "Products?$filter=Name eq 'Very nice Chocolate Milk'";
-    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
-    assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 1);
-    let (filter, _) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Name");
+    let filter = parse_filter_of("Products?$filter=Name eq 'Very nice Chocolate Milk'");
     assert_eq!(
-        contents.operation,
-        FilterOperation::Eq(Value::String("Very nice Chocolate Milk".to_string()))
+        filter,
+        binary(member("Name"), BinaryOperator::Eq, string("Very nice Chocolate Milk"))
     );
 }
 
+#[test]
+fn can_preserve_consecutive_spaces_in_value() {
+    let filter = parse_filter_of("Products?$filter=Name   eq  'a  b'");
+    assert_eq!(filter, binary(member("Name"), BinaryOperator::Eq, string("a  b")));
+}
+
 #[test]
 fn can_create_a_resource_from_a_url_with_a_filter_in_operation() {
-    let url = "Products?$filter=Name in ('Milk', 'Butter', 'Cheese')";
-    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
-    assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 1);
-    let (filter, _chain) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Name");
+    let filter = parse_filter_of("Products?$filter=Name in ('Milk', 'Butter', 'Cheese')");
     assert_eq!(
-        contents.operation,
-        FilterOperation::In(vec![
-            Value::String("Milk".to_string()),
-            Value::String("Butter".to_string()),
-            Value::String("Cheese".to_string())
-        ])
+        filter,
+        binary(
+            member("Name"),
+            BinaryOperator::In,
+            Expression::List(vec![string("Milk"), string("Butter"), string("Cheese")])
+        )
     );
 }
 
 #[test]
 fn can_create_a_resource_from_a_url_with_a_filter_in_operation_wo_spaces() {
-    let url = "Products?$filter=Name in ('Milk','Butter','Cheese')";
-    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
-    assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 1);
-    let (filter, _chain) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Name");
+    let filter = parse_filter_of("Products?$filter=Name in ('Milk','Butter','Cheese')");
     assert_eq!(
-        contents.operation,
-        FilterOperation::In(vec![
-            Value::String("Milk".to_string()),
-            Value::String("Butter".to_string()),
-            Value::String("Cheese".to_string())
-        ])
+        filter,
+        binary(
+            member("Name"),
+            BinaryOperator::In,
+            Expression::List(vec![string("Milk"), string("Butter"), string("Cheese")])
+        )
     );
 }
 
 #[test]
 fn can_create_a_resource_from_a_url_with_a_filter_in_numbers() {
-    let url = "Products?$filter=Price in (1,2,3)";
-    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
-    assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 1);
-    let (filter, _chain) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert_eq!(contents.field, "Price");
+    let filter = parse_filter_of("Products?$filter=Price in (1,2,3)");
     assert_eq!(
-        contents.operation,
-        FilterOperation::In(vec![Value::Integer(1), Value::Integer(2), Value::Integer(3),])
+        filter,
+        binary(
+            member("Price"),
+            BinaryOperator::In,
+            Expression::List(vec![
+                Expression::Literal(Value::Integer(1)),
+                Expression::Literal(Value::Integer(2)),
+                Expression::Literal(Value::Integer(3)),
+            ])
+        )
     );
 }
 
 #[test]
 fn can_create_a_resource_from_a_url_with_a_not_function_filter() {
-    let url = "Products?$filter=not endswith(Name,'ilk')";
-    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
-    assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 1);
-    let (filter, _chain) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert!(contents.not);
+    let filter = parse_filter_of("Products?$filter=not endswith(Name,'ilk')");
     assert_eq!(
-        contents.operation,
-        FilterOperation::Function("endswith(Name,'ilk')".to_string())
+        filter,
+        not(Expression::Function(
+            Function::EndsWith,
+            vec![member("Name"), string("ilk")]
+        ))
     );
 }
 
 #[test]
 fn can_create_a_resource_from_a_url_with_a_has_filter() {
-    let url = "Products?$filter=style has Sales.Pattern'Yellow'";
-    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
-    assert_eq!(resource.entity.name, "Products");
-    assert_eq!(resource.filters.len(), 1);
-    let (filter, _chain) = &resource.filters.contents()[0];
-    let contents = filter.contents().unwrap();
-    assert!(!contents.not);
-    assert_eq!(contents.field, "style");
+    let filter = parse_filter_of("Products?$filter=style has Sales.Pattern'Yellow'");
     assert_eq!(
-        contents.operation,
-        FilterOperation::Has("Sales.Pattern'Yellow'".to_string())
+        filter,
+        binary(
+            member("style"),
+            BinaryOperator::Has,
+            enum_member("Sales.Pattern", "Yellow")
+        )
     );
 }
 
 #[test]
 fn can_create_a_resource_with_a_logical_filter() {
     let url = "People?$filter=(not(contains(FirstName,'Q')) or (Gender eq Microsoft.OData.SampleService.Models.TripPin.PersonGender'Male')) and not(LastName eq 'Ketchum')";
-    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
-    assert_eq!(resource.filters.0.len(), 2);
-    let (filter, chain) = &resource.filters.contents()[0];
-    let (not, nested) = filter.nested().unwrap();
-    assert!(!not);
-    assert_eq!(chain, &Some(Chain::And));
-    assert_eq!(nested.0.len(), 2);
-
-    // First filter
-    let (filter, _chain) = &nested.0[0];
-    let FieldFilterContents {
-        not,
-        field: _field,
-        operation,
-    } = filter.contents().unwrap();
-    assert!(not);
-    assert_eq!(
-        operation,
-        &FilterOperation::Function("contains(FirstName,'Q')".to_string())
-    );
-
-    // Second filter
-    let (filter, _chain) = &nested.0[1];
-    let FieldFilterContents { not, field, operation } = filter.contents().unwrap();
-    assert!(!not);
-    assert_eq!(field, "Gender");
-    assert_eq!(
-        operation,
-        &FilterOperation::Eq(Value::String(
-            "Microsoft.OData.SampleService.Models.TripPin.PersonGender'Male'".to_string()
-        ))
+    let filter = parse_filter_of(url);
+    assert_eq!(
+        filter,
+        logical(
+            logical(
+                not(Expression::Function(
+                    Function::Contains,
+                    vec![member("FirstName"), string("Q")]
+                )),
+                LogicalOperator::Or,
+                binary(
+                    member("Gender"),
+                    BinaryOperator::Eq,
+                    enum_member("Microsoft.OData.SampleService.Models.TripPin.PersonGender", "Male")
+                )
+            ),
+            LogicalOperator::And,
+            not(binary(member("LastName"), BinaryOperator::Eq, string("Ketchum")))
+        )
+    );
+}
+
+#[test]
+fn can_parse_groups_without_surrounding_whitespace() {
+    let filter = parse_filter_of("People?$filter=(A eq 1)and(B eq 2)");
+    assert_eq!(
+        filter,
+        logical(
+            binary(member("A"), BinaryOperator::Eq, Expression::Literal(Value::Integer(1))),
+            LogicalOperator::And,
+            binary(member("B"), BinaryOperator::Eq, Expression::Literal(Value::Integer(2)))
+        )
     );
+}
+
+#[test]
+fn can_parse_nested_groups() {
+    let filter = parse_filter_of("People?$filter=((A eq 1 or (B eq 2)) and C eq 3)");
+    assert_eq!(
+        filter,
+        logical(
+            logical(
+                binary(member("A"), BinaryOperator::Eq, Expression::Literal(Value::Integer(1))),
+                LogicalOperator::Or,
+                binary(member("B"), BinaryOperator::Eq, Expression::Literal(Value::Integer(2)))
+            ),
+            LogicalOperator::And,
+            binary(member("C"), BinaryOperator::Eq, Expression::Literal(Value::Integer(3)))
+        )
+    );
+}
+
+#[test]
+fn can_parse_with_operator_precedence() {
+    // not > and > or
+    let filter = parse_filter_of("People?$filter=A eq 1 or not B eq 2 and C eq 3");
+    assert_eq!(
+        filter,
+        logical(
+            binary(member("A"), BinaryOperator::Eq, Expression::Literal(Value::Integer(1))),
+            LogicalOperator::Or,
+            logical(
+                not(binary(
+                    member("B"),
+                    BinaryOperator::Eq,
+                    Expression::Literal(Value::Integer(2))
+                )),
+                LogicalOperator::And,
+                binary(member("C"), BinaryOperator::Eq, Expression::Literal(Value::Integer(3)))
+            )
+        )
+    );
+}
+
+#[test]
+fn can_parse_escaped_quotes_in_value() {
+    let filter = parse_filter_of("People?$filter=LastName eq 'O''Neil'");
+    assert_eq!(filter, binary(member("LastName"), BinaryOperator::Eq, string("O'Neil")));
+}
 
-    // Third filter
-    let (filter, _chain) = &resource.filters.contents()[1];
-    let FieldFilterContents { not, field, operation } = filter.contents().unwrap();
-    assert!(not);
-    assert_eq!(field, "LastName");
-    assert_eq!(operation, &FilterOperation::Eq(Value::String("Ketchum".to_string())));
+#[test]
+fn can_detect_invalid_filters() {
+    for url in [
+        "People?$filter=Name eq 'Milk",
+        "People?$filter=(Name eq 'Milk'",
+        "People?$filter=Name eq 'Milk')",
+        "People?$filter=Name eq",
+        "People?$filter=Name eq 'Milk' xor Price lt 2",
+    ] {
+        assert!(ODataResource::try_from(url).is_err(), "{url} should not parse");
+    }
 }
 
 #[test]
@@ -414,8 +483,8 @@ fn can_parse_orderby() {
     let url = "People?$orderby=BaseRate asc";
     let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
     assert_eq!(resource.order_by.len(), 1);
-    let OrderBy { field, direction } = &resource.order_by[0];
-    assert_eq!(field, "BaseRate");
+    let OrderBy { expression, direction } = &resource.order_by[0];
+    assert_eq!(expression, &member("BaseRate"));
     assert_eq!(direction, &OrderByDirection::Asc);
 }
 
@@ -424,11 +493,2198 @@ fn can_parse_orderby_with_two_fields() {
     let url = "People?$orderby=Rating desc,BaseRate";
     let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
     assert_eq!(resource.order_by.len(), 2);
-    let OrderBy { field, direction } = &resource.order_by[0];
-    assert_eq!(field, "Rating");
+    let OrderBy { expression, direction } = &resource.order_by[0];
+    assert_eq!(expression, &member("Rating"));
     assert_eq!(direction, &OrderByDirection::Desc);
 
-    let OrderBy { field, direction } = &resource.order_by[1];
-    assert_eq!(field, "BaseRate");
+    let OrderBy { expression, direction } = &resource.order_by[1];
+    assert_eq!(expression, &member("BaseRate"));
     assert_eq!(direction, &OrderByDirection::Asc);
 }
+
+#[test]
+fn can_parse_orderby_with_an_arithmetic_expression() {
+    let url = "Orders?$orderby=Price mul Quantity desc, Name";
+    let resource = ODataResource::try_from(url).expect("Failed to create a resource from the URL");
+    assert_eq!(resource.order_by.len(), 2);
+    let OrderBy { expression, direction } = &resource.order_by[0];
+    assert_eq!(
+        expression,
+        &binary(member("Price"), BinaryOperator::Mul, member("Quantity"))
+    );
+    assert_eq!(direction, &OrderByDirection::Desc);
+}
+
+#[test]
+fn can_detect_invalid_orderby() {
+    let url = "People?$orderby=Rating up";
+    assert!(ODataResource::try_from(url).is_err());
+}
+
+#[test]
+fn can_parse_arithmetic_operators() {
+    let filter = parse_filter_of("Orders?$filter=Price mul Quantity gt 100");
+    assert_eq!(
+        filter,
+        binary(
+            binary(member("Price"), BinaryOperator::Mul, member("Quantity")),
+            BinaryOperator::Gt,
+            Expression::Literal(Value::Integer(100))
+        )
+    );
+}
+
+#[test]
+fn can_parse_arithmetic_with_precedence() {
+    // mul/div/divby/mod bind tighter than add/sub, which are left associative
+    let filter = parse_filter_of("Orders?$filter=A add B mul C sub D divby 2 eq E mod 3");
+    let left = binary(
+        binary(
+            member("A"),
+            BinaryOperator::Add,
+            binary(member("B"), BinaryOperator::Mul, member("C")),
+        ),
+        BinaryOperator::Sub,
+        binary(
+            member("D"),
+            BinaryOperator::DivBy,
+            Expression::Literal(Value::Integer(2)),
+        ),
+    );
+    let right = binary(member("E"), BinaryOperator::Mod, Expression::Literal(Value::Integer(3)));
+    assert_eq!(filter, binary(left, BinaryOperator::Eq, right));
+}
+
+#[test]
+fn can_parse_negation() {
+    let filter = parse_filter_of("Orders?$filter=-Price lt -5 and -(Price div 2) ge - Discount");
+    let negate = |operand| Expression::Unary(UnaryOperator::Negate, Box::new(operand));
+    assert_eq!(
+        filter,
+        logical(
+            binary(
+                negate(member("Price")),
+                BinaryOperator::Lt,
+                Expression::Literal(Value::Integer(-5))
+            ),
+            LogicalOperator::And,
+            binary(
+                negate(binary(
+                    member("Price"),
+                    BinaryOperator::Div,
+                    Expression::Literal(Value::Integer(2))
+                )),
+                BinaryOperator::Ge,
+                negate(member("Discount"))
+            )
+        )
+    );
+}
+
+#[test]
+fn can_parse_functions_as_operands() {
+    let filter = parse_filter_of("People?$filter=length(Name) gt 5 and indexof(Name,'ss') eq 1");
+    assert_eq!(
+        filter,
+        logical(
+            binary(
+                Expression::Function(Function::Length, vec![member("Name")]),
+                BinaryOperator::Gt,
+                Expression::Literal(Value::Integer(5))
+            ),
+            LogicalOperator::And,
+            binary(
+                Expression::Function(Function::IndexOf, vec![member("Name"), string("ss")]),
+                BinaryOperator::Eq,
+                Expression::Literal(Value::Integer(1))
+            )
+        )
+    );
+}
+
+#[test]
+fn can_parse_nested_functions() {
+    let filter = parse_filter_of("People?$filter=toupper(concat(concat(FirstName,' '),trim(LastName))) eq 'A B'");
+    let concat = Expression::Function(
+        Function::Concat,
+        vec![
+            Expression::Function(Function::Concat, vec![member("FirstName"), string(" ")]),
+            Expression::Function(Function::Trim, vec![member("LastName")]),
+        ],
+    );
+    assert_eq!(
+        filter,
+        binary(
+            Expression::Function(Function::ToUpper, vec![concat]),
+            BinaryOperator::Eq,
+            string("A B")
+        )
+    );
+}
+
+#[test]
+fn can_parse_substring_with_optional_length() {
+    let filter = parse_filter_of("People?$filter=substring(Name,1) eq 'ob' or substring(Name,1,2) eq 'ob'");
+    let Expression::Logical(_, operands) = filter else {
+        panic!("expected a logical expression");
+    };
+    let [left, right] = operands.as_slice() else {
+        panic!("expected two operands");
+    };
+    assert!(
+        matches!(*left, Expression::Binary(ref f, _, _) if matches!(**f, Expression::Function(Function::Substring, ref args) if args.len() == 2))
+    );
+    assert!(
+        matches!(*right, Expression::Binary(ref f, _, _) if matches!(**f, Expression::Function(Function::Substring, ref args) if args.len() == 3))
+    );
+}
+
+#[test]
+fn can_parse_matches_pattern() {
+    let filter = parse_filter_of("People?$filter=matchesPattern(Name,'^A.*e$')");
+    assert_eq!(
+        filter,
+        Expression::Function(Function::MatchesPattern, vec![member("Name"), string("^A.*e$")])
+    );
+}
+
+#[test]
+fn can_detect_unknown_functions_and_invalid_arguments() {
+    for url in [
+        "People?$filter=frobnicate(Name) eq 1",
+        "People?$filter=contains(Name) eq 1",
+        "People?$filter=substring(Name,1,2,3) eq 'a'",
+        "People?$filter=length() eq 1",
+    ] {
+        assert!(ODataResource::try_from(url).is_err(), "{url} should not parse");
+    }
+}
+
+#[test]
+fn can_parse_date_and_time_functions() {
+    let filter = parse_filter_of("Orders?$filter=year(CreatedAt) eq 2024 and date(CreatedAt) lt date(now())");
+    assert_eq!(
+        filter,
+        logical(
+            binary(
+                Expression::Function(Function::Year, vec![member("CreatedAt")]),
+                BinaryOperator::Eq,
+                Expression::Literal(Value::Integer(2024))
+            ),
+            LogicalOperator::And,
+            binary(
+                Expression::Function(Function::Date, vec![member("CreatedAt")]),
+                BinaryOperator::Lt,
+                Expression::Function(Function::Date, vec![Expression::Function(Function::Now, vec![])])
+            )
+        )
+    );
+}
+
+#[test]
+fn can_parse_all_date_and_time_functions() {
+    for (name, function) in [
+        ("month(CreatedAt)", Function::Month),
+        ("day(CreatedAt)", Function::Day),
+        ("hour(CreatedAt)", Function::Hour),
+        ("minute(CreatedAt)", Function::Minute),
+        ("second(CreatedAt)", Function::Second),
+        ("fractionalseconds(CreatedAt)", Function::FractionalSeconds),
+        ("time(CreatedAt)", Function::Time),
+        ("totaloffsetminutes(CreatedAt)", Function::TotalOffsetMinutes),
+        ("maxdatetime()", Function::MaxDateTime),
+        ("mindatetime()", Function::MinDateTime),
+    ] {
+        let filter = parse_filter_of(&format!("Orders?$filter={name} ne null"));
+        let Expression::Binary(left, _, _) = filter else {
+            panic!("expected a comparison for {name}");
+        };
+        assert!(matches!(*left, Expression::Function(f, _) if f == function), "{name}");
+    }
+
+    assert!(ODataResource::try_from("Orders?$filter=now(CreatedAt) eq null").is_err());
+}
+
+fn lambda(path: &str, operator: LambdaOperator, variable: &str, predicate: Expression) -> Expression {
+    Expression::Lambda(Lambda {
+        path: MemberPath(path.split('/').map(|s| s.to_string()).collect()),
+        operator,
+        predicate: Some((variable.to_string(), Box::new(predicate))),
+    })
+}
+
+#[test]
+fn can_parse_an_any_lambda() {
+    let filter = parse_filter_of("People?$filter=Emails/any(e: endswith(e, 'contoso.com'))");
+    assert_eq!(
+        filter,
+        lambda(
+            "Emails",
+            LambdaOperator::Any,
+            "e",
+            Expression::Function(Function::EndsWith, vec![member("e"), string("contoso.com")])
+        )
+    );
+}
+
+#[test]
+fn can_parse_an_any_lambda_without_predicate() {
+    let filter = parse_filter_of("People?$filter=Friends/any() and not Trips/any()");
+    let friends = Expression::Lambda(Lambda {
+        path: MemberPath(vec!["Friends".to_string()]),
+        operator: LambdaOperator::Any,
+        predicate: None,
+    });
+    let trips = Expression::Lambda(Lambda {
+        path: MemberPath(vec!["Trips".to_string()]),
+        operator: LambdaOperator::Any,
+        predicate: None,
+    });
+    assert_eq!(filter, logical(friends, LogicalOperator::And, not(trips)));
+}
+
+#[test]
+fn can_parse_nested_lambdas_with_it() {
+    let filter = parse_filter_of(
+        "People?$filter=AddressInfo/any(ai:ai/City/Region eq 'WA' and $it/Trips/all(t:t/Budget gt 1000)) eq true",
+    );
+    let trips = lambda(
+        "$it/Trips",
+        LambdaOperator::All,
+        "t",
+        binary(
+            member("t/Budget"),
+            BinaryOperator::Gt,
+            Expression::Literal(Value::Integer(1000)),
+        ),
+    );
+    assert_eq!(
+        filter,
+        binary(
+            lambda(
+                "AddressInfo",
+                LambdaOperator::Any,
+                "ai",
+                logical(
+                    binary(member("ai/City/Region"), BinaryOperator::Eq, string("WA")),
+                    LogicalOperator::And,
+                    trips
+                )
+            ),
+            BinaryOperator::Eq,
+            Expression::Literal(Value::Boolean(true))
+        )
+    );
+}
+
+#[test]
+fn can_detect_invalid_lambdas() {
+    for filter in [
+        "Trips/all()",
+        "Trips/any(t)",
+        "Trips/any(t:)",
+        "Trips/any(t:t/Budget gt 10",
+        "Trips/any($it:$it/Budget gt 10)",
+        "Trips/any(t:t/PlanItems/any(t:t/Price gt 10))",
+    ] {
+        let url = format!("People?$filter={filter}");
+        assert!(ODataResource::try_from(url.as_str()).is_err(), "{filter}");
+    }
+}
+
+fn select_property(path: &str) -> SelectItem {
+    SelectItem::Property {
+        path: MemberPath(path.split('/').map(|s| s.to_string()).collect()),
+        select: vec![],
+    }
+}
+
+#[test]
+fn can_create_a_resource_from_a_url_with_a_select() {
+    let resource = ODataResource::try_from("People?$select=FirstName, LastName,AddressInfo/City")
+        .expect("Failed to create a resource from the URL");
+    assert_eq!(
+        resource.select,
+        vec![
+            select_property("FirstName"),
+            select_property("LastName"),
+            select_property("AddressInfo/City"),
+        ]
+    );
+    assert!(!selects_all(&resource.select));
+}
+
+#[test]
+fn can_parse_select_wildcards() {
+    let select = parse_select("*,Microsoft.OData.SampleService.Models.TripPin.*").expect("Failed to parse $select");
+    assert_eq!(
+        select,
+        vec![
+            SelectItem::All,
+            SelectItem::AllOperations("Microsoft.OData.SampleService.Models.TripPin".to_string()),
+        ]
+    );
+    assert!(selects_all(&select));
+    assert!(selects_all(&[]));
+}
+
+#[test]
+fn can_parse_a_nested_select() {
+    let select = parse_select("Name,AddressInfo($select=Address,City($select=Name;$select=Region))")
+        .expect("Failed to parse $select");
+    assert_eq!(
+        select,
+        vec![
+            select_property("Name"),
+            SelectItem::Property {
+                path: MemberPath(vec!["AddressInfo".to_string()]),
+                select: vec![
+                    select_property("Address"),
+                    SelectItem::Property {
+                        path: MemberPath(vec!["City".to_string()]),
+                        select: vec![select_property("Region")],
+                    },
+                ],
+            },
+        ]
+    );
+    assert_eq!(select[1].property(), Some("AddressInfo"));
+}
+
+#[test]
+fn can_detect_invalid_selects() {
+    for select in [
+        "",
+        "Name,",
+        "Name eq 1",
+        "Address(",
+        "Address($top=1)",
+        "Address)",
+        ".*",
+        "Address//City",
+    ] {
+        let url = format!("People?$select={select}");
+        assert!(ODataResource::try_from(url.as_str()).is_err(), "{select}");
+    }
+}
+
+fn expand_navigation(path: &str, options: ExpandOptions) -> ExpandItem {
+    ExpandItem {
+        path: ExpandPath::Navigation(MemberPath(path.split('/').map(|s| s.to_string()).collect())),
+        reference: false,
+        options,
+    }
+}
+
+#[test]
+fn can_create_a_resource_from_a_url_with_an_expand() {
+    let resource = ODataResource::try_from("People('russellwhyte')?$expand=Trips,Friends")
+        .expect("Failed to create a resource from the URL");
+    assert_eq!(
+        resource.expand,
+        vec![
+            expand_navigation("Trips", ExpandOptions::default()),
+            expand_navigation("Friends", ExpandOptions::default()),
+        ]
+    );
+    assert_eq!(resource.expand[0].navigation(), Some("Trips"));
+}
+
+#[test]
+fn can_parse_an_expand_with_nested_options() {
+    let expand = parse_expand(
+        "Trips($select=Name,Budget;$filter=Budget gt 1000;$orderby=Name desc;$top=2;$skip=1;$count=true;\
+         $expand=PlanItems($select=ConfirmationCode))",
+    )
+    .expect("Failed to parse $expand");
+
+    let plan_items = expand_navigation(
+        "PlanItems",
+        ExpandOptions {
+            select: parse_select("ConfirmationCode").unwrap(),
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        expand,
+        vec![expand_navigation(
+            "Trips",
+            ExpandOptions {
+                select: vec![select_property("Name"), select_property("Budget")],
+                filter: Some(binary(
+                    member("Budget"),
+                    BinaryOperator::Gt,
+                    Expression::Literal(Value::Integer(1000))
+                )),
+                order_by: vec![OrderBy {
+                    expression: member("Name"),
+                    direction: OrderByDirection::Desc,
+                }],
+                top: Some(2),
+                skip: Some(1),
+                count: true,
+                expand: vec![plan_items],
+                ..Default::default()
+            }
+        )]
+    );
+}
+
+#[test]
+fn can_parse_expand_references_levels_and_wildcards() {
+    let expand =
+        parse_expand("Friends/$ref($filter=contains(Name,'(');$top=5),Friends($levels=max),*($levels=2),*/$ref")
+            .expect("Failed to parse $expand");
+    assert_eq!(expand.len(), 4);
+
+    assert!(expand[0].reference);
+    assert_eq!(expand[0].navigation(), Some("Friends"));
+    assert_eq!(expand[0].options.top, Some(5));
+    assert!(expand[0].options.filter.is_some());
+
+    assert_eq!(expand[1].options.levels, Some(Levels::Max));
+
+    assert_eq!(expand[2].path, ExpandPath::All);
+    assert_eq!(expand[2].options.levels, Some(Levels::Count(2)));
+
+    assert_eq!(expand[3].path, ExpandPath::All);
+    assert!(expand[3].reference);
+}
+
+#[test]
+fn can_detect_invalid_expands() {
+    for expand in [
+        "",
+        "Trips,",
+        "Trips(",
+        "Trips($top=two)",
+        "Trips($unknown=1)",
+        "Trips($count=maybe)",
+        "Friends/$ref($select=Name)",
+        "*($top=1)",
+        "Trips($filter=Budget gt)",
+    ] {
+        let url = format!("People?$expand={expand}");
+        assert!(ODataResource::try_from(url.as_str()).is_err(), "{expand}");
+    }
+}
+
+#[test]
+fn can_parse_an_apply_pipeline() {
+    let resource = ODataResource::try_from(
+        "Sales?$apply=filter(Amount gt 0)/groupby((Category,Customer/Country),aggregate(Amount with sum as Total,$count as Count))",
+    )
+    .expect("Failed to parse $apply");
+    assert_eq!(resource.apply.len(), 2);
+
+    let Transformation::Filter(filter) = &resource.apply[0] else {
+        panic!("expected a filter, found {:?}", resource.apply[0]);
+    };
+    assert_eq!(filter, &parse_filter("Amount gt 0").unwrap());
+
+    let Transformation::GroupBy(properties, transformations) = &resource.apply[1] else {
+        panic!("expected a groupby, found {:?}", resource.apply[1]);
+    };
+    let properties: Vec<_> = properties.iter().map(|path| path.segments().join("/")).collect();
+    assert_eq!(properties, ["Category", "Customer/Country"]);
+    assert_eq!(
+        transformations,
+        &[Transformation::Aggregate(vec![
+            Aggregate {
+                expression: Some(parse_filter("Amount").unwrap()),
+                method: AggregateMethod::Sum,
+                alias: "Total".to_string(),
+            },
+            Aggregate {
+                expression: None,
+                method: AggregateMethod::Count,
+                alias: "Count".to_string(),
+            },
+        ])]
+    );
+}
+
+#[test]
+fn can_parse_apply_transformations() {
+    let apply = parse_apply(
+        "topcount(2,Amount)/bottomsum(100.5,Amount mul 2)/compute(Price mul Quantity as Total)/identity/aggregate(Amount with Ns.median as Median,Customer with countdistinct as Customers)",
+    )
+    .expect("Failed to parse $apply");
+
+    assert_eq!(
+        apply[0],
+        Transformation::TopBottom(TopBottom::TopCount, Value::Integer(2), parse_filter("Amount").unwrap())
+    );
+    assert_eq!(
+        apply[1],
+        Transformation::TopBottom(
+            TopBottom::BottomSum,
+            Value::Decimal(dec!(100.5)),
+            parse_filter("Amount mul 2").unwrap()
+        )
+    );
+    assert_eq!(
+        apply[2],
+        Transformation::Compute(vec![Compute {
+            expression: parse_filter("Price mul Quantity").unwrap(),
+            alias: "Total".to_string(),
+        }])
+    );
+    assert_eq!(apply[3], Transformation::Identity);
+
+    let Transformation::Aggregate(aggregates) = &apply[4] else {
+        panic!("expected an aggregate, found {:?}", apply[4]);
+    };
+    assert_eq!(aggregates[0].method, AggregateMethod::Custom("Ns.median".to_string()));
+    assert_eq!(aggregates[1].method, AggregateMethod::CountDistinct);
+}
+
+#[test]
+fn can_parse_concatenated_apply_sequences() {
+    let apply = parse_apply("concat(topcount(1,Amount),filter(Amount lt 10)/aggregate($count as Count))")
+        .expect("Failed to parse $apply");
+
+    let [Transformation::Concat(sequences)] = apply.as_slice() else {
+        panic!("expected a concat, found {apply:?}");
+    };
+    assert_eq!(sequences.len(), 2);
+    assert_eq!(sequences[0].len(), 1);
+    assert_eq!(sequences[1].len(), 2);
+}
+
+#[test]
+fn can_detect_invalid_applies() {
+    for apply in [
+        "",
+        "rollup(Category)",
+        "join(Items as Item)",
+        "groupby(Category)",
+        "groupby((Category),)",
+        "aggregate(Amount as Total)",
+        "aggregate(Amount with median as Total)",
+        "aggregate(Amount with sum)",
+        "aggregate($count as $Count)",
+        "topcount(Amount,2)",
+        "filter(Amount gt 0",
+        "filter(Amount gt 0)/",
+        "identity()",
+    ] {
+        let url = format!("Sales?$apply={apply}");
+        assert!(ODataResource::try_from(url.as_str()).is_err(), "{apply}");
+    }
+
+    assert!(matches!(
+        parse_apply("filter(Amount gt 0)/rollup(Category)"),
+        Err(ODataError::Syntax(SyntaxError { offset: 20, found, .. })) if found == "rollup"
+    ));
+}
+
+#[test]
+fn can_parse_computed_properties() {
+    let resource = ODataResource::try_from(
+        "Products?$compute=Price mul Quantity as Total,year(CreatedAt) as Year&$select=Name,Total&$orderby=Total desc",
+    )
+    .expect("Failed to parse $compute");
+
+    assert_eq!(
+        resource.compute,
+        vec![
+            Compute {
+                expression: parse_filter("Price mul Quantity").unwrap(),
+                alias: "Total".to_string(),
+            },
+            Compute {
+                expression: parse_filter("year(CreatedAt)").unwrap(),
+                alias: "Year".to_string(),
+            },
+        ]
+    );
+    assert_eq!(resource.select[1].property(), Some("Total"));
+}
+
+#[test]
+fn can_detect_invalid_computed_properties() {
+    for compute in [
+        "",
+        "Price mul Quantity",
+        "Price mul as Total",
+        "Price as Ns.Total",
+        "Price as Total,",
+        "Price as Total,Quantity as Total",
+    ] {
+        let url = format!("Products?$compute={compute}");
+        assert!(
+            matches!(ODataResource::try_from(url.as_str()), Err(ODataError::Syntax(_))),
+            "{compute}"
+        );
+    }
+}
+
+#[test]
+fn can_resolve_parameter_aliases() {
+    let resource = ODataResource::try_from(
+        "People(UserName=@user)?$filter=contains(FirstName,@name) and Age gt @age&$orderby=@sort desc&@user='russellwhyte'&@name=@first&@first='Rus'&@age=30&@sort=LastName",
+    )
+    .expect("Failed to resolve the parameter aliases");
+
+    assert_eq!(resource.entity.key, Some(Key::named([("UserName", "russellwhyte")])));
+    assert_eq!(
+        resource.filter,
+        Some(parse_filter("contains(FirstName,'Rus') and Age gt 30").unwrap())
+    );
+    assert_eq!(resource.order_by[0].expression, parse_filter("LastName").unwrap());
+}
+
+#[test]
+fn can_resolve_json_parameter_aliases() {
+    let resource = ODataResource::try_from(
+        r#"People?$filter=HomeAddress eq @address or contains(@names,FirstName)&@address={"City":"Oslo"}&@names=["Scott","Ronald"]"#,
+    )
+    .expect("Failed to resolve the JSON parameter aliases");
+
+    let address = Expression::Literal(Value::Json(serde_json::json!({ "City": "Oslo" })));
+    let names = Expression::Literal(Value::Json(serde_json::json!(["Scott", "Ronald"])));
+    assert_eq!(
+        resource.filter,
+        Some(Expression::Logical(
+            LogicalOperator::Or,
+            vec![
+                Expression::Binary(
+                    Box::new(parse_filter("HomeAddress").unwrap()),
+                    BinaryOperator::Eq,
+                    Box::new(address)
+                ),
+                Expression::Function(Function::Contains, vec![names, parse_filter("FirstName").unwrap()]),
+            ],
+        ))
+    );
+}
+
+#[test]
+fn can_detect_invalid_parameter_aliases() {
+    for url in [
+        "Products?$filter=Price gt @price",
+        "Products?$filter=Price gt @a&@a=@b&@b=@a",
+        "Products?$filter=Price gt @a&@a=@a",
+        "Products?$orderby=@sort&@other=Name",
+        "Products(Id=@id)?@id=Price add 1",
+        "Products?$filter=Price gt @a&@a={\"a\":",
+    ] {
+        assert!(
+            matches!(ODataResource::try_from(url), Err(ODataError::Syntax(_))),
+            "{url}"
+        );
+    }
+}
+
+#[test]
+fn can_limit_the_expansion_of_parameter_aliases() {
+    // each alias refers to the next one 20 times, so the expansion grows by 20 with every level
+    let references = |next: usize| vec![format!("@a{next}"); 20].join(" add ");
+    let aliases: Vec<String> = (1..8)
+        .map(|level| format!("@a{level}={}", references(level + 1)))
+        .collect();
+    let url = format!("Products?$filter=Price gt @a1&{}&@a8=1", aliases.join("&"));
+
+    let started = std::time::Instant::now();
+    let error = ODataResource::try_from(url.as_str()).expect_err("Expected the expansion to be rejected");
+    assert!(matches!(error, ODataError::Syntax(_)));
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+    // aliases resolved before still count their levels when they are referred to from deeper down
+    let chain: Vec<String> = (1..8).map(|level| format!("@a{level}=@a{}", level + 1)).collect();
+    let chain = format!("{}&@a8=1", chain.join("&"));
+    assert!(ODataResource::try_from(format!("Products?$filter=Price gt @a1&{chain}").as_str()).is_ok());
+    for filter in ["@b", "@a1 and Price gt @b"] {
+        let url = format!("Products?$filter=Price gt {filter}&@b=@a1&{chain}");
+        assert!(
+            matches!(ODataResource::try_from(url.as_str()), Err(ODataError::Syntax(_))),
+            "{url}"
+        );
+    }
+
+    // an alias referred to many times is resolved once
+    let resource = ODataResource::try_from(
+        "Products?$filter=Price gt @a and Price lt @a add @a&$orderby=@a&@a=@b mul 2&@b=Rating",
+    )
+    .expect("Failed to resolve the parameter aliases");
+    assert_eq!(
+        resource.filter,
+        Some(parse_filter("Price gt Rating mul 2 and Price lt Rating mul 2 add Rating mul 2").unwrap())
+    );
+}
+
+#[test]
+fn can_parse_typed_primitive_literals() {
+    let literal = |value: &str| {
+        parse_filter(&format!("Value eq {value}")).map(|filter| match filter {
+            Expression::Binary(_, _, right) => right.literal().cloned(),
+            _ => None,
+        })
+    };
+    let expected = [
+        ("3000000000", Value::Int64(3_000_000_000)),
+        ("1.5e10", Value::Double(1.5e10)),
+        ("-2E-3", Value::Double(-0.002)),
+        ("INF", Value::Double(f64::INFINITY)),
+        ("-INF", Value::Double(f64::NEG_INFINITY)),
+        (
+            "01234567-89ab-cdef-0123-456789abcdef",
+            Value::Guid("01234567-89ab-cdef-0123-456789abcdef".parse().unwrap()),
+        ),
+        (
+            "deadbeef-89ab-cdef-0123-456789abcdef",
+            Value::Guid("deadbeef-89ab-cdef-0123-456789abcdef".parse().unwrap()),
+        ),
+        (
+            "2024-02-29",
+            Value::Date(chrono::NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+        ),
+        (
+            "2024-02-29T10:30:00.5+01:00",
+            Value::DateTimeOffset(chrono::DateTime::parse_from_rfc3339("2024-02-29T10:30:00.5+01:00").unwrap()),
+        ),
+        (
+            "2024-02-29T10:30Z",
+            Value::DateTimeOffset(chrono::DateTime::parse_from_rfc3339("2024-02-29T10:30:00Z").unwrap()),
+        ),
+        (
+            "10:30:15.25",
+            Value::TimeOfDay(chrono::NaiveTime::from_hms_milli_opt(10, 30, 15, 250).unwrap()),
+        ),
+        (
+            "duration'P1DT2H30M0.5S'",
+            Value::Duration(
+                chrono::Duration::days(1) + chrono::Duration::minutes(150) + chrono::Duration::milliseconds(500),
+            ),
+        ),
+        ("duration'-PT1M'", Value::Duration(chrono::Duration::minutes(-1))),
+        ("binary'T0RhdGE'", Value::Binary(b"OData".to_vec())),
+        ("binary'T0RhdGE='", Value::Binary(b"OData".to_vec())),
+        (
+            "Ns.Color'Red,Blue'",
+            Value::Enum("Ns.Color".to_string(), "Red,Blue".to_string()),
+        ),
+        (
+            r#"{"City":"Oslo","Tags":["a]"]}"#,
+            Value::Json(serde_json::json!({ "City": "Oslo", "Tags": ["a]"] })),
+        ),
+    ];
+
+    for (text, value) in expected {
+        assert_eq!(literal(text).expect(text), Some(value), "{text}");
+    }
+
+    assert!(matches!(literal("NaN"), Ok(Some(Value::Double(value))) if value.is_nan()));
+}
+
+#[test]
+fn can_display_typed_primitive_literals() {
+    for text in [
+        "3000000000",
+        "1.5E10",
+        "-INF",
+        "NaN",
+        "01234567-89ab-cdef-0123-456789abcdef",
+        "2024-02-29",
+        "2024-02-29T10:30:00.500+01:00",
+        "10:30:15.250",
+        "duration'P1DT2H30M0.5S'",
+        "duration'-PT1M'",
+        "duration'PT0S'",
+        "binary'T0RhdGE'",
+        "Ns.Color'Red'",
+    ] {
+        let filter = parse_filter(&format!("Value eq {text}")).expect(text);
+        let Expression::Binary(_, _, right) = filter else {
+            panic!("expected a comparison for {text}");
+        };
+        assert_eq!(right.literal().map(|value| value.to_string()).as_deref(), Some(text));
+    }
+}
+
+#[test]
+fn can_detect_invalid_typed_literals() {
+    for filter in [
+        "Value eq duration'1D'",
+        "Value eq duration'P1.5D'",
+        "Value eq duration'PT'",
+        "Value eq binary'!!'",
+        "Value eq [1,2",
+        "Value eq {\"a\":}",
+        "Value eq 99999999999999999999999999999999",
+        "Value eq -1e400",
+        "Value eq 1.2.3",
+    ] {
+        assert!(matches!(parse_filter(filter), Err(ODataError::Syntax(_))), "{filter}");
+    }
+}
+
+#[test]
+fn can_detect_durations_out_of_range() {
+    for filter in [
+        "d eq duration'P999999999999D'",
+        "d eq duration'PT99999999999999999999H'",
+        "d eq duration'P100000000000DT1000000000000H'",
+    ] {
+        assert!(matches!(parse_filter(filter), Err(ODataError::Syntax(_))), "{filter}");
+    }
+
+    // the sum of the days and hours is still in range, so it is kept exactly
+    let filter = parse_filter("d eq duration'P100000000000DT99999999999H'").expect("Failed to parse the duration");
+    let Expression::Binary(_, _, right) = filter else {
+        panic!("expected a comparison");
+    };
+    let expected = 100_000_000_000 * 86_400_000 + 99_999_999_999 * 3_600_000;
+    assert_eq!(
+        right.literal(),
+        Some(&Value::Duration(chrono::Duration::milliseconds(expected)))
+    );
+    assert_eq!(right.to_string(), "duration'P104166666666DT15H'");
+}
+
+#[test]
+fn can_parse_composite_and_typed_keys() {
+    let resource = ODataResource::try_from("OrderItems(OrderId=1,ItemNo=2)").expect("Failed to parse a composite key");
+    assert_eq!(resource.entity.name, "OrderItems");
+    assert_eq!(
+        resource.entity.key,
+        Some(Key::named(vec![
+            ("OrderId".to_string(), Value::Integer(1)),
+            ("ItemNo".to_string(), Value::Integer(2)),
+        ]))
+    );
+    assert_eq!(resource.entity.key.unwrap().to_string(), "OrderId=1,ItemNo=2");
+
+    let resource = ODataResource::try_from("Orders(3000000000)/Items(Name='a,b=c',Size=1.5)/Product")
+        .expect("Failed to parse typed keys");
+    assert_eq!(resource.entity.key, Some(Key::single(Value::Int64(3_000_000_000))));
+    assert_eq!(
+        resource.relationships[0].key,
+        Some(Key::named(vec![
+            ("Name".to_string(), Value::String("a,b=c".to_string())),
+            ("Size".to_string(), Value::Decimal(dec!(1.5))),
+        ]))
+    );
+
+    let resource =
+        ODataResource::try_from("Devices(01234567-89ab-cdef-0123-456789abcdef)").expect("Failed to parse a guid key");
+    assert_eq!(
+        resource.entity.key,
+        Some(Key::single(Value::Guid(
+            "01234567-89ab-cdef-0123-456789abcdef".parse().unwrap()
+        )))
+    );
+}
+
+#[test]
+fn can_detect_invalid_keys() {
+    for url in [
+        "Orders(1,2)",
+        "Orders(Id=1,2)",
+        "Orders(Name)",
+        "Orders(Id=)",
+        "Orders('a)",
+    ] {
+        assert!(
+            matches!(ODataResource::try_from(url), Err(ODataError::Syntax(_))),
+            "{url}"
+        );
+    }
+}
+
+#[test]
+fn can_validate_keys_against_the_entity_type() {
+    let mut entity_type = EntityType::new("OrderItem".to_string());
+    entity_type.add_property("OrderId".to_string(), "Edm.Int64".to_string());
+    entity_type.add_property("ItemNo".to_string(), "Edm.Int32".to_string());
+    entity_type.set_key(["OrderId", "ItemNo"].into_iter());
+
+    let key = Key::named(vec![
+        ("ItemNo".to_string(), Value::Integer(2)),
+        ("OrderId".to_string(), Value::Int64(3_000_000_000)),
+    ]);
+    assert_eq!(
+        key.named_values(&entity_type).expect("Failed to validate the key"),
+        vec![
+            ("OrderId".to_string(), Value::Int64(3_000_000_000)),
+            ("ItemNo".to_string(), Value::Integer(2)),
+        ]
+    );
+
+    for key in [
+        Key::single(1),
+        Key::named([("OrderId", 1)]),
+        Key::named(vec![
+            ("OrderId".to_string(), Value::Integer(1)),
+            ("ItemNo".to_string(), Value::String("2".to_string())),
+        ]),
+        Key::named(vec![
+            ("OrderId".to_string(), Value::Integer(1)),
+            ("ItemNo".to_string(), Value::Integer(2)),
+            ("Extra".to_string(), Value::Integer(3)),
+        ]),
+    ] {
+        assert!(
+            matches!(key.named_values(&entity_type), Err(ODataError::InvalidKey(_))),
+            "{key}"
+        );
+    }
+
+    let mut entity_type = EntityType::new("Device".to_string());
+    entity_type.add_property("Id".to_string(), "Edm.Guid".to_string());
+    entity_type.set_key(["Id"].into_iter());
+    let guid = Value::Guid("01234567-89ab-cdef-0123-456789abcdef".parse().unwrap());
+    assert_eq!(
+        Key::single(guid.clone()).named_values(&entity_type).unwrap(),
+        vec![("Id".to_string(), guid)]
+    );
+}
+
+fn navigation_property(name: &str, edm_type: &str) -> NavigationProperty {
+    NavigationProperty {
+        name: name.to_string(),
+        _type: edm_type.to_string(),
+        nullable: None,
+        partner: None,
+        contains_target: None,
+        referential_constraint: None,
+        on_delete: None,
+        annotation: None,
+    }
+}
+
+fn trip_pin_edm() -> Edmx {
+    let mut person = EntityType::new("Person".to_string());
+    person.add_property("UserName".to_string(), "Edm.String".to_string());
+    person.add_property("FirstName".to_string(), "Edm.String".to_string());
+    person.set_key(["UserName"].into_iter());
+    person.navigation_property = Some(vec![
+        navigation_property("Friends", "Collection(Trippin.Person)"),
+        navigation_property("BestFriend", "Trippin.Person"),
+        navigation_property("Trips", "Collection(Trippin.Trip)"),
+    ]);
+
+    let mut trip = EntityType::new("Trip".to_string());
+    trip.add_property("TripId".to_string(), "Edm.Int32".to_string());
+    trip.add_property("Name".to_string(), "Edm.String".to_string());
+    trip.set_key(["TripId"].into_iter());
+
+    let mut employee = EntityType::new("Employee".to_string());
+    employee.base_type = Some("Trippin.Person".to_string());
+    employee.add_property("Cost".to_string(), "Edm.Int64".to_string());
+    employee.navigation_property = Some(vec![navigation_property("Peers", "Collection(Trippin.Person)")]);
+
+    let mut edm = Edmx::new("Trippin".to_string());
+    let schema = &mut edm.data_services.schema[0];
+    schema.entity_type = Some(vec![person, trip, employee]);
+    schema.entity_container = Some(vec![EntityContainer {
+        name: "Container".to_string(),
+        entity_set: Some(vec![EntitySet {
+            name: "People".to_string(),
+            entity_type: "Trippin.Person".to_string(),
+            include_annotations: None,
+            navigation_property_binding: None,
+            annotation: None,
+        }]),
+        singleton: Some(vec![Singleton {
+            name: "Me".to_string(),
+            _type: "Trippin.Person".to_string(),
+            navigation_property_binding: None,
+            annotation: None,
+        }]),
+        action_import: None,
+        function_import: None,
+        annotation: None,
+    }]);
+
+    edm
+}
+
+#[test]
+fn can_parse_keys_as_segments() {
+    let edm = trip_pin_edm();
+    let options = ParseOptions::default().with_edm(&edm).with_key_as_segment(true);
+    let parse = |url: &str| ODataResource::parse(url, &options).expect("Failed to parse a key as segment");
+
+    let resource = parse("People/russellwhyte");
+    assert_eq!(resource.entity.name, "People");
+    assert_eq!(resource.entity.key, Some(Key::single("russellwhyte")));
+    assert!(resource.property.is_none());
+
+    let resource = parse("People/O'Neil/FirstName/$value");
+    assert_eq!(resource.entity.key, Some(Key::single("O'Neil")));
+    assert_eq!(resource.property.unwrap(), "FirstName");
+    assert_eq!(resource.operation.unwrap(), Operation::Value);
+
+    let resource = parse("People/russellwhyte/Friends/scottketchum/Trips/1001/Name");
+    assert_eq!(resource.entity.key, Some(Key::single("russellwhyte")));
+    assert_eq!(resource.relationships.len(), 2);
+    assert_eq!(resource.relationships[0].name, "Friends");
+    assert_eq!(resource.relationships[0].key, Some(Key::single("scottketchum")));
+    assert_eq!(resource.relationships[1].name, "Trips");
+    assert_eq!(resource.relationships[1].key, Some(Key::single(1001)));
+    assert_eq!(resource.property.unwrap(), "Name");
+
+    // single-valued navigation properties and singletons don't take a key
+    let resource = parse("Me/BestFriend/FirstName");
+    assert_eq!(resource.entity.key, None);
+    assert_eq!(resource.relationships[0].name, "BestFriend");
+    assert_eq!(resource.relationships[0].key, None);
+    assert_eq!(resource.property.unwrap(), "FirstName");
+
+    // keys in parentheses are still accepted
+    let resource = parse("People('russellwhyte')/Friends/scottketchum");
+    assert_eq!(resource.property.unwrap(), "Friends('scottketchum')");
+
+    let resource = parse("People/$count");
+    assert_eq!(resource.entity.key, None);
+    assert_eq!(resource.operation.unwrap(), Operation::Count);
+
+    assert!(matches!(
+        ODataResource::parse("People/russellwhyte/Trips/first/Name", &options),
+        Err(ODataError::Syntax(_))
+    ));
+}
+
+#[test]
+fn can_parse_keys_as_segments_only_when_enabled() {
+    let resource = ODataResource::try_from("People/russellwhyte").expect("Failed to parse the resource");
+    assert_eq!(resource.entity.key, None);
+    assert_eq!(resource.property.unwrap(), "russellwhyte");
+
+    let mut category = EntityType::new("Categories".to_string());
+    category.add_property("Id".to_string(), "Edm.Int32".to_string());
+    category.set_key(["Id"].into_iter());
+    let model = ODataModel::default().with_entity_type(category);
+    let resource = model
+        .parse_resource("Categories/1")
+        .expect("Failed to parse the resource");
+    assert_eq!(resource.property.unwrap(), "1");
+
+    let model = model.with_key_as_segment(true);
+    let resource = model
+        .parse_resource("Categories/1")
+        .expect("Failed to parse the resource");
+    assert_eq!(resource.entity.key, Some(Key::single(1)));
+}
+
+#[test]
+fn can_parse_type_cast_segments() {
+    let resource = ODataResource::try_from("People/Trippin.Employee").expect("Failed to parse a type cast");
+    assert_eq!(resource.entity.name, "People");
+    assert_eq!(resource.entity.type_cast.as_deref(), Some("Trippin.Employee"));
+    assert!(resource.property.is_none());
+    assert_eq!(resource.entity.to_string(), "People/Trippin.Employee");
+
+    let resource = ODataResource::try_from("People('russellwhyte')/Trippin.Employee/Cost")
+        .expect("Failed to parse a type cast of an entity");
+    assert_eq!(resource.entity.key, Some(Key::single("russellwhyte")));
+    assert_eq!(resource.entity.type_cast.as_deref(), Some("Trippin.Employee"));
+    assert_eq!(resource.property.unwrap(), "Cost");
+
+    let resource = ODataResource::try_from("People/Trippin.Employee('russellwhyte')/Peers/$count")
+        .expect("Failed to parse a key after a type cast");
+    assert_eq!(resource.entity.key, Some(Key::single("russellwhyte")));
+    assert_eq!(resource.property.unwrap(), "Peers");
+    assert_eq!(resource.operation.unwrap(), Operation::Count);
+
+    let resource = ODataResource::try_from("People('russellwhyte')/Friends/Trippin.Employee/FirstName")
+        .expect("Failed to parse a type cast of a navigation property");
+    assert_eq!(resource.entity.type_cast, None);
+    assert_eq!(resource.relationships[0].name, "Friends");
+    assert_eq!(resource.relationships[0].type_cast.as_deref(), Some("Trippin.Employee"));
+    assert_eq!(resource.property.unwrap(), "FirstName");
+
+    for url in [
+        "People('russellwhyte')/Trippin.Employee('scottketchum')",
+        "People/Trippin.Employee/Trippin.Manager",
+    ] {
+        assert!(
+            matches!(ODataResource::try_from(url), Err(ODataError::Syntax(_))),
+            "{url}"
+        );
+    }
+}
+
+#[test]
+fn can_parse_type_casts_with_keys_as_segments() {
+    let edm = trip_pin_edm();
+    let options = ParseOptions::default().with_edm(&edm).with_key_as_segment(true);
+
+    // the derived type inherits the key, and adds navigation properties
+    let resource = ODataResource::parse(
+        "People/Trippin.Employee/russellwhyte/Peers/scottketchum/FirstName",
+        &options,
+    )
+    .expect("Failed to parse a type cast with keys as segments");
+    assert_eq!(resource.entity.key, Some(Key::single("russellwhyte")));
+    assert_eq!(resource.entity.type_cast.as_deref(), Some("Trippin.Employee"));
+    assert_eq!(resource.relationships[0].name, "Peers");
+    assert_eq!(resource.relationships[0].key, Some(Key::single("scottketchum")));
+    assert_eq!(resource.property.unwrap(), "FirstName");
+
+    // a qualified name that isn't a type is a key
+    let resource = ODataResource::parse("People/russell.whyte", &options).expect("Failed to parse a dotted key");
+    assert_eq!(resource.entity.key, Some(Key::single("russell.whyte")));
+    assert_eq!(resource.entity.type_cast, None);
+}
+
+fn type_function(function: TypeFunctionKind, operand: Option<Expression>, type_name: &str) -> Expression {
+    Expression::TypeFunction(TypeFunction {
+        function,
+        operand: operand.map(Box::new),
+        type_name: type_name.to_string(),
+    })
+}
+
+#[test]
+fn can_parse_isof_and_cast() {
+    assert_eq!(
+        parse_filter_of("People?$filter=isof(Trippin.Employee)"),
+        type_function(TypeFunctionKind::IsOf, None, "Trippin.Employee")
+    );
+    assert_eq!(
+        parse_filter_of("People?$filter=isof(BestFriend, Trippin.Employee) and Age gt 30"),
+        logical(
+            type_function(TypeFunctionKind::IsOf, Some(member("BestFriend")), "Trippin.Employee"),
+            LogicalOperator::And,
+            binary(
+                member("Age"),
+                BinaryOperator::Gt,
+                Expression::Literal(Value::Integer(30))
+            )
+        )
+    );
+    assert_eq!(
+        parse_filter_of("People?$filter=cast(Age,Edm.String) eq '30'"),
+        binary(
+            type_function(TypeFunctionKind::Cast, Some(member("Age")), "Edm.String"),
+            BinaryOperator::Eq,
+            string("30")
+        )
+    );
+
+    for url in [
+        "People?$filter=isof()",
+        "People?$filter=isof(Age)",
+        "People?$filter=cast(Age,String)",
+        "People?$filter=cast(Age,Edm.String",
+    ] {
+        assert!(
+            matches!(ODataResource::try_from(url), Err(ODataError::Syntax(_))),
+            "{url}"
+        );
+    }
+}
+
+#[test]
+fn can_parse_bound_functions_without_an_edm() {
+    let resource = ODataResource::try_from("People('russellwhyte')/Ns.GetFavoriteAirline()")
+        .expect("Failed to parse a bound function");
+    assert_eq!(resource.entity.key, Some(Key::single("russellwhyte")));
+    assert_eq!(resource.entity.type_cast, None);
+    assert_eq!(
+        resource.invocation,
+        Some(Invocation {
+            name: "Ns.GetFavoriteAirline".to_string(),
+            kind: InvocationKind::Function,
+            is_bound: true,
+            parameters: vec![],
+        })
+    );
+
+    let resource = ODataResource::try_from(
+        "People('russellwhyte')/Friends/Ns.GetFriendsTrips(userName=@user)/$count?@user='ronaldmundy'",
+    )
+    .expect("Failed to parse a bound function with parameters");
+    assert_eq!(resource.relationships[0].name, "Friends");
+    assert!(resource.property.is_none());
+    assert_eq!(resource.operation, Some(Operation::Count));
+    let invocation = resource.invocation.unwrap();
+    assert_eq!(invocation.name, "Ns.GetFriendsTrips");
+    assert_eq!(
+        invocation.parameters,
+        vec![("userName".to_string(), Value::String("ronaldmundy".to_string()))]
+    );
+}
+
+fn sample_edm() -> Edmx {
+    quick_xml::de::from_str(include_str!("../../odata-edm/test_data/sample_edm.xml")).expect("Failed to read the EDM")
+}
+
+const TRIP_PIN: &str = "Microsoft.OData.SampleService.Models.TripPin";
+
+#[test]
+fn can_parse_function_and_action_imports() {
+    let edm = sample_edm();
+    let options = ParseOptions::default().with_edm(&edm);
+
+    let resource = ODataResource::parse("GetNearestAirport(lat=33, lon=-118)/Name", &options)
+        .expect("Failed to parse a function import");
+    assert_eq!(resource.kind, ODataResourceKind::FunctionImport);
+    assert_eq!(resource.entity.name, "GetNearestAirport");
+    assert_eq!(resource.entity.key, None);
+    assert_eq!(resource.property.unwrap(), "Name");
+    assert_eq!(
+        resource.invocation,
+        Some(Invocation {
+            name: "GetNearestAirport".to_string(),
+            kind: InvocationKind::Function,
+            is_bound: false,
+            parameters: vec![
+                ("lat".to_string(), Value::Integer(33)),
+                ("lon".to_string(), Value::Integer(-118)),
+            ],
+        })
+    );
+
+    let resource = ODataResource::parse("ResetDataSource", &options).expect("Failed to parse an action import");
+    assert_eq!(resource.kind, ODataResourceKind::ActionImport);
+    assert_eq!(resource.invocation.unwrap().kind, InvocationKind::Action);
+
+    // without the EDM, the parameters are taken for a key
+    let resource = ODataResource::try_from("GetNearestAirport(lat=33,lon=-118)").expect("Failed to parse a key");
+    assert_eq!(resource.kind, ODataResourceKind::EntitySet);
+    assert!(resource.invocation.is_none());
+
+    for url in [
+        "GetNearestAirport(lat=33)",
+        "GetNearestAirport(lat=33,lon=-118,alt=0)",
+        "GetNearestAirport(lat=33,lat=34,lon=-118)",
+        "GetNearestAirport(33,-118)",
+        "ResetDataSource()",
+        "ResetDataSource/Name",
+    ] {
+        assert!(
+            matches!(ODataResource::parse(url, &options), Err(ODataError::Syntax(_))),
+            "{url}"
+        );
+    }
+}
+
+#[test]
+fn can_parse_bound_functions_and_actions() {
+    let edm = sample_edm();
+    let options = ParseOptions::default().with_edm(&edm);
+
+    let url = format!("People('russellwhyte')/Trips(0)/{TRIP_PIN}.GetInvolvedPeople()");
+    let resource = ODataResource::parse(&url, &options).expect("Failed to parse a bound function");
+    assert_eq!(resource.kind, ODataResourceKind::EntitySet);
+    assert_eq!(resource.relationships[0].name, "Trips");
+    assert_eq!(resource.relationships[0].key, Some(Key::single(0)));
+    let invocation = resource.invocation.unwrap();
+    assert_eq!(invocation.name, format!("{TRIP_PIN}.GetInvolvedPeople"));
+    assert!(invocation.is_bound);
+
+    let url = format!("People('russellwhyte')/{TRIP_PIN}.ShareTrip");
+    let resource = ODataResource::parse(&url, &options).expect("Failed to parse a bound action");
+    assert_eq!(resource.entity.type_cast, None);
+    let mut invocation = resource.invocation.unwrap();
+    assert_eq!(invocation.kind, InvocationKind::Action);
+    assert!(invocation.parameters.is_empty());
+
+    invocation
+        .set_body(r##"{"userName":"scottketchum","tripId":7,"@odata.type":"#ShareTrip"}"##)
+        .expect("Failed to read the action parameters");
+    assert_eq!(
+        invocation.parameters,
+        vec![
+            ("tripId".to_string(), Value::Integer(7)),
+            ("userName".to_string(), Value::String("scottketchum".to_string())),
+        ]
+    );
+    assert!(invocation.set_body("[1]").is_err());
+
+    let url = format!("People('russellwhyte')/{TRIP_PIN}.GetFriendsTrips(userName='ronaldmundy')");
+    let mut invocation = ODataResource::parse(&url, &options)
+        .expect("Failed to parse a bound function")
+        .invocation
+        .unwrap();
+    assert!(matches!(
+        invocation.set_body("{}"),
+        Err(ODataError::InvalidInvocation(_))
+    ));
+
+    for url in [
+        format!("People('russellwhyte')/{TRIP_PIN}.ShareTrip(userName='scottketchum')"),
+        format!("People('russellwhyte')/{TRIP_PIN}.GetFriendsTrips()"),
+        format!("People('russellwhyte')/{TRIP_PIN}.GetFavoriteAirline()/Name/Length"),
+    ] {
+        assert!(
+            matches!(ODataResource::parse(&url, &options), Err(ODataError::Syntax(_))),
+            "{url}"
+        );
+    }
+}
+
+#[test]
+fn can_parse_entity_references() {
+    let resource = ODataResource::try_from("People('russellwhyte')/Friends/$ref").expect("Failed to parse a reference");
+    assert_eq!(resource.property.unwrap(), "Friends");
+    assert_eq!(resource.operation, Some(Operation::Ref));
+
+    let resource = ODataResource::try_from("People('russellwhyte')/Friends/$ref?$id=People('scottketchum')")
+        .expect("Failed to parse a reference with an id");
+    assert_eq!(resource.operation, Some(Operation::Ref));
+    assert_eq!(resource.id.as_deref(), Some("People('scottketchum')"));
+}
+
+#[test]
+fn can_parse_service_resources() {
+    for (url, kind) in [
+        ("$metadata", ODataResourceKind::Metadata),
+        ("$batch", ODataResourceKind::Batch),
+        ("$all?$search=russell", ODataResourceKind::All),
+        (
+            "$crossjoin(Products, Sales)?$filter=Products/Id eq Sales/ProductId",
+            ODataResourceKind::CrossJoin(vec!["Products".to_string(), "Sales".to_string()]),
+        ),
+    ] {
+        let resource = ODataResource::try_from(url).expect("Failed to parse a service resource");
+        assert_eq!(resource.kind, kind, "{url}");
+    }
+
+    let resource = ODataResource::try_from("$all/Trippin.Person?$filter=FirstName eq 'Russell'")
+        .expect("Failed to parse $all with a type");
+    assert_eq!(resource.kind, ODataResourceKind::All);
+    assert_eq!(resource.entity.type_cast.as_deref(), Some("Trippin.Person"));
+    assert!(resource.filter.is_some());
+
+    let resource = ODataResource::try_from("$entity/Trippin.Person?$id=People('russellwhyte')&$select=FirstName")
+        .expect("Failed to parse $entity");
+    assert_eq!(resource.kind, ODataResourceKind::EntityId);
+    assert_eq!(resource.entity.name, "$entity");
+    assert_eq!(resource.entity.type_cast.as_deref(), Some("Trippin.Person"));
+    assert_eq!(resource.id.as_deref(), Some("People('russellwhyte')"));
+    assert_eq!(resource.select.len(), 1);
+
+    for url in [
+        "$entity",
+        "$metadata/People",
+        "$batch/1",
+        "$crossjoin()",
+        "$crossjoin(Products,'Sales')",
+        "$all/People",
+        "$unknown",
+    ] {
+        assert!(
+            matches!(ODataResource::try_from(url), Err(ODataError::Syntax(_))),
+            "{url}"
+        );
+    }
+}
+
+#[test]
+fn can_print_a_resource_as_a_canonical_url() {
+    for (url, canonical) in [
+        ("People('russellwhyte')", "People('russellwhyte')"),
+        ("People('O''Neil')/Friends/$count", "People('O''Neil')/Friends/$count"),
+        ("OrderItems(OrderId=1, ItemNo=2)", "OrderItems(OrderId=1,ItemNo=2)"),
+        ("Files('a%2fb%3fc')", "Files('a%2Fb%3Fc')"),
+        ("People/Trippin.Employee('russellwhyte')/Peers", "People('russellwhyte')/Trippin.Employee/Peers"),
+        (
+            "People('russellwhyte')/Trippin.GetFriendsTrips(userName='scott')/Name",
+            "People('russellwhyte')/Trippin.GetFriendsTrips(userName='scott')/Name",
+        ),
+        (
+            "People?$top=2&$filter=FirstName eq 'Scott' and (Age gt 30 or not Emails/any())&$orderby=LastName desc,FirstName",
+            "People?$filter=FirstName%20eq%20'Scott'%20and%20(Age%20gt%2030%20or%20not%20Emails/any())&$orderby=LastName%20desc,FirstName&$top=2",
+        ),
+        (
+            "Products?$filter=Price sub (Discount add 1) gt -(5) and Name in ('A%26B','C%2BD')",
+            "Products?$filter=Price%20sub%20(Discount%20add%201)%20gt%20-(5)%20and%20Name%20in%20('A%26B','C%2BD')",
+        ),
+        (
+            "People?$select=FirstName,Address($select=City)&$expand=Trips($filter=Budget gt 1000;$top=2),Friends/$ref",
+            "People?$select=FirstName,Address($select=City)&$expand=Trips($filter=Budget%20gt%201000;$top=2),Friends/$ref",
+        ),
+        (
+            "Sales?$apply=filter(Amount gt 0)/groupby((Product/Name),aggregate(Amount with sum as Total,$count as Count))",
+            "Sales?$apply=filter(Amount%20gt%200)/groupby((Product/Name),aggregate(Amount%20with%20sum%20as%20Total,$count%20as%20Count))",
+        ),
+        ("People?$search=russell%20OR%20scott&$count=true", "People?$search=russell%20OR%20scott&$count=true"),
+        ("People?$filter=FirstName eq @name&@name='Scott'", "People?$filter=FirstName%20eq%20'Scott'"),
+        ("People?$format=application/json;odata.metadata=none", "People?$format=application/json;odata.metadata=none"),
+        ("$crossjoin(Products,Sales)", "$crossjoin(Products,Sales)"),
+        ("$entity?$id=People('russellwhyte')", "$entity?$id=People('russellwhyte')"),
+    ] {
+        let resource = ODataResource::try_from(url).expect("Failed to parse the url");
+        assert_eq!(resource.to_relative_url(), canonical, "{url}");
+
+        let mut reparsed = ODataResource::try_from(canonical).expect("Failed to parse the canonical url");
+        reparsed.url = resource.url.clone();
+        assert_eq!(reparsed, resource, "{canonical}");
+    }
+}
+
+#[test]
+fn can_print_members_named_like_keywords() {
+    let filter = logical(
+        binary(member("null"), BinaryOperator::Eq, Expression::Literal(Value::Null)),
+        LogicalOperator::And,
+        not(binary(member("not"), BinaryOperator::Gt, member("A/INF"))),
+    );
+    let printed = filter.to_string();
+    assert_eq!(printed, "$it/null eq null and (not $it/not gt A/INF)");
+    assert_eq!(
+        parse_filter(&printed).expect("Failed to parse the printed filter"),
+        filter
+    );
+
+    let filter = parse_filter("$it/true/any(t:t eq $it/NaN)").expect("Failed to parse the filter");
+    let Expression::Lambda(lambda) = &filter else {
+        panic!("Expected a lambda, got {filter:?}");
+    };
+    assert_eq!(lambda.path, MemberPath(vec!["true".to_string()]));
+    assert_eq!(filter.to_string(), "$it/true/any(t:t eq $it/NaN)");
+    assert!(parse_filter("Tags/any(null:null eq 'x')").is_err());
+}
+
+#[test]
+fn can_print_function_and_action_imports() {
+    let edm = sample_edm();
+    let options = ParseOptions::default().with_edm(&edm);
+
+    let resource = ODataResource::parse("GetNearestAirport(lat = 33, lon = -118)", &options)
+        .expect("Failed to parse a function import");
+    assert_eq!(resource.to_relative_url(), "GetNearestAirport(lat=33,lon=-118)");
+
+    let resource = ODataResource::parse("ResetDataSource", &options).expect("Failed to parse an action import");
+    assert_eq!(resource.to_relative_url(), "ResetDataSource");
+}
+
+#[test]
+fn can_round_trip_a_resource_through_an_endpoint() {
+    let endpoint = ODataEndpoint::new("http://services.odata.org", Some("V4"), "TripPinService");
+    let resource = ODataResource::try_from("People('russellwhyte')/Trips?$filter=Name eq 'Paris %231'&$skip=1")
+        .expect("Failed to parse the url");
+
+    let url = endpoint.url_for(&resource);
+    assert_eq!(
+        url,
+        "http://services.odata.org/V4/TripPinService/People('russellwhyte')/Trips?$filter=Name%20eq%20'Paris%20%231'&$skip=1"
+    );
+
+    let mut reparsed = endpoint
+        .parse_resource(&url)
+        .expect("Failed to parse the url of the endpoint");
+    reparsed.url = resource.url.clone();
+    assert_eq!(reparsed, resource);
+}
+
+#[test]
+fn can_parse_relative_to_a_deep_service_root() {
+    let service_root = service_root::ServiceRoot::new("https://example.com/api/odata/v4/tenant").unwrap();
+    let options = resource::ParseOptions::default().with_service_root(&service_root);
+    let parse = |url: &str| ODataResource::parse(url, &options).expect("Failed to parse the url");
+
+    for url in [
+        "https://example.com/api/odata/v4/tenant/People('russellwhyte')/Trips?$top=2",
+        "https://example.com:443/api/odata/v4/tenant/People('russellwhyte')/Trips?$top=2",
+        "/api/odata/v4/tenant/People('russellwhyte')/Trips?$top=2",
+        "People('russellwhyte')/Trips?$top=2",
+    ] {
+        assert_eq!(
+            parse(url).to_relative_url(),
+            "People('russellwhyte')/Trips?$top=2",
+            "{url}"
+        );
+    }
+
+    let (_, path) = service_root.resolve("/api/odata/v4/tenant").unwrap();
+    assert_eq!(path, "");
+    assert_eq!(parse("$metadata").kind, ODataResourceKind::Metadata);
+}
+
+#[test]
+fn can_reject_urls_outside_the_service_root() {
+    let service_root = service_root::ServiceRoot::new("https://example.com/api/odata/v4/tenant/").unwrap();
+    let options = resource::ParseOptions::default().with_service_root(&service_root);
+
+    for url in [
+        "https://example.org/api/odata/v4/tenant/People",
+        "http://example.com/api/odata/v4/tenant/People",
+        "https://example.com:8443/api/odata/v4/tenant/People",
+        "https://example.com/api/odata/v4/other/People",
+        "/api/odata/v4/tenant-2/People",
+        "/api/odata/v4/tenant/../other/People",
+        "/People",
+    ] {
+        assert!(
+            matches!(
+                ODataResource::parse(url, &options),
+                Err(error::ODataError::OutsideServiceRoot(..))
+            ),
+            "{url}"
+        );
+    }
+}
+
+#[test]
+fn can_parse_relative_to_a_service_root_path() {
+    let model = ODataModel::new("/V4/UserService");
+    let resource = model.parse_resource("/V4/UserService/Users(1)").unwrap();
+    assert_eq!(resource.entity.name, "Users");
+
+    // without a host, only the path of absolute URLs is checked
+    let resource = model
+        .parse_resource("http://localhost:8080/V4/UserService/Users")
+        .unwrap();
+    assert_eq!(resource.entity.name, "Users");
+    assert!(model.parse_resource("/V4/OrderService/Orders").is_err());
+}
+
+#[test]
+fn can_parse_urls_of_an_endpoint_with_a_deep_base_url() {
+    let endpoint = ODataEndpoint::new("https://example.com/api/odata", Some("v4"), "tenant");
+    assert_eq!(
+        endpoint.service_root().unwrap().to_string(),
+        "https://example.com/api/odata/v4/tenant/"
+    );
+
+    let resource = endpoint
+        .parse_resource("https://example.com/api/odata/v4/tenant/Airports('KSFO')/Location")
+        .unwrap();
+    assert_eq!(resource.entity.name, "Airports");
+    assert_eq!(resource.property.as_deref(), Some("Location"));
+
+    let error = endpoint
+        .parse_resource("https://services.odata.org/V4/TripPinService/People")
+        .expect_err("Expected the url of another service to be rejected");
+    assert_eq!(
+        error.to_string(),
+        "invalid OData Url; https://services.odata.org/V4/TripPinService/People is outside the service root \
+         https://example.com/api/odata/v4/tenant/"
+    );
+}
+
+mod round_trip {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn identifier() -> impl Strategy<Value = String> {
+        // also the words that would be read as literals or operators, like `not` or `null`
+        let reserved = [
+            "true", "false", "null", "INF", "NaN", "not", "and", "or", "eq", "has", "any", "desc",
+        ];
+        prop_oneof![
+            "[A-Za-z_][A-Za-z0-9_]{0,6}",
+            prop::sample::select(reserved.to_vec()).prop_map(str::to_string),
+        ]
+    }
+
+    fn literal() -> impl Strategy<Value = Value> {
+        prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Boolean),
+            any::<i32>().prop_map(Value::Integer),
+            (any::<i64>(), 1..6u32)
+                .prop_map(|(mantissa, scale)| Value::Decimal(rust_decimal::Decimal::new(mantissa, scale))),
+            "[ -~äé€]{0,8}".prop_map(Value::String),
+        ]
+    }
+
+    fn expression() -> impl Strategy<Value = Expression> {
+        let leaf = prop_oneof![
+            literal().prop_map(Expression::Literal),
+            prop::collection::vec(identifier(), 1..3).prop_map(|path| Expression::Member(MemberPath(path))),
+        ];
+
+        leaf.prop_recursive(4, 24, 2, |inner| {
+            let operator = prop_oneof![
+                Just(BinaryOperator::Eq),
+                Just(BinaryOperator::Ne),
+                Just(BinaryOperator::Gt),
+                Just(BinaryOperator::Le),
+                Just(BinaryOperator::Add),
+                Just(BinaryOperator::Sub),
+                Just(BinaryOperator::Mul),
+                Just(BinaryOperator::Mod),
+                Just(BinaryOperator::Has),
+            ];
+            let logical_operator = prop_oneof![Just(LogicalOperator::And), Just(LogicalOperator::Or)];
+
+            prop_oneof![
+                (inner.clone(), operator, inner.clone())
+                    .prop_map(|(left, operator, right)| binary(left, operator, right)),
+                (inner.clone(), logical_operator, inner.clone())
+                    .prop_map(|(left, operator, right)| logical(left, operator, right)),
+                inner.clone().prop_map(not),
+                inner
+                    .clone()
+                    .prop_map(|operand| Expression::Unary(UnaryOperator::Negate, Box::new(operand))),
+                (inner.clone(), inner)
+                    .prop_map(|(left, right)| Expression::Function(Function::Contains, vec![left, right])),
+            ]
+        })
+    }
+
+    fn key() -> impl Strategy<Value = Option<Key>> {
+        let named_value = || (identifier(), literal());
+        prop_oneof![
+            Just(None),
+            "[ -~]{0,8}".prop_map(|value| Some(Key::single(value))),
+            literal().prop_map(|value| Some(Key::single(value))),
+            named_value().prop_map(|value| Some(Key::named([value]))),
+            prop::collection::vec(named_value(), 2..4).prop_map(|values| Some(Key::named(values))),
+        ]
+    }
+
+    fn format() -> impl Strategy<Value = ODataFormat> {
+        let media_type = prop::sample::select(vec!["application/json", "application/xml", "json", "xml"]);
+        let metadata = prop::sample::select(vec!["none", "minimal", "full"]);
+        (media_type, metadata, any::<bool>()).prop_map(|(format, metadata, streaming)| ODataFormat {
+            format: format.to_string(),
+            metadata: ODataMetaData::from(metadata),
+            streaming,
+        })
+    }
+
+    fn order_by() -> impl Strategy<Value = Vec<OrderBy>> {
+        let direction = prop_oneof![Just(OrderByDirection::Asc), Just(OrderByDirection::Desc)];
+        prop::collection::vec(
+            (expression(), direction).prop_map(|(expression, direction)| OrderBy { expression, direction }),
+            0..3,
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn can_parse_a_printed_resource(
+            name in identifier(),
+            key in key(),
+            property in prop::option::of(identifier()),
+            filter in prop::option::of(expression()),
+            order_by in order_by(),
+            search in prop::option::of("[ -~]{1,8}"),
+            top in prop::option::of(any::<u32>()),
+            skip in prop::option::of(any::<u32>()),
+            requested_format in format(),
+        ) {
+            let resource = ODataResource {
+                entity: Entity { name, key, type_cast: None },
+                property,
+                filter,
+                order_by,
+                search,
+                top,
+                skip,
+                requested_format,
+                ..Default::default()
+            };
+
+            let url = resource.to_relative_url();
+            let mut parsed = ODataResource::try_from(url.as_str()).map_err(|error| TestCaseError::fail(format!("{url}: {error}")))?;
+            parsed.url = String::new();
+            prop_assert_eq!(&parsed, &resource, "{}", url);
+            prop_assert_eq!(parsed.to_relative_url(), url);
+        }
+    }
+}
+
+#[test]
+fn can_build_the_resource_the_parser_gives() {
+    use query::{lit, prop, ODataQuery};
+
+    for (query, url) in [
+        (
+            ODataQuery::entity_set("People")
+                .key("russellwhyte")
+                .filter(prop("Age").gt(30).and(prop("FirstName").equals("Scott").or(!prop("Emails").contains("@"))))
+                .order_by_desc("Name")
+                .order_by("Address/City")
+                .top(10)
+                .skip(20)
+                .select(["FirstName", "Address/City"]),
+            "People('russellwhyte')?$filter=Age gt 30 and (FirstName eq 'Scott' or not contains(Emails,'@'))&$orderby=Name desc,Address/City&$top=10&$skip=20&$select=FirstName,Address/City",
+        ),
+        (
+            ODataQuery::entity_set("People")
+                .key("russellwhyte")
+                .navigate("Trips")
+                .key(1)
+                .navigate("PlanItems")
+                .count(),
+            "People('russellwhyte')/Trips(1)/PlanItems/$count",
+        ),
+        (
+            ODataQuery::entity_set("People").key("russellwhyte").navigate("Trips").key(1),
+            "People('russellwhyte')/Trips(1)",
+        ),
+        (
+            ODataQuery::entity_set("OrderItems").key([("OrderId", 1), ("ItemNo", 2)]),
+            "OrderItems(OrderId=1,ItemNo=2)",
+        ),
+        (
+            ODataQuery::entity_set("Products")
+                .filter((prop("Price") * prop("Quantity") - 5).ge(lit(dec!(10.50))))
+                .filter(prop("Category").is_in(["Food", "Drinks"]))
+                .order_by_expression(-prop("Rating"), OrderByDirection::Asc)
+                .expand(["Supplier"])
+                .search("organic")
+                .inline_count(),
+            "Products?$filter=Price mul Quantity sub 5 ge 10.50 and Category in ('Food','Drinks')&$orderby=-Rating&$expand=Supplier&$search=organic&$count=true",
+        ),
+        (
+            ODataQuery::entity_set("Orders")
+                .key(7i64)
+                .navigate("Items")
+                .filter(prop("Id").equals(30i64).or(prop("Id").lt(3_000_000_000i64)))
+                .filter(prop("Quantity").le(5).and(prop("Quantity").not_equals(0)))
+                .filter(prop("Price").gt(1).or(prop("Price").ge(lit(dec!(0.5)))).or(prop("Price").lt(0))),
+            "Orders(7)/Items?$filter=(Id eq 30 or Id lt 3000000000) and (Quantity le 5 and Quantity ne 0) and (Price gt 1 or Price ge 0.5 or Price lt 0)",
+        ),
+    ] {
+        let printed = query.to_relative_url();
+        let mut parsed = ODataResource::try_from(url).expect("Failed to parse the url");
+        parsed.url = String::new();
+        assert_eq!(query.build(), parsed, "{url}");
+        assert_eq!(
+            ODataResource::try_from(printed.as_str()).expect("Failed to parse the printed query").to_relative_url(),
+            printed
+        );
+    }
+}
+
+#[test]
+fn can_report_the_position_of_syntax_errors() {
+    for (url, option, offset, expected, found) in [
+        ("People?$filter=Name eq", "$filter", 7, "an operand", ""),
+        (
+            "People?$filter=Name eq 'Scott' and (Age gt 30",
+            "$filter",
+            30,
+            "')'",
+            "",
+        ),
+        ("People?$filter=foo(Name)", "$filter", 0, "a canonical function", "foo"),
+        (
+            "People?$filter=Name eq 'Scott",
+            "$filter",
+            8,
+            "a string with a closing quote",
+            "'Scott",
+        ),
+        ("People?$orderby=Name desc Age", "$orderby", 10, "',' or the end", "Age"),
+        ("People?$top=-1", "$top", 0, "a non-negative integer", "-1"),
+        ("People?$count=yes", "$count", 0, "true or false", "yes"),
+        (
+            "People?$select=FirstName,Address(City)",
+            "$select",
+            18,
+            "a nested $select",
+            "City",
+        ),
+        (
+            "People?$expand=Trips($filter=Budget gt;$top=2)",
+            "$expand",
+            23,
+            "an operand",
+            "",
+        ),
+        (
+            "People?$expand=Trips($levels=all)",
+            "$expand",
+            14,
+            "a number of levels or 'max'",
+            "all",
+        ),
+        (
+            "Sales?$apply=groupby((Amount add 1))",
+            "$apply",
+            9,
+            "a property to group by",
+            "Amount add 1",
+        ),
+        ("People?$filter=Name eq @n&@n=(1", "@n", 2, "')'", ""),
+    ] {
+        let Err(ODataError::Syntax(error)) = ODataResource::try_from(url) else {
+            panic!("Expected a syntax error for {url}");
+        };
+        assert_eq!(
+            error,
+            SyntaxError {
+                option: Some(option.to_string()),
+                offset,
+                expected: expected.to_string(),
+                found: found.to_string(),
+            },
+            "{url}"
+        );
+    }
+
+    let error = ODataResource::try_from("People?$filter=Name eq").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid OData query; $filter at position 7; expected an operand, found the end"
+    );
+}
+
+#[test]
+fn can_report_the_position_of_path_and_alias_errors() {
+    let edm = sample_edm();
+    let options = ParseOptions::default().with_edm(&edm);
+
+    for (url, option, offset, expected, found) in [
+        ("T(", None, 1, "a key in balanced brackets", "("),
+        ("Orders(Id=1,2)", None, 12, "the name of a key property", "2"),
+        (
+            "People('russellwhyte')/Friends(x y)/Name",
+            None,
+            33,
+            "end of expression",
+            "y",
+        ),
+        (
+            "People/Trippin.Employee/Trippin.Manager",
+            None,
+            24,
+            "a single type cast for 'People'",
+            "Trippin.Manager",
+        ),
+        ("$entity", None, 7, "the $id query option for $entity", ""),
+        (
+            "$crossjoin(Products,'Sales')",
+            None,
+            20,
+            "the name of an entity set",
+            "'Sales'",
+        ),
+        (
+            "GetNearestAirport(lat=33,lon=-118,alt=0)",
+            None,
+            34,
+            "a parameter of 'GetNearestAirport'",
+            "alt",
+        ),
+        (
+            "ResetDataSource/Name",
+            None,
+            16,
+            "the end of the path after an action",
+            "Name",
+        ),
+        (
+            "Products(Id=@id)?@id=Price add 1",
+            None,
+            12,
+            "a literal to be used as a key",
+            "@id",
+        ),
+        (
+            "Products?$compute=Price as Total,Quantity as Total",
+            Some("$compute"),
+            27,
+            "an alias that isn't used yet",
+            "Total",
+        ),
+        (
+            "Products?$filter=Price gt @price",
+            Some("$filter"),
+            9,
+            "a defined parameter alias",
+            "@price",
+        ),
+        (
+            "Products?$filter=Price gt @a&@a=1 add @b&@b=@a",
+            Some("@b"),
+            0,
+            "an alias that doesn't refer to itself",
+            "@a",
+        ),
+    ] {
+        let Err(ODataError::Syntax(error)) = ODataResource::parse(url, &options) else {
+            panic!("Expected a syntax error for {url}");
+        };
+        assert_eq!(
+            error,
+            SyntaxError {
+                option: option.map(str::to_string),
+                offset,
+                expected: expected.to_string(),
+                found: found.to_string(),
+            },
+            "{url}"
+        );
+    }
+
+    let error = ODataResource::try_from("T(").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid OData query; at position 1; expected a key in balanced brackets, found '('"
+    );
+}
+
+#[test]
+fn can_reject_deeply_nested_input() {
+    let long_or = vec!["Age eq 1"; 100].join(" or ");
+    assert!(parse_filter(&long_or).is_ok());
+
+    for filter in [
+        format!("{}1{}", "(".repeat(100_000), ")".repeat(100_000)),
+        "not ".repeat(100_000) + "Active",
+        "-".repeat(100_000) + "Age",
+        "Age add ".repeat(100_000) + "1",
+        "Friends/any(f:".repeat(10_000) + "true" + &")".repeat(10_000),
+    ] {
+        assert!(
+            matches!(parse_filter(&filter), Err(ODataError::Syntax(_))),
+            "{}",
+            &filter[..20]
+        );
+    }
+
+    for url in [
+        format!("People?$expand={}", "Friends($expand=".repeat(10_000)),
+        format!("People?$select={}", "Address($select=".repeat(10_000)),
+        format!("Sales?$apply={}", "groupby((Amount),".repeat(10_000)),
+    ] {
+        assert!(ODataResource::try_from(url.as_str()).is_err(), "{}", &url[..30]);
+    }
+
+    let aliases: Vec<String> = (0..10_000).map(|i| format!("@a{i}=@a{}", i + 1)).collect();
+    let url = format!("People?$filter=Age eq @a0&{}", aliases.join("&"));
+    assert!(matches!(
+        ODataResource::try_from(url.as_str()),
+        Err(ODataError::Syntax(_))
+    ));
+}
+
+#[test]
+fn can_bind_a_resource_to_the_edm() {
+    let edm = sample_edm();
+    let bind = |url: &str| {
+        let options = ParseOptions::default().with_edm(&edm);
+        let resource = ODataResource::parse(url, &options).expect("Failed to parse the resource");
+        resource
+            .bind(&edm)
+            .unwrap_or_else(|error| panic!("Failed to bind {url}: {error}"))
+    };
+
+    let bound = bind(
+        "People('russellwhyte')/Trips?$filter=Budget gt 1000 and PlanItems/any(p:p/ConfirmationCode eq 'X')\
+        &$orderby=Name&$select=Name,Tags&$expand=Photos($filter=Name ne null)",
+    );
+    assert_eq!(bound.entity_type.as_deref(), Some(format!("{TRIP_PIN}.Trip").as_str()));
+    assert!(bound.is_collection);
+    let Some(Expression::Logical(_, operands)) = bound.resource.filter else {
+        panic!("Expected the filter to be kept");
+    };
+    // the integer is compared with an Edm.Single
+    assert_eq!(
+        operands[0],
+        binary(
+            member("Budget"),
+            BinaryOperator::Gt,
+            Expression::Literal(Value::Double(1000.0))
+        )
+    );
+
+    let bound = bind("Airports('KSFO')/Location/City/Name");
+    assert_eq!(bound.entity_type, None);
+    assert!(!bound.is_collection);
+
+    let bound = bind(&format!(
+        "People('russellwhyte')/Trips(1)/PlanItems/{TRIP_PIN}.Flight?$expand=Airline,From($select=Location/Address)"
+    ));
+    assert_eq!(bound.entity_type, Some(format!("{TRIP_PIN}.Flight")));
+
+    bind("Airports?$filter=Location/City/Region eq 'CA' and contains(Name,'San')&$select=Location/City");
+    bind(&format!(
+        "People?$filter=Gender eq {TRIP_PIN}.PersonGender'Female' or Gender eq 'Male'"
+    ));
+    bind("People?$filter=Emails/any(e:e eq 'x') and Friends/$count gt 2 and $it/FirstName eq 'Scott'");
+    // a dynamic property of an open type
+    bind("People?$filter=Nickname eq 'Rusty'");
+    bind("Airlines?$compute=length(Name) as NameLength&$apply=groupby((Name),aggregate($count as Total))&$orderby=Total desc,NameLength");
+    bind("Me/Friends");
+    bind("$metadata");
+}
+
+#[test]
+fn can_report_all_diagnostics_of_a_resource() {
+    let edm = sample_edm();
+    let diagnostics = |url: &str| match ODataResource::try_from(url)
+        .expect("Failed to parse the resource")
+        .bind(&edm)
+    {
+        Err(ODataError::Binding(diagnostics)) => diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic.kind, diagnostic.option, diagnostic.target))
+            .collect::<Vec<_>>(),
+        result => panic!("Expected diagnostics for {url}, got {result:?}"),
+    };
+    let option = |name: &str| Some(name.to_string());
+
+    assert_eq!(
+        diagnostics("Airports?$filter=Name eq 1 or Location/Town eq 'x'&$orderby=IataCode/Length&$expand=Name"),
+        vec![
+            (DiagnosticKind::TypeMismatch, option("$filter"), "Name".to_string()),
+            (
+                DiagnosticKind::UnknownProperty,
+                option("$filter"),
+                "Location/Town".to_string()
+            ),
+            (
+                DiagnosticKind::UnknownProperty,
+                option("$orderby"),
+                "IataCode/Length".to_string()
+            ),
+            (DiagnosticKind::InvalidPath, option("$expand"), "Name".to_string()),
+        ]
+    );
+    assert_eq!(
+        diagnostics("Planes"),
+        vec![(DiagnosticKind::UnknownResource, None, "Planes".to_string())]
+    );
+    assert_eq!(
+        diagnostics("Airports(1)/Location"),
+        vec![(DiagnosticKind::InvalidKey, None, "Airports(1)".to_string())]
+    );
+    assert_eq!(
+        diagnostics("People('russellwhyte')/Trips/Name"),
+        vec![(DiagnosticKind::InvalidPath, None, "Name".to_string())]
+    );
+    assert_eq!(
+        diagnostics("People?$filter=Trips/Name eq 'x' and isof(Ns.Robot)&$select=Foo"),
+        vec![
+            (DiagnosticKind::InvalidPath, option("$filter"), "Trips/Name".to_string()),
+            (DiagnosticKind::UnknownType, option("$filter"), "Ns.Robot".to_string()),
+        ]
+    );
+    assert_eq!(
+        diagnostics(&format!(
+            "People?$filter=Gender eq {TRIP_PIN}.PersonGender'Other'&$select=Emails/Foo"
+        )),
+        vec![
+            (DiagnosticKind::TypeMismatch, option("$filter"), "Gender".to_string()),
+            (DiagnosticKind::InvalidPath, option("$select"), "Emails/Foo".to_string()),
+        ]
+    );
+
+    let Err(error) = ODataModel::default()
+        .with_edm(sample_edm())
+        .bind(ODataResource::try_from("Airlines?$filter=Code eq 'AA'").expect("Failed to parse the resource"))
+    else {
+        panic!("Expected the unknown property to be reported");
+    };
+    assert_eq!(
+        error.to_string(),
+        "invalid OData request; $filter: 'Code' is not a property of 'Airline'"
+    );
+}
+
+mod no_panics {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Strings made of the pieces of OData URLs, which get further into the parser than random characters
+    fn url_like() -> impl Strategy<Value = String> {
+        let pieces = prop::sample::select(vec![
+            "People",
+            "/",
+            "(",
+            ")",
+            "'",
+            ",",
+            "?",
+            "&",
+            "=",
+            "$filter=",
+            "$expand=",
+            "$select=",
+            "$orderby=",
+            "$apply=",
+            "$compute=",
+            "$top=",
+            "$count",
+            "$ref",
+            "$entity",
+            "$crossjoin",
+            " eq ",
+            " and ",
+            "not ",
+            "-",
+            "1",
+            "2.5",
+            "1e5",
+            "@a",
+            "@a=",
+            "[",
+            "]",
+            "{",
+            "}",
+            "\"",
+            "Name",
+            "Ns.Type",
+            "any(",
+            "x:",
+            ";",
+            "%",
+            "%27",
+            "%2F",
+            "duration'",
+            "binary'",
+            "Ns.Color'",
+            "groupby((",
+            "aggregate(",
+            " with sum as T",
+            "cast(",
+            "isof(",
+            "$it",
+            "*",
+            ".",
+            "2024-01-15",
+            "INF",
+            "é",
+        ]);
+        prop::collection::vec(pieces, 0..40).prop_map(|pieces| pieces.concat())
+    }
+
+    proptest! {
+        #[test]
+        fn can_parse_any_input_without_panicking(input in prop_oneof![url_like(), any::<String>()]) {
+            if let Ok(resource) = ODataResource::try_from(input.as_str()) {
+                let _ = ODataResource::try_from(resource.to_relative_url().as_str());
+            }
+            let _ = parse_filter(&input);
+            let _ = ODataResource::parse(&input, &ParseOptions::default().with_edm(&trip_pin_edm()).with_key_as_segment(true));
+        }
+    }
+}
+
+#[test]
+fn can_parse_long_chains_of_logical_operators() {
+    let clauses: Vec<String> = (0..1000).map(|id| format!("Id eq {id}")).collect();
+    let url = format!("Products?$filter={}&$orderby=Id", clauses.join(" or "));
+    let resource = ODataResource::try_from(url.as_str()).expect("Failed to parse a long chain of or");
+    let Some(Expression::Logical(LogicalOperator::Or, operands)) = &resource.filter else {
+        panic!("Expected a single chain of or");
+    };
+    assert_eq!(operands.len(), 1000);
+    assert_eq!(operands[999], parse_filter("Id eq 999").unwrap());
+
+    let reparsed = ODataResource::try_from(resource.to_relative_url().as_str()).expect("Failed to parse the url");
+    assert_eq!(reparsed.filter, resource.filter);
+
+    let filter = vec!["Age gt 1"; 1000].join(" and ");
+    assert!(parse_filter(&filter).is_ok());
+
+    // brackets nest the chains, and a mix of operators keeps the precedence of and
+    assert_eq!(
+        parse_filter("A or (B or C) or D and E").unwrap(),
+        Expression::Logical(
+            LogicalOperator::Or,
+            vec![
+                member("A"),
+                logical(member("B"), LogicalOperator::Or, member("C")),
+                logical(member("D"), LogicalOperator::And, member("E")),
+            ]
+        )
+    );
+    assert_eq!(
+        parse_filter("A or (B or C) or D and E").unwrap().to_string(),
+        "A or (B or C) or D and E"
+    );
+}
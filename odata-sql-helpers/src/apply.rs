@@ -0,0 +1,131 @@
+//! Translate the $apply transformations of the Data Aggregation extension into grouped and aggregated selects.
+
+use crate::filter::{column_name, unsupported, FilterBuilder};
+use crate::{ColumnList, ColumnValue};
+use heck::ToSnakeCase;
+use odata_model::apply::{Aggregate, AggregateMethod, Transformation};
+use odata_model::error::ODataResult;
+use odata_model::expression::{Expression, MemberPath};
+use sea_orm::{
+    sea_query::{Asterisk, Expr, Func, SimpleExpr},
+    ColumnDef, ColumnType, ColumnTypeTrait, EntityTrait, QueryFilter, QuerySelect, Select,
+};
+
+/// The query while the transformations are applied, with the columns of the aggregated result
+pub(crate) struct Aggregation<'b, E: EntityTrait> {
+    pub query: Select<E>,
+    builder: &'b FilterBuilder<'b>,
+    /// The grouping properties and aggregate aliases, by their snake cased name; none while the rows are not grouped
+    pub result: Option<ColumnList>,
+    /// Whether the groups are aggregated, i.e. filters apply to the aggregated values instead of the rows
+    aggregated: bool,
+}
+
+impl<'b, E: EntityTrait> Aggregation<'b, E> {
+    pub fn new(query: Select<E>, builder: &'b FilterBuilder<'b>) -> Self {
+        Self {
+            query,
+            builder,
+            result: None,
+            aggregated: false,
+        }
+    }
+
+    /// Apply the transformations in order; filters before the grouping become WHERE conditions and filters after it
+    /// HAVING conditions. Transformations that have no SQL translation yet, like `topcount`, `concat` and `compute`,
+    /// or regrouping an aggregated result, fail with [`ODataError::Unsupported`].
+    pub fn apply(mut self, transformations: &[Transformation]) -> ODataResult<Self> {
+        for transformation in transformations {
+            self = match transformation {
+                Transformation::Identity => self,
+                Transformation::Filter(filter) => self.filter(filter)?,
+                Transformation::GroupBy(properties, transformations) if self.result.is_none() => {
+                    let mut grouped = self.group_by(properties)?.apply(transformations)?;
+                    grouped.aggregated = true;
+                    grouped
+                }
+                Transformation::Aggregate(aggregates) if !self.aggregated => self.aggregate(aggregates)?,
+                transformation => return Err(unsupported(format!("'{transformation}'"))),
+            };
+        }
+
+        Ok(self)
+    }
+
+    fn filter(mut self, filter: &Expression) -> ODataResult<Self> {
+        match &self.result {
+            Some(result) if self.aggregated => {
+                let builder = FilterBuilder::new(result, self.builder.backend);
+                self.query = self.query.having(builder.build_condition(filter)?);
+            }
+            _ => {
+                self.query = self.query.filter(self.builder.build_condition(filter)?);
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Group by the columns of the properties, which are resolved like the properties of a filter, e.g. through a
+    /// complex property or a joined navigation property. Later transformations refer to a grouping property by its
+    /// path, e.g. `user_last_name` for `User/LastName`.
+    fn group_by(mut self, properties: &[MemberPath]) -> ODataResult<Self> {
+        let mut result = ColumnList::default();
+        self.query = self.query.select_only();
+
+        for property in properties {
+            let column = self.builder.build_column(&Expression::Member(property.clone()))?;
+            self.query = self.query.expr(column.column.clone()).group_by(column.column.clone());
+            result.push(column_name(property.segments()), column);
+        }
+
+        self.result = Some(result);
+        Ok(self)
+    }
+
+    fn aggregate(mut self, aggregates: &[Aggregate]) -> ODataResult<Self> {
+        let mut result = self.result.take().unwrap_or_else(|| {
+            // aggregating without grouping yields a single row
+            self.query = self.query.clone().select_only();
+            ColumnList::default()
+        });
+
+        for aggregate in aggregates {
+            let column = self.aggregate_opp(aggregate)?;
+            self.query = self.query.column_as(column.column.clone(), aggregate.alias.as_str());
+            result.push(aggregate.alias.to_snake_case(), column);
+        }
+
+        self.result = Some(result);
+        self.aggregated = true;
+        Ok(self)
+    }
+
+    /// The aggregate function and the type of the aggregated value
+    fn aggregate_opp(&self, aggregate: &Aggregate) -> ODataResult<ColumnValue> {
+        let Some(expression) = &aggregate.expression else {
+            return Ok(ColumnValue::from((
+                Expr::col(Asterisk).count(),
+                ColumnType::BigInteger.def(),
+            )));
+        };
+
+        let ColumnValue { column: value, def } = self.builder.build_column(expression)?;
+
+        let aggregated: (SimpleExpr, ColumnDef) = match aggregate.method {
+            AggregateMethod::Sum => (Func::sum(value).into(), def),
+            AggregateMethod::Min => (Func::min(value).into(), def),
+            AggregateMethod::Max => (Func::max(value).into(), def),
+            AggregateMethod::Average => (Func::avg(value).into(), ColumnType::Double.def()),
+            AggregateMethod::CountDistinct => (
+                self.builder.custom("COUNT(DISTINCT $1)", [value]),
+                ColumnType::BigInteger.def(),
+            ),
+            AggregateMethod::Count => (Expr::col(Asterisk).count(), ColumnType::BigInteger.def()),
+            // custom aggregation methods are specific to the service
+            AggregateMethod::Custom(_) => return Err(unsupported(format!("'{aggregate}'"))),
+        };
+
+        Ok(ColumnValue::from(aggregated))
+    }
+}
@@ -0,0 +1,690 @@
+//! Load the related entities of the $expand items and merge them into the JSON rows of a query.
+//!
+//! To-one relations are loaded with a join on the parent table, to-many relations with a single query per
+//! navigation property that selects the related rows of all parents at once. $top and $skip of a to-many relation
+//! number the related rows of each parent with `ROW_NUMBER()`.
+
+use crate::filter::FilterBuilder;
+use crate::{
+    get_column_names, get_relations, order_by_opp, search_and_filter_condition, ColumnList, PrimaryKeys, RelationList,
+};
+use heck::ToSnakeCase;
+use odata_model::error::{ODataError, ODataResult};
+use odata_model::expand::{ExpandItem, ExpandPath, Levels};
+use odata_model::expression::MemberPath;
+use odata_model::select::{selects_all, SelectItem};
+use sea_orm::entity::prelude::{Date, DateTime, DateTimeWithTimeZone, Decimal, Time, Uuid};
+use sea_orm::sea_query::{
+    Alias, Asterisk, Expr, Func, JoinType, OverStatement, Query, SelectStatement, SimpleExpr, TableRef, WindowStatement,
+};
+use sea_orm::{
+    ColumnType, ConnectionTrait, DbErr, EntityTrait, FromQueryResult, JsonValue, Order, RelationDef, RelationType,
+};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+
+/// The depth of nested expansions that `$levels=max` may reach; deeper hierarchies are rejected rather than cut off
+const MAX_LEVELS: u32 = 8;
+
+/// The prefix of the parent keys that are selected along with the related rows of a to-one relation
+const PARENT_KEY_PREFIX: &str = "$parent.";
+
+/// The number of a related row among the related rows of its parent, for $top and $skip
+const ROW_NUMBER_COLUMN: &str = "$row_number";
+
+/// The number of related rows of a parent, for $count along with $top or $skip
+const COUNT_COLUMN: &str = "$count";
+
+/// Why the related entities couldn't be loaded
+#[derive(Debug)]
+pub enum ExpandError {
+    /// The options of an $expand item can't be translated, e.g. a $filter on an unknown property
+    Query(ODataError),
+    Db(DbErr),
+}
+
+impl std::fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpandError::Query(error) => error.fmt(f),
+            ExpandError::Db(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ExpandError {}
+
+impl From<ODataError> for ExpandError {
+    fn from(error: ODataError) -> Self {
+        ExpandError::Query(error)
+    }
+}
+
+impl From<DbErr> for ExpandError {
+    fn from(error: DbErr) -> Self {
+        ExpandError::Db(error)
+    }
+}
+
+/// The columns and relations of an entity
+#[derive(Debug)]
+pub struct EntityInfo {
+    table: String,
+    p_keys: PrimaryKeys,
+    pub(crate) columns: ColumnList,
+    pub(crate) relations: RelationList,
+}
+
+impl EntityInfo {
+    pub fn of<E: EntityTrait>() -> Self {
+        let (p_keys, columns) = get_column_names::<E>();
+
+        Self {
+            table: E::default().table_name().to_string(),
+            p_keys,
+            columns,
+            relations: get_relations::<E>(),
+        }
+    }
+
+    /// The columns for the $select items, in the order of the entity; the primary keys are always included
+    fn selected_columns(&self, select: &[SelectItem]) -> Vec<&str> {
+        self.columns
+            .keys()
+            .into_iter()
+            .filter(|name| {
+                selects_all(select)
+                    || self.p_keys.iter().any(|key| key == *name)
+                    || select
+                        .iter()
+                        .filter_map(|item| item.property())
+                        .any(|property| property.to_snake_case() == *name)
+            })
+            .collect()
+    }
+}
+
+/// The entities that can be expanded, and whose columns the properties of related entities in $filter and $orderby
+/// are checked against; related entities that aren't in the list are left out of $expand, and rejected elsewhere
+#[derive(Debug, Default)]
+pub struct EntityList {
+    entities: Vec<EntityInfo>,
+}
+
+impl EntityList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_entity<E: EntityTrait>(mut self) -> Self {
+        self.entities.push(EntityInfo::of::<E>());
+        self
+    }
+
+    pub(crate) fn get(&self, table: &TableRef) -> Option<&EntityInfo> {
+        let table = match table {
+            TableRef::Table(table)
+            | TableRef::TableAlias(table, _)
+            | TableRef::SchemaTable(_, table)
+            | TableRef::SchemaTableAlias(_, table, _) => table.to_string(),
+            _ => return None,
+        };
+
+        self.entities.iter().find(|entity| entity.table == table)
+    }
+}
+
+/// Load the related entities of the $expand items and add them to the rows, which are the JSON objects of the
+/// entity `E`, e.g. as returned by `into_json()`. The related entities are added under the name of the navigation
+/// property, as an array for to-many and as an object or null for to-one relations.
+/// ```ignore
+/// let rows = users::Entity::find().with_odata_resource(&resource, db.get_database_backend())?.into_json().all(&db).await?;
+/// let entities = EntityList::new().with_entity::<orders::Entity>();
+/// let rows = expand_json::<users::Entity, _>(rows, &resource.expand, &entities, &db).await?;
+/// ```
+pub async fn expand_json<E, C>(
+    mut rows: Vec<JsonValue>,
+    expand: &[ExpandItem],
+    entities: &EntityList,
+    db: &C,
+) -> Result<Vec<JsonValue>, ExpandError>
+where
+    E: EntityTrait,
+    C: ConnectionTrait + Sync,
+{
+    let entity = EntityInfo::of::<E>();
+    expand_rows(&entity, &mut rows, expand.to_vec(), entities, db, 0).await?;
+    Ok(rows)
+}
+
+type ExpandFuture<'a> = Pin<Box<dyn Future<Output = Result<(), ExpandError>> + Send + 'a>>;
+
+fn expand_rows<'a, C>(
+    entity: &'a EntityInfo,
+    rows: &'a mut [JsonValue],
+    items: Vec<ExpandItem>,
+    entities: &'a EntityList,
+    db: &'a C,
+    depth: u32,
+) -> ExpandFuture<'a>
+where
+    C: ConnectionTrait + Sync,
+{
+    Box::pin(async move {
+        if rows.is_empty() {
+            return Ok(());
+        }
+
+        for item in resolve_wildcards(entity, entities, items) {
+            // navigation properties of complex properties have no relation of their own
+            let ExpandPath::Navigation(path) = &item.path else {
+                continue;
+            };
+            let Some(navigation) = path.as_property() else {
+                continue;
+            };
+            let Some(relation) = entity.relations.get(navigation) else {
+                continue;
+            };
+            let Some(target) = entities.get(&relation.to_tbl) else {
+                continue;
+            };
+            if matches!(item.options.levels, Some(Levels::Max)) && depth >= MAX_LEVELS {
+                return Err(ODataError::Unsupported(format!(
+                    "$levels=max of '{navigation}' beyond {MAX_LEVELS} levels"
+                ))
+                .into());
+            }
+
+            let expansion = Expansion {
+                navigation,
+                item: &item,
+                relation,
+                target,
+                depth,
+            };
+            match relation.rel_type {
+                RelationType::HasMany => expansion.load_many(rows, entities, db).await?,
+                RelationType::HasOne => expansion.load_one(entity, rows, entities, db).await?,
+            }
+        }
+
+        Ok(())
+    })
+}
+
+/// Replace `*` by the navigation properties of the entity that lead to known entities; navigation properties that
+/// are expanded explicitly keep their own options
+fn resolve_wildcards(entity: &EntityInfo, entities: &EntityList, items: Vec<ExpandItem>) -> Vec<ExpandItem> {
+    let explicit: Vec<String> = items
+        .iter()
+        .filter_map(|item| item.navigation())
+        .map(|navigation| navigation.to_snake_case())
+        .collect();
+    let mut resolved = Vec::new();
+
+    for item in items {
+        if item.path != ExpandPath::All {
+            resolved.push(item);
+            continue;
+        }
+
+        for name in entity.relations.names() {
+            let known = entity
+                .relations
+                .get(name)
+                .is_some_and(|relation| entities.get(&relation.to_tbl).is_some());
+            if known && !explicit.contains(&name.to_snake_case()) {
+                resolved.push(ExpandItem {
+                    path: ExpandPath::Navigation(MemberPath(vec![name.to_string()])),
+                    ..item.clone()
+                });
+            }
+        }
+    }
+
+    resolved
+}
+
+struct Expansion<'e> {
+    navigation: &'e str,
+    item: &'e ExpandItem,
+    relation: &'e RelationDef,
+    target: &'e EntityInfo,
+    /// The depth of the parent rows, 0 for the rows of the query
+    depth: u32,
+}
+
+impl Expansion<'_> {
+    /// The expansions of the related entities, including the next level of a recursive expansion
+    fn nested_items(&self) -> Vec<ExpandItem> {
+        let mut nested = self.item.options.expand.clone();
+
+        match self.item.options.levels {
+            // expand until there are no related rows left
+            Some(Levels::Max) => nested.push(self.item.clone()),
+            Some(Levels::Count(levels)) if levels > 1 => {
+                let mut next = self.item.clone();
+                next.options.levels = Some(Levels::Count(levels - 1));
+                nested.push(next);
+            }
+            _ => {}
+        }
+
+        nested
+    }
+
+    /// The filter builder for the options of the related entity
+    fn builder<'c>(&'c self, backend: sea_orm::DbBackend, entities: &'c EntityList) -> FilterBuilder<'c> {
+        FilterBuilder::new(&self.target.columns, backend)
+            .with_relations(&self.target.relations)
+            .with_entities(entities)
+    }
+
+    fn selected_columns(&self) -> Vec<&str> {
+        if self.item.reference {
+            return self.target.p_keys.keys();
+        }
+
+        self.target.selected_columns(&self.item.options.select)
+    }
+
+    /// Load the related rows of all parents with one query, matching the related columns with the parent values
+    async fn load_many<C>(&self, rows: &mut [JsonValue], entities: &EntityList, db: &C) -> Result<(), ExpandError>
+    where
+        C: ConnectionTrait + Sync,
+    {
+        let backend = db.get_database_backend();
+        let options = &self.item.options;
+        let from_cols = column_names(&self.relation.from_col);
+        let to_cols = column_names(&self.relation.to_col);
+
+        let mut parent_keys: Vec<Vec<JsonValue>> = Vec::new();
+        let mut seen = HashSet::new();
+        for row in rows.iter() {
+            let key = key_values(row, &from_cols);
+            if !key.iter().any(JsonValue::is_null) && seen.insert(key_string(&key)) {
+                parent_keys.push(key);
+            }
+        }
+
+        let selected = self.selected_columns();
+        // the related columns are needed to assign the rows to their parents
+        let helpers: Vec<&str> = to_cols
+            .iter()
+            .map(|col| col.as_str())
+            .filter(|col| !selected.contains(col))
+            .collect();
+
+        // $top and $skip apply to the related rows of each parent
+        let per_parent = options.top.is_some() || options.skip.is_some();
+        let mut children = Vec::new();
+        let mut counts: HashMap<String, JsonValue> = HashMap::new();
+        if !parent_keys.is_empty() {
+            let to_exprs = to_cols.iter().map(|col| Expr::col(Alias::new(col)).into()).collect();
+            let to_types = column_types(&self.target.columns, &to_cols);
+            let builder = self.builder(backend, entities);
+            let mut condition = sea_orm::Condition::all()
+                .add(key_expr(to_exprs).is_in(parent_keys.iter().map(|key| key_value(key, &to_types))));
+            let filter = search_and_filter_condition(options.search.as_deref(), options.filter.as_ref(), &builder)?;
+            if !filter.is_empty() {
+                condition = condition.add(filter);
+            }
+            let order = options
+                .order_by
+                .iter()
+                .map(|order_by| order_by_opp(&builder, order_by))
+                .collect::<ODataResult<Vec<_>>>()?;
+
+            let mut query = Query::select();
+            for name in selected.iter().chain(helpers.iter()) {
+                if let Some(column) = self.target.columns.get(name) {
+                    query.expr(column.column.clone());
+                }
+            }
+            query.from(self.relation.to_tbl.clone()).cond_where(condition.clone());
+
+            let query = if per_parent {
+                self.limit_per_parent(query, &to_cols, order)
+            } else {
+                for (col, order) in order {
+                    query.order_by_expr(col, order);
+                }
+                query
+            };
+
+            // the rows of a parent are cut by $top and $skip, so they are counted separately
+            if options.count && per_parent {
+                let mut count = Query::select();
+                count
+                    .columns(to_cols.iter().map(Alias::new))
+                    .expr_as(Func::count(Expr::col(Asterisk)), Alias::new(COUNT_COLUMN))
+                    .from(self.relation.to_tbl.clone())
+                    .cond_where(condition)
+                    .group_by_columns(to_cols.iter().map(Alias::new));
+                for row in query_json(db, &count).await? {
+                    let total = row.get(COUNT_COLUMN).cloned().unwrap_or(JsonValue::Null);
+                    counts.insert(key_string(&key_values(&row, &to_cols)), total);
+                }
+            }
+
+            children = query_json(db, &query).await?;
+            expand_rows(
+                self.target,
+                &mut children,
+                self.nested_items(),
+                entities,
+                db,
+                self.depth + 1,
+            )
+            .await?;
+        }
+
+        let mut groups: HashMap<String, Vec<JsonValue>> = HashMap::new();
+        for mut child in children {
+            let key = key_string(&key_values(&child, &to_cols));
+            if let JsonValue::Object(fields) = &mut child {
+                for helper in &helpers {
+                    fields.remove(*helper);
+                }
+                fields.remove(ROW_NUMBER_COLUMN);
+            }
+            groups.entry(key).or_default().push(self.output(child));
+        }
+
+        for row in rows.iter_mut() {
+            let key = key_string(&key_values(row, &from_cols));
+            let group = groups.get(&key).cloned().unwrap_or_default();
+            let Some(fields) = row.as_object_mut() else {
+                continue;
+            };
+
+            if options.count {
+                let count = if per_parent {
+                    counts.get(&key).cloned().unwrap_or_else(|| 0.into())
+                } else {
+                    group.len().into()
+                };
+                fields.insert(format!("{}@odata.count", self.navigation), count);
+            }
+            fields.insert(self.navigation.to_string(), JsonValue::Array(group));
+        }
+
+        Ok(())
+    }
+
+    /// Number the related rows of each parent in the order of $orderby, and select the rows of the $skip and $top
+    /// range; without $orderby, the rows are numbered in the order of their primary keys
+    fn limit_per_parent(
+        &self,
+        mut query: SelectStatement,
+        to_cols: &[String],
+        order: Vec<(SimpleExpr, Order)>,
+    ) -> SelectStatement {
+        let mut window = WindowStatement::new();
+        window.partition_by_columns(to_cols.iter().map(Alias::new));
+        if order.is_empty() {
+            for key in self.target.p_keys.iter() {
+                window.order_by(Alias::new(key), Order::Asc);
+            }
+        }
+        for (col, order) in order {
+            window.order_by_expr(col, order);
+        }
+        query.expr_window_as(
+            Func::cust(Alias::new("ROW_NUMBER")),
+            window,
+            Alias::new(ROW_NUMBER_COLUMN),
+        );
+
+        let skip = self.item.options.skip.unwrap_or(0) as u64;
+        let row_number = || Expr::col(Alias::new(ROW_NUMBER_COLUMN));
+        let mut numbered = Query::select();
+        numbered
+            .column(Asterisk)
+            .from_subquery(query, Alias::new("numbered"))
+            .and_where(row_number().gt(skip));
+        if let Some(top) = self.item.options.top {
+            numbered.and_where(row_number().lte(skip + top as u64));
+        }
+        numbered.order_by(Alias::new(ROW_NUMBER_COLUMN), Order::Asc);
+        numbered
+    }
+
+    /// Load the related row of each parent by joining the related table on the parent table
+    async fn load_one<C>(
+        &self,
+        entity: &EntityInfo,
+        rows: &mut [JsonValue],
+        entities: &EntityList,
+        db: &C,
+    ) -> Result<(), ExpandError>
+    where
+        C: ConnectionTrait + Sync,
+    {
+        let options = &self.item.options;
+        let p_keys: Vec<String> = entity.p_keys.iter().map(|key| key.to_string()).collect();
+        let parent_keys: Vec<Vec<JsonValue>> = rows
+            .iter()
+            .map(|row| key_values(row, &p_keys))
+            .filter(|key| !key.iter().any(JsonValue::is_null))
+            .collect();
+
+        let mut children = Vec::new();
+        if !parent_keys.is_empty() {
+            let parent = Alias::new(&entity.table);
+            let alias = Alias::new(self.navigation);
+
+            let mut query = Query::select();
+            for key in &p_keys {
+                query.expr_as(
+                    Expr::col((parent.clone(), Alias::new(key))),
+                    Alias::new(format!("{PARENT_KEY_PREFIX}{key}")),
+                );
+            }
+            for name in self.selected_columns() {
+                query.expr_as(Expr::col((alias.clone(), Alias::new(name))), Alias::new(name));
+            }
+
+            let mut on = sea_orm::Condition::all();
+            for (from, to) in self
+                .relation
+                .from_col
+                .clone()
+                .into_iter()
+                .zip(self.relation.to_col.clone())
+            {
+                on = on.add(Expr::col((alias.clone(), to)).equals((parent.clone(), from)));
+            }
+            query.from(self.relation.from_tbl.clone()).join_as(
+                JoinType::InnerJoin,
+                self.relation.to_tbl.clone(),
+                alias.clone(),
+                on,
+            );
+
+            let parent_exprs = p_keys
+                .iter()
+                .map(|key| Expr::col((parent.clone(), Alias::new(key))).into())
+                .collect();
+            let key_types = column_types(&entity.columns, &p_keys);
+            query.and_where(key_expr(parent_exprs).is_in(parent_keys.iter().map(|key| key_value(key, &key_types))));
+
+            let builder = self.builder(db.get_database_backend(), entities);
+            let condition = search_and_filter_condition(options.search.as_deref(), options.filter.as_ref(), &builder)?;
+            if !condition.is_empty() {
+                // the columns of the condition aren't qualified, so it selects the matching related rows in a subquery
+                let target_exprs = self
+                    .target
+                    .p_keys
+                    .iter()
+                    .map(|key| Expr::col((alias.clone(), Alias::new(key))).into())
+                    .collect();
+                let mut matching = Query::select();
+                matching
+                    .columns(self.target.p_keys.iter().map(Alias::new))
+                    .from(self.relation.to_tbl.clone())
+                    .cond_where(condition);
+                query.and_where(key_expr(target_exprs).in_subquery(matching));
+            }
+            // there is a single related row per parent, so $orderby has nothing to sort, but it must still be valid
+            for order_by in &options.order_by {
+                order_by_opp(&builder, order_by)?;
+            }
+
+            children = query_json(db, &query).await?;
+        }
+
+        // split the parent keys from the related rows
+        let mut keys = Vec::new();
+        for child in children.iter_mut() {
+            let key: Vec<JsonValue> = p_keys
+                .iter()
+                .map(|key| {
+                    child
+                        .as_object_mut()
+                        .and_then(|fields| fields.remove(&format!("{PARENT_KEY_PREFIX}{key}")))
+                        .unwrap_or(JsonValue::Null)
+                })
+                .collect();
+            keys.push(key_string(&key));
+        }
+
+        expand_rows(
+            self.target,
+            &mut children,
+            self.nested_items(),
+            entities,
+            db,
+            self.depth + 1,
+        )
+        .await?;
+
+        let related: HashMap<String, JsonValue> = keys
+            .into_iter()
+            .zip(children)
+            .map(|(key, child)| (key, self.output(child)))
+            .collect();
+
+        for row in rows.iter_mut() {
+            let child = related
+                .get(&key_string(&key_values(row, &p_keys)))
+                .cloned()
+                .unwrap_or(JsonValue::Null);
+            if let Some(fields) = row.as_object_mut() {
+                fields.insert(self.navigation.to_string(), child);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The related row as it is returned; only the entity id for `$ref`
+    fn output(&self, child: JsonValue) -> JsonValue {
+        if !self.item.reference {
+            return child;
+        }
+
+        let keys: Vec<String> = self
+            .target
+            .p_keys
+            .iter()
+            .map(|key| {
+                let value = child.get(key).cloned().unwrap_or(JsonValue::Null);
+                let value = match value {
+                    JsonValue::String(value) => format!("'{}'", value.replace('\'', "''")),
+                    value => value.to_string(),
+                };
+                if self.target.p_keys.keys().len() == 1 {
+                    value
+                } else {
+                    format!("{key}={value}")
+                }
+            })
+            .collect();
+
+        serde_json::json!({ "@odata.id": format!("{}({})", self.target.table, keys.join(",")) })
+    }
+}
+
+async fn query_json<C: ConnectionTrait>(db: &C, query: &SelectStatement) -> Result<Vec<JsonValue>, DbErr> {
+    let results = db.query_all(db.get_database_backend().build(query)).await?;
+    results
+        .iter()
+        .map(|result| JsonValue::from_query_result(result, ""))
+        .collect()
+}
+
+fn column_names(identity: &sea_orm::Identity) -> Vec<String> {
+    identity.clone().into_iter().map(|col| col.to_string()).collect()
+}
+
+fn key_values(row: &JsonValue, columns: &[String]) -> Vec<JsonValue> {
+    columns
+        .iter()
+        .map(|col| row.get(col).cloned().unwrap_or(JsonValue::Null))
+        .collect()
+}
+
+/// The columns of a key as one expression, a tuple for composite keys
+fn key_expr(mut columns: Vec<SimpleExpr>) -> Expr {
+    if columns.len() == 1 {
+        Expr::expr(columns.remove(0))
+    } else {
+        Expr::tuple(columns)
+    }
+}
+
+/// The types of the key columns, if they are columns of the entity
+fn column_types<'c>(columns: &'c ColumnList, names: &[String]) -> Vec<Option<&'c ColumnType>> {
+    names
+        .iter()
+        .map(|name| columns.get(name).map(|column| column.def.get_column_type()))
+        .collect()
+}
+
+/// The values of a key as one expression, a tuple for composite keys
+fn key_value(key: &[JsonValue], types: &[Option<&ColumnType>]) -> SimpleExpr {
+    let value = |index: usize| into_value(&key[index], types.get(index).copied().flatten()).into();
+    match key {
+        [_] => value(0),
+        values => SimpleExpr::Tuple((0..values.len()).map(value).collect()),
+    }
+}
+
+fn key_string(key: &[JsonValue]) -> String {
+    JsonValue::from(key.to_vec()).to_string()
+}
+
+/// The value of a key column as the type of the column. UUIDs, timestamps and decimals are strings in the JSON rows,
+/// and the databases don't compare them with text, nor decimals with doubles without losing digits.
+fn into_value(value: &JsonValue, column_type: Option<&ColumnType>) -> sea_orm::Value {
+    let text = match value {
+        JsonValue::String(value) => Some(value.clone()),
+        JsonValue::Number(value) => Some(value.to_string()),
+        _ => None,
+    };
+    let typed = text.zip(column_type).and_then(|(text, column_type)| match column_type {
+        ColumnType::Uuid => text.parse::<Uuid>().ok().map(Into::into),
+        ColumnType::Decimal(_) | ColumnType::Money(_) => text.parse::<Decimal>().ok().map(Into::into),
+        ColumnType::TimestampWithTimeZone => DateTimeWithTimeZone::parse_from_rfc3339(&text).ok().map(Into::into),
+        ColumnType::DateTime | ColumnType::Timestamp => text.parse::<DateTime>().ok().map(Into::into),
+        ColumnType::Date => text.parse::<Date>().ok().map(Into::into),
+        ColumnType::Time => text.parse::<Time>().ok().map(Into::into),
+        _ => None,
+    });
+    if let Some(typed) = typed {
+        return typed;
+    }
+
+    match value {
+        JsonValue::Bool(value) => (*value).into(),
+        JsonValue::Number(value) => match value.as_i64() {
+            Some(value) => value.into(),
+            None => value.as_f64().into(),
+        },
+        JsonValue::String(value) => value.as_str().into(),
+        value => value.to_string().into(),
+    }
+}
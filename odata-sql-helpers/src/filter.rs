@@ -0,0 +1,657 @@
+//! Translate OData filter expressions into SeaOrm conditions.
+
+use crate::expand::EntityList;
+use crate::{ColumnList, ColumnValue, RelationList};
+use heck::ToSnakeCase;
+use odata_model::error::{ODataError, ODataResult};
+use odata_model::expression::{
+    BinaryOperator, Expression, Function, Lambda, LambdaOperator, LogicalOperator, MemberPath, UnaryOperator, IT,
+};
+use odata_model::resource::Value;
+use sea_orm::{
+    sea_query::{Alias, BinOper, Expr, Func, FunctionCall, IntoIden, Query, SimpleExpr, TableRef},
+    ColumnType, ColumnTypeTrait, Condition, DbBackend, RelationDef, RelationType,
+};
+
+/// The range variable of a lambda expression, the unique alias of what it ranges over, and what that is
+#[derive(Clone)]
+struct RangeVariable<'c> {
+    name: String,
+    alias: String,
+    range: Range<'c>,
+}
+
+#[derive(Clone)]
+enum Range<'c> {
+    /// The rows of a related table, with the columns and relations of its entity
+    Entity {
+        columns: &'c ColumnList,
+        relations: &'c RelationList,
+    },
+    /// The values of a collection column, in the given column of the aliased table function
+    Values { column: String },
+}
+
+/// Builds SQL expressions for the columns of a single table, using the dialect of the given backend
+#[derive(Clone)]
+pub(crate) struct FilterBuilder<'c> {
+    pub table_columns: &'c ColumnList,
+    pub backend: DbBackend,
+    relations: Option<&'c RelationList>,
+    /// The related entities, for the columns of the tables that lambdas and navigation paths refer to
+    entities: Option<&'c EntityList>,
+    /// The $compute expressions, by their snake cased alias
+    computed: Option<&'c ColumnList>,
+    /// The range variables of the enclosing lambda expressions, the innermost last
+    variables: Vec<RangeVariable<'c>>,
+    /// The to-one navigation properties joined to the query, by their snake cased name
+    joins: Vec<String>,
+}
+
+impl<'c> FilterBuilder<'c> {
+    pub fn new(table_columns: &'c ColumnList, backend: DbBackend) -> Self {
+        Self {
+            table_columns,
+            backend,
+            relations: None,
+            entities: None,
+            computed: None,
+            variables: Vec::new(),
+            joins: Vec::new(),
+        }
+    }
+
+    /// Use the relations of the entity to translate lambda operators; without them, lambdas are rejected
+    pub fn with_relations(mut self, relations: &'c RelationList) -> Self {
+        self.relations = Some(relations);
+        self
+    }
+
+    /// Validate the properties of related entities against the columns of the entities; without them, paths to the
+    /// properties of related entities are rejected
+    pub fn with_entities(mut self, entities: &'c EntityList) -> Self {
+        self.entities = Some(entities);
+        self
+    }
+
+    /// Resolve the computed properties of $compute, in addition to the columns of the table
+    pub fn with_computed(mut self, computed: &'c ColumnList) -> Self {
+        self.computed = Some(computed);
+        self
+    }
+
+    /// Resolve the paths through the joined navigation properties, see [`navigation_joins`]
+    pub fn with_joins(mut self, joins: Vec<String>) -> Self {
+        self.joins = joins;
+        self
+    }
+
+    pub fn has_joins(&self) -> bool {
+        !self.joins.is_empty()
+    }
+
+    pub fn is_computed(&self, property: &str) -> bool {
+        self.computed
+            .is_some_and(|computed| computed.contains_key(&property.to_snake_case()))
+    }
+
+    /// Translate a filter expression into a condition. Expressions referring to unknown columns fail with
+    /// [`ODataError::InvalidQuery`], and constructs without a SQL translation with [`ODataError::Unsupported`]; no
+    /// part of the filter is ever left out, as that would select more rows than the filter allows.
+    pub fn build_condition(&self, filter: &Expression) -> ODataResult<Condition> {
+        match filter {
+            Expression::Logical(operator, operands) => {
+                let mut condition = match operator {
+                    LogicalOperator::And => Condition::all(),
+                    LogicalOperator::Or => Condition::any(),
+                };
+
+                for operand in operands {
+                    condition = condition.add(self.build_condition(operand)?);
+                }
+
+                Ok(condition)
+            }
+            Expression::Unary(UnaryOperator::Not, operand) => Ok(self.build_condition(operand)?.not()),
+            Expression::Binary(left, operator, right) if !operator.is_arithmetic() => {
+                Ok(Condition::all().add(self.compare_opp(left, *operator, right)?))
+            }
+            _ => Ok(Condition::all().add(self.build_operand(filter)?)),
+        }
+    }
+
+    /// Translate an operand, i.e. a column, a literal, a function call or an arithmetic expression, into a SQL
+    /// expression
+    pub fn build_operand(&self, operand: &Expression) -> ODataResult<SimpleExpr> {
+        match operand {
+            Expression::Literal(value) => self.literal_opp(value),
+            Expression::Member(path) => self.member_opp(path),
+            Expression::Binary(left, operator, right) if operator.is_arithmetic() => {
+                self.arithmetic_opp(left, *operator, right)
+            }
+            Expression::Unary(UnaryOperator::Negate, operand) => {
+                let operand = self.build_operand(operand)?;
+                Ok(self.custom("-($1)", [operand]))
+            }
+            Expression::Function(function, arguments) => self.function_opp(*function, arguments),
+            Expression::Lambda(lambda) => self.lambda_opp(lambda),
+            Expression::TypeFunction(type_function) => self.type_function_opp(type_function),
+            // conditions are only translated where a condition is expected
+            _ => Err(unsupported(format!("'{operand}' as an operand"))),
+        }
+    }
+
+    /// Translate an expression into a column value, typed like the column it refers to, or as a decimal otherwise
+    pub fn build_column(&self, expression: &Expression) -> ODataResult<ColumnValue> {
+        let value = self.build_operand(expression)?;
+        let def = expression
+            .member()
+            .and_then(|path| path.as_property())
+            .and_then(|property| self.table_columns.get(&property.to_snake_case()))
+            .map(|column| column.def.clone())
+            .unwrap_or_else(|| ColumnType::Decimal(None).def());
+
+        Ok(ColumnValue::from((value, def)))
+    }
+
+    /// Translate a literal; the floating point specials and durations differ between the database backends. MySQL
+    /// has neither, and SQLite has no NaN and no interval type, so these literals are rejected there instead of being
+    /// compared as something else.
+    fn literal_opp(&self, value: &Value) -> ODataResult<SimpleExpr> {
+        match value {
+            Value::Double(value) if !value.is_finite() => {
+                let special = match *value {
+                    value if value.is_nan() => "NaN",
+                    value if value > 0.0 => "Infinity",
+                    _ => "-Infinity",
+                };
+                match self.backend {
+                    DbBackend::Postgres => Ok(Expr::val(special).cast_as(Alias::new("DOUBLE PRECISION"))),
+                    // a literal out of range is infinite in SQLite, which has no NaN
+                    DbBackend::Sqlite if !value.is_nan() => Ok(Expr::cust(special.replace("Infinity", "9e999"))),
+                    _ => Err(unsupported(format!("{special} on {:?}", self.backend))),
+                }
+            }
+            Value::Duration(duration) => {
+                match self.backend {
+                    DbBackend::Postgres => Ok(Expr::val(format!("{} milliseconds", duration.num_milliseconds()))
+                        .cast_as(Alias::new("INTERVAL"))),
+                    DbBackend::MySql | DbBackend::Sqlite => Err(unsupported(format!("{value} on {:?}", self.backend))),
+                }
+            }
+            _ => into_simple_expr(value).ok_or_else(|| unsupported(format!("the literal '{value}' as an operand"))),
+        }
+    }
+
+    /// Resolve a property of the filtered table, `$it/Property`, or `variable/Property` of an enclosing lambda.
+    /// Properties of complex types map to the column named after their path, e.g. `address_city` for `Address/City`,
+    /// and properties of a joined navigation property to the columns of the related table, e.g. `Customer/Name`.
+    fn member_opp(&self, path: &MemberPath) -> ODataResult<SimpleExpr> {
+        let Some((first, rest)) = path.segments().split_first() else {
+            return Err(ODataError::InvalidQuery("an empty property path".to_string()));
+        };
+
+        if let Some(variable) = self.variable(first) {
+            let column = match variable.range {
+                Range::Entity { .. } if rest.is_empty() => {
+                    return Err(unsupported(format!("the range variable '{first}' by itself")));
+                }
+                Range::Entity { columns, .. } => related_column(columns, path, rest)?,
+                Range::Values { ref column } if rest.is_empty() => column.clone(),
+                // the values of a collection have no properties of their own
+                Range::Values { .. } => return Err(ODataError::InvalidQuery(format!("'{path}' has no column"))),
+            };
+            return Ok(Expr::col((Alias::new(&variable.alias), Alias::new(column))).into());
+        }
+
+        let segments = if first == IT { rest } else { path.segments() };
+        if let [navigation, property @ ..] = segments {
+            let alias = navigation.to_snake_case();
+            let relation = self.relations.and_then(|relations| relations.get(navigation));
+            if let Some(relation) = relation.filter(|_| !property.is_empty() && self.joins.contains(&alias)) {
+                let column = related_column(self.related_columns(relation, navigation)?, path, property)?;
+                return Ok(Expr::col((Alias::new(alias), Alias::new(column))).into());
+            }
+            if relation.is_some_and(|relation| relation.rel_type == RelationType::HasMany) {
+                return Err(ODataError::InvalidQuery(format!(
+                    "'{path}' goes through the collection '{navigation}', which can only be navigated with any or all"
+                )));
+            }
+        }
+
+        let snaked = column_name(segments);
+        self.table_column(&snaked)
+            .or_else(|| self.computed?.get(&snaked).map(|computed| computed.column.clone()))
+            .ok_or_else(|| ODataError::InvalidQuery(format!("'{path}' has no column")))
+    }
+
+    /// The innermost range variable of the given name
+    fn variable(&self, name: &str) -> Option<&RangeVariable<'c>> {
+        self.variables.iter().rev().find(|variable| variable.name == name)
+    }
+
+    /// The columns and relations of the entity a relation leads to
+    fn related_entity(
+        &self,
+        relation: &RelationDef,
+        navigation: &str,
+    ) -> ODataResult<(&'c ColumnList, &'c RelationList)> {
+        self.entities
+            .and_then(|entities| entities.get(&relation.to_tbl))
+            .map(|entity| (&entity.columns, &entity.relations))
+            .ok_or_else(|| unsupported(format!("'{navigation}' to an entity that isn't in the entity list")))
+    }
+
+    /// The columns of the table a relation leads to
+    fn related_columns(&self, relation: &RelationDef, navigation: &str) -> ODataResult<&'c ColumnList> {
+        Ok(self.related_entity(relation, navigation)?.0)
+    }
+
+    /// A column of the table by its snake cased name
+    pub fn table_column(&self, name: &str) -> Option<SimpleExpr> {
+        let column = self.table_columns.get(name)?;
+
+        match self.relations {
+            // within a lambda, or with joined tables, the table must be named explicitly
+            Some(relations) if !self.variables.is_empty() || !self.joins.is_empty() => {
+                Some(Expr::col((Alias::new(&relations.table), Alias::new(name))).into())
+            }
+            _ => Some(column.column.clone()),
+        }
+    }
+
+    /// Translate `any` into a correlated EXISTS subquery on the related table, and `all` into a NOT EXISTS
+    /// subquery for the related rows that don't match the predicate. The related table is aliased after the
+    /// navigation property and the nesting depth, e.g. `orders_1`, as the name of the range variable may be taken by
+    /// another table, like in `users/any(users: ...)`. The lambda may start from the range variable of an enclosing
+    /// lambda, e.g. `Orders/any(o: o/Items/any(i: ...))`, and may range over a collection column, see
+    /// [`FilterBuilder::collection_values`].
+    fn lambda_opp(&self, lambda: &Lambda) -> ODataResult<SimpleExpr> {
+        let unsupported_lambda = || unsupported(format!("'{}/{}'", lambda.path, lambda.operator.name()));
+        let relations = self.relations.ok_or_else(unsupported_lambda)?;
+        let (parent, navigation) = match lambda.path.segments() {
+            [navigation] => (None, navigation),
+            [it, navigation] if it == IT => (None, navigation),
+            [variable, navigation] => (
+                Some(self.variable(variable).ok_or_else(unsupported_lambda)?),
+                navigation,
+            ),
+            _ => return Err(unsupported_lambda()),
+        };
+        // the parent is the table itself, or the related table of a range variable
+        let (parent, columns, relations) = match parent {
+            None => (relations.table.as_str(), self.table_columns, relations),
+            Some(RangeVariable {
+                alias,
+                range: Range::Entity { columns, relations },
+                ..
+            }) => (alias.as_str(), *columns, *relations),
+            Some(_) => return Err(ODataError::InvalidQuery(format!("'{}' has no column", lambda.path))),
+        };
+
+        let name = navigation.to_snake_case();
+        let alias = format!("{name}_{}", self.variables.len() + 1);
+        let (from, mut condition, range) = match columns.get(&name) {
+            Some(column) => {
+                let values = Expr::col((Alias::new(parent), Alias::new(&name))).into();
+                let (function, column) = self.collection_values(lambda, values, column.def.get_column_type())?;
+                let from = TableRef::FunctionCall(function, Alias::new(&alias).into_iden());
+                let column = column.map_or_else(|| alias.clone(), str::to_string);
+                (from, Condition::all(), Range::Values { column })
+            }
+            None => {
+                let relation = relations.get(navigation).ok_or_else(|| {
+                    ODataError::InvalidQuery(format!("'{}' is not a navigation property", lambda.path))
+                })?;
+                let (from, condition) = relations
+                    .related_table(relation, parent, &alias)
+                    .ok_or_else(unsupported_lambda)?;
+                let (columns, relations) = self.related_entity(relation, navigation)?;
+                (from, condition, Range::Entity { columns, relations })
+            }
+        };
+
+        if let Some((variable, predicate)) = &lambda.predicate {
+            let mut inner = self.clone();
+            inner.variables.push(RangeVariable {
+                name: variable.clone(),
+                alias,
+                range,
+            });
+            let predicate = inner.build_condition(predicate)?;
+            condition = match lambda.operator {
+                LambdaOperator::Any => condition.add(predicate),
+                // a predicate that is NULL doesn't hold either, so the rows where it isn't TRUE are counterexamples
+                LambdaOperator::All => {
+                    let is_not_true: SimpleExpr = Expr::case(predicate, false).finally(true).into();
+                    condition.add(is_not_true)
+                }
+            };
+        }
+
+        let subquery = Query::select()
+            .expr(Expr::val(1))
+            .from(from)
+            .cond_where(condition)
+            .to_owned();
+
+        let exists = Expr::exists(subquery);
+        match lambda.operator {
+            LambdaOperator::Any => Ok(exists),
+            LambdaOperator::All => Ok(exists.not()),
+        }
+    }
+
+    /// The table function that lists the values of a collection column, and the column of the values, if it isn't
+    /// named after the alias of the function. Collections are Postgres arrays, listed with `unnest`, or JSON arrays,
+    /// listed with `jsonb_array_elements_text` on Postgres, where the values are text, and with `json_each` on SQLite.
+    fn collection_values(
+        &self,
+        lambda: &Lambda,
+        values: SimpleExpr,
+        column_type: &ColumnType,
+    ) -> ODataResult<(FunctionCall, Option<&'static str>)> {
+        let is_array = matches!(column_type, ColumnType::Array(_));
+        let is_json = matches!(column_type, ColumnType::Json | ColumnType::JsonBinary);
+        match self.backend {
+            DbBackend::Postgres if is_array => Ok((Func::cust(Alias::new("unnest")).arg(values), None)),
+            DbBackend::Postgres if is_json => {
+                let values = Expr::expr(values).cast_as(Alias::new("jsonb"));
+                Ok((
+                    Func::cust(Alias::new("jsonb_array_elements_text")).arg(values),
+                    Some("value"),
+                ))
+            }
+            DbBackend::Sqlite if is_json => Ok((Func::cust(Alias::new("json_each")).arg(values), Some("value"))),
+            backend if is_array || is_json => Err(unsupported(format!(
+                "'{}/{}' on {backend:?}",
+                lambda.path,
+                lambda.operator.name()
+            ))),
+            _ => Err(ODataError::InvalidQuery(format!(
+                "'{}' is not a collection",
+                lambda.path
+            ))),
+        }
+    }
+
+    /// A custom SQL expression, where `$1`, `$2`, ... refer to the given values. Postgres numbers its placeholders,
+    /// while MySQL and SQLite use positional `?` placeholders, so the values are repeated in order of appearance there.
+    pub fn custom<const N: usize>(&self, template: &str, values: [SimpleExpr; N]) -> SimpleExpr {
+        if self.backend == DbBackend::Postgres {
+            return Expr::cust_with_exprs(template, values);
+        }
+
+        let mut sql = String::with_capacity(template.len());
+        let mut ordered = Vec::new();
+        let mut rest = template;
+
+        while let Some(pos) = rest.find('$') {
+            sql.push_str(&rest[..pos]);
+            rest = &rest[pos + 1..];
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            match rest[..digits]
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| values.get(n.wrapping_sub(1)))
+            {
+                Some(value) => {
+                    sql.push('?');
+                    ordered.push(value.clone());
+                }
+                None => sql.push_str(&format!("${}", &rest[..digits])),
+            }
+            rest = &rest[digits..];
+        }
+        sql.push_str(rest);
+
+        Expr::cust_with_exprs(sql, ordered)
+    }
+
+    fn arithmetic_opp(
+        &self,
+        left_operand: &Expression,
+        operation: BinaryOperator,
+        right_operand: &Expression,
+    ) -> ODataResult<SimpleExpr> {
+        let left = self.build_operand(left_operand)?;
+        let right = self.build_operand(right_operand)?;
+
+        let expression = match operation {
+            BinaryOperator::Add => left.add(right),
+            BinaryOperator::Sub => left.sub(right),
+            BinaryOperator::Mul => left.mul(right),
+            BinaryOperator::Div if self.is_integer(left_operand) && self.is_integer(right_operand) => {
+                match self.backend {
+                    // `/` always yields a decimal in MySQL
+                    DbBackend::MySql => Expr::expr(left).binary(BinOper::Custom("DIV"), right),
+                    // EXTRACT yields a numeric value in Postgres, which `/` divides with a remainder
+                    DbBackend::Postgres => {
+                        let integer = |operand: &Expression, value: SimpleExpr| match operand {
+                            Expression::Function(..) => value.cast_as(Alias::new("BIGINT")),
+                            _ => value,
+                        };
+                        integer(left_operand, left).div(integer(right_operand, right))
+                    }
+                    DbBackend::Sqlite => left.div(right),
+                }
+            }
+            BinaryOperator::Div => left.div(right),
+            // force a decimal division, even when both operands are integers
+            BinaryOperator::DivBy => left.mul(Expr::cust("1.0")).div(right),
+            BinaryOperator::Mod => Expr::expr(left).modulo(right),
+            _ => return Err(unsupported(format!("'{}' as an arithmetic operator", operation.name()))),
+        };
+
+        Ok(expression)
+    }
+
+    /// Whether an expression yields an integer, so `div` divides it without a remainder; properties are integers when
+    /// their column is
+    fn is_integer(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Literal(value) => matches!(value, Value::Integer(_) | Value::Int64(_)),
+            Expression::Member(path) => path
+                .as_property()
+                .and_then(|property| self.table_columns.get(&property.to_snake_case()))
+                .is_some_and(|column| {
+                    matches!(
+                        column.def.get_column_type(),
+                        ColumnType::TinyInteger
+                            | ColumnType::SmallInteger
+                            | ColumnType::Integer
+                            | ColumnType::BigInteger
+                            | ColumnType::TinyUnsigned
+                            | ColumnType::SmallUnsigned
+                            | ColumnType::Unsigned
+                            | ColumnType::BigUnsigned
+                    )
+                }),
+            Expression::Unary(UnaryOperator::Negate, operand) => self.is_integer(operand),
+            Expression::Binary(left, operator, right) => {
+                operator.is_arithmetic()
+                    && *operator != BinaryOperator::DivBy
+                    && self.is_integer(left)
+                    && self.is_integer(right)
+            }
+            Expression::Function(function, _) => matches!(
+                function,
+                Function::Length
+                    | Function::IndexOf
+                    | Function::Year
+                    | Function::Month
+                    | Function::Day
+                    | Function::Hour
+                    | Function::Minute
+                    | Function::Second
+                    | Function::TotalOffsetMinutes
+            ),
+            _ => false,
+        }
+    }
+
+    fn compare_opp(&self, left: &Expression, operation: BinaryOperator, right: &Expression) -> ODataResult<SimpleExpr> {
+        // comparisons with null translate into IS NULL / IS NOT NULL
+        let (operand, null_check) = match (left, right) {
+            (operand, Expression::Literal(Value::Null)) | (Expression::Literal(Value::Null), operand) => {
+                (operand, true)
+            }
+            _ => (left, false),
+        };
+
+        if null_check {
+            let operand = Expr::expr(self.build_operand(operand)?);
+            return match operation {
+                BinaryOperator::Eq => Ok(operand.is_null()),
+                BinaryOperator::Ne => Ok(operand.is_not_null()),
+                _ => Err(unsupported(format!("'{}' with null", operation.name()))),
+            };
+        }
+
+        let left = Expr::expr(self.build_operand(left)?);
+
+        let expression = match operation {
+            BinaryOperator::Eq => left.eq(self.build_operand(right)?),
+            BinaryOperator::Ne => left.ne(self.build_operand(right)?),
+            BinaryOperator::Gt => left.gt(self.build_operand(right)?),
+            BinaryOperator::Ge => left.gte(self.build_operand(right)?),
+            BinaryOperator::Lt => left.lt(self.build_operand(right)?),
+            BinaryOperator::Le => left.lte(self.build_operand(right)?),
+            BinaryOperator::In => {
+                let Expression::List(values) = right else {
+                    return Err(unsupported(format!("'in' with '{right}'")));
+                };
+                let values = values
+                    .iter()
+                    .map(|value| self.build_operand(value))
+                    .collect::<ODataResult<Vec<_>>>()?;
+                left.is_in(values)
+            }
+            // enum flags are stored by the name of a single member
+            BinaryOperator::Has => return Err(unsupported("'has'")),
+            // arithmetic operators don't yield a condition by themselves
+            BinaryOperator::Add
+            | BinaryOperator::Sub
+            | BinaryOperator::Mul
+            | BinaryOperator::Div
+            | BinaryOperator::DivBy
+            | BinaryOperator::Mod => return Err(unsupported(format!("'{}' as a condition", operation.name()))),
+        };
+
+        Ok(expression)
+    }
+}
+
+/// The error for an expression that is valid OData, but has no SQL translation, e.g. `'has'`
+pub(crate) fn unsupported(expression: impl std::fmt::Display) -> ODataError {
+    ODataError::Unsupported(format!("{expression} has no SQL translation"))
+}
+
+/// The column of a related table that the property of a path maps to
+fn related_column(columns: &ColumnList, path: &MemberPath, property: &[String]) -> ODataResult<String> {
+    let column = column_name(property);
+    if columns.contains_key(&column) {
+        Ok(column)
+    } else {
+        Err(ODataError::InvalidQuery(format!("'{path}' has no column")))
+    }
+}
+
+/// The column a property path maps to, e.g. `address_city` for `Address/City` of a complex type
+pub(crate) fn column_name(path: &[String]) -> String {
+    let segments: Vec<String> = path.iter().map(|segment| segment.to_snake_case()).collect();
+    segments.join("_")
+}
+
+/// The to-one navigation properties the paths of the expressions go through, e.g. `customer` for `Customer/Name`, by
+/// their snake cased name. Only the navigation properties of the table itself are known, so a path through several
+/// navigation properties is left out, like paths through collections outside of a lambda.
+pub(crate) fn navigation_joins<'e>(
+    expressions: impl IntoIterator<Item = &'e Expression>,
+    relations: &RelationList,
+) -> Vec<String> {
+    let mut joins = Vec::new();
+    for expression in expressions {
+        collect_navigations(expression, relations, &mut Vec::new(), &mut joins);
+    }
+    joins
+}
+
+fn collect_navigations(
+    expression: &Expression,
+    relations: &RelationList,
+    variables: &mut Vec<String>,
+    joins: &mut Vec<String>,
+) {
+    let mut collect = |expression: &Expression, variables: &mut Vec<String>| {
+        collect_navigations(expression, relations, variables, joins)
+    };
+
+    match expression {
+        Expression::Member(path) => {
+            let segments = match path.segments().split_first() {
+                Some((first, rest)) if first == IT => rest,
+                Some((first, _)) if variables.contains(first) => return,
+                _ => path.segments(),
+            };
+            let [navigation, _, ..] = segments else {
+                return;
+            };
+            let alias = navigation.to_snake_case();
+            let is_to_one = relations
+                .get(navigation)
+                .is_some_and(|relation| relation.rel_type == RelationType::HasOne);
+            if is_to_one && !joins.contains(&alias) {
+                joins.push(alias);
+            }
+        }
+        Expression::List(items) | Expression::Function(_, items) => {
+            for item in items {
+                collect(item, variables);
+            }
+        }
+        Expression::Unary(_, operand) => collect(operand, variables),
+        Expression::Binary(left, _, right) => {
+            collect(left, variables);
+            collect(right, variables);
+        }
+        Expression::Logical(_, operands) => {
+            for operand in operands {
+                collect(operand, variables);
+            }
+        }
+        Expression::Lambda(lambda) => {
+            if let Some((variable, predicate)) = &lambda.predicate {
+                variables.push(variable.clone());
+                collect(predicate, variables);
+                variables.pop();
+            }
+        }
+        Expression::TypeFunction(type_function) => {
+            if let Some(operand) = &type_function.operand {
+                collect(operand, variables);
+            }
+        }
+        Expression::Literal(_) => (),
+    }
+}
+
+pub(crate) fn into_simple_expr(v: &Value) -> Option<SimpleExpr> {
+    match v {
+        Value::String(s) => Some(SimpleExpr::from(s)),
+        Value::Integer(n) => Some((*n).into()),
+        Value::Decimal(d) => Some((*d).into()),
+        Value::Boolean(b) => Some((*b).into()),
+        Value::Int64(n) => Some((*n).into()),
+        Value::Double(d) => Some((*d).into()),
+        Value::Guid(guid) => Some((*guid).into()),
+        Value::Date(date) => Some((*date).into()),
+        Value::DateTimeOffset(date_time) => Some((*date_time).into()),
+        Value::TimeOfDay(time) => Some((*time).into()),
+        Value::Binary(bytes) => Some(bytes.clone().into()),
+        // enum members are stored by name
+        Value::Enum(_, member) => Some(member.into()),
+        Value::Json(json) => Some(json.clone().into()),
+        Value::Null | Value::Duration(_) | Value::QueryOption(_) => None,
+    }
+}
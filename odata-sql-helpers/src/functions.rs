@@ -0,0 +1,248 @@
+//! Translate the OData canonical functions into the native SQL functions of each database backend.
+
+use crate::filter::{unsupported, FilterBuilder};
+use odata_model::error::{ODataError, ODataResult};
+use odata_model::expression::{Expression, Function, TypeFunction, TypeFunctionKind};
+use odata_model::resource::Value;
+use sea_orm::{
+    sea_query::{Alias, BinOper, Expr, Func, LikeExpr, SimpleExpr},
+    DbBackend,
+};
+
+const LIKE_ESCAPE: char = '\\';
+
+impl FilterBuilder<'_> {
+    pub(crate) fn function_opp(&self, function: Function, arguments: &[Expression]) -> ODataResult<SimpleExpr> {
+        let expression = match function {
+            Function::Contains => self.like_opp(arguments, true, true)?,
+            Function::StartsWith => self.like_opp(arguments, false, true)?,
+            Function::EndsWith => self.like_opp(arguments, true, false)?,
+            Function::Length => Func::char_length(self.argument(arguments, 0)?).into(),
+            Function::IndexOf => {
+                let value = self.argument(arguments, 0)?;
+                let search = self.argument(arguments, 1)?;
+                let position = match self.backend {
+                    DbBackend::Postgres => Func::cust(Alias::new("STRPOS")).args([value, search]),
+                    DbBackend::MySql => Func::cust(Alias::new("LOCATE")).args([search, value]),
+                    DbBackend::Sqlite => Func::cust(Alias::new("INSTR")).args([value, search]),
+                };
+                // SQL positions are 1-based, OData positions are 0-based
+                SimpleExpr::from(position).sub(1)
+            }
+            Function::Substring => {
+                let value = self.argument(arguments, 0)?;
+                let start = match self.expression_argument(arguments, 1)? {
+                    // SQL positions are 1-based; the start may be i32::MAX, so the 1 is added in 64 bits
+                    Expression::Literal(Value::Integer(start)) => SimpleExpr::from(i64::from(*start) + 1),
+                    start => self.build_operand(start)?.add(1),
+                };
+                let mut substring_args = vec![value, start];
+                if arguments.len() > 2 {
+                    substring_args.push(self.argument(arguments, 2)?);
+                }
+                Func::cust(Alias::new("SUBSTR")).args(substring_args).into()
+            }
+            Function::ToLower => Func::lower(self.argument(arguments, 0)?).into(),
+            Function::ToUpper => Func::upper(self.argument(arguments, 0)?).into(),
+            Function::Trim => Func::cust(Alias::new("TRIM")).arg(self.argument(arguments, 0)?).into(),
+            Function::Concat => self.concat([self.argument(arguments, 0)?, self.argument(arguments, 1)?]),
+            Function::MatchesPattern => {
+                let value = self.argument(arguments, 0)?;
+                let pattern = self.argument(arguments, 1)?;
+                let operator = match self.backend {
+                    DbBackend::Postgres => BinOper::Custom("~"),
+                    DbBackend::MySql => BinOper::Custom("REGEXP"),
+                    // the REGEXP operator of SQLite calls a function that SQLite doesn't define itself
+                    DbBackend::Sqlite => {
+                        return Err(unsupported(format!("'{}' on {:?}", function.name(), self.backend)))
+                    }
+                };
+                Expr::expr(value).binary(operator, pattern)
+            }
+            Function::Year | Function::Month | Function::Day | Function::Hour | Function::Minute | Function::Second => {
+                self.date_part(function, self.argument(arguments, 0)?)?
+            }
+            Function::FractionalSeconds => {
+                let value = self.argument(arguments, 0)?;
+                match self.backend {
+                    DbBackend::Postgres => {
+                        self.custom("EXTRACT(SECOND FROM $1) - FLOOR(EXTRACT(SECOND FROM $1))", [value])
+                    }
+                    DbBackend::MySql => self.custom("MICROSECOND($1) / 1000000.0", [value]),
+                    DbBackend::Sqlite => self.custom(
+                        "CAST(strftime('%f', $1) AS REAL) - CAST(strftime('%S', $1) AS INTEGER)",
+                        [value],
+                    ),
+                }
+            }
+            Function::Date => {
+                let value = self.argument(arguments, 0)?;
+                match self.backend {
+                    DbBackend::Postgres => value.cast_as(Alias::new("DATE")),
+                    DbBackend::MySql | DbBackend::Sqlite => Func::cust(Alias::new("DATE")).arg(value).into(),
+                }
+            }
+            Function::Time => {
+                let value = self.argument(arguments, 0)?;
+                match self.backend {
+                    DbBackend::Postgres => value.cast_as(Alias::new("TIME")),
+                    DbBackend::MySql | DbBackend::Sqlite => Func::cust(Alias::new("TIME")).arg(value).into(),
+                }
+            }
+            Function::TotalOffsetMinutes => {
+                let value = self.argument(arguments, 0)?;
+                match self.backend {
+                    DbBackend::Postgres => self.custom("EXTRACT(TIMEZONE FROM $1) / 60", [value]),
+                    // DATETIME and TIMESTAMP values don't keep the offset they were written with
+                    DbBackend::MySql => {
+                        return Err(unsupported(format!("'{}' on {:?}", function.name(), self.backend)))
+                    }
+                    // the difference between the local and the UTC interpretation of an ISO 8601 text value
+                    DbBackend::Sqlite => self.custom(
+                        "CAST(ROUND((julianday(substr($1, 1, 19)) - julianday($1)) * 1440) AS INTEGER)",
+                        [value],
+                    ),
+                }
+            }
+            Function::Now => Expr::current_timestamp().into(),
+            Function::MaxDateTime => self.date_time_literal("9999-12-31 23:59:59.999999"),
+            Function::MinDateTime => self.date_time_literal("0001-01-01 00:00:00"),
+        };
+
+        Ok(expression)
+    }
+
+    /// Translate `cast` to a primitive type into a SQL CAST; type checks with `isof` and casts to structured types
+    /// have no SQL translation, as a table doesn't record the type of its rows, so they are rejected
+    pub(crate) fn type_function_opp(&self, type_function: &TypeFunction) -> ODataResult<SimpleExpr> {
+        let expression = || Expression::TypeFunction(type_function.clone());
+        let TypeFunction {
+            function: TypeFunctionKind::Cast,
+            operand: Some(operand),
+            type_name,
+        } = type_function
+        else {
+            return Err(unsupported(format!("'{}'", expression())));
+        };
+        if !type_name.starts_with("Edm.") {
+            return Err(unsupported(format!("'{}'", expression())));
+        }
+
+        let sql_type = match (type_name.as_str(), self.backend) {
+            ("Edm.String", DbBackend::MySql) => "CHAR",
+            ("Edm.String", DbBackend::Postgres | DbBackend::Sqlite) => "TEXT",
+            ("Edm.Byte" | "Edm.SByte" | "Edm.Int16" | "Edm.Int32" | "Edm.Int64", DbBackend::MySql) => "SIGNED",
+            ("Edm.Byte" | "Edm.SByte" | "Edm.Int16", DbBackend::Postgres) => "SMALLINT",
+            ("Edm.Int32", DbBackend::Postgres) => "INTEGER",
+            ("Edm.Int64", DbBackend::Postgres) => "BIGINT",
+            ("Edm.Byte" | "Edm.SByte" | "Edm.Int16" | "Edm.Int32" | "Edm.Int64", DbBackend::Sqlite) => "INTEGER",
+            ("Edm.Decimal", DbBackend::MySql) => "DECIMAL(65, 30)",
+            ("Edm.Decimal", DbBackend::Postgres | DbBackend::Sqlite) => "NUMERIC",
+            ("Edm.Double" | "Edm.Single", DbBackend::MySql) => "DOUBLE",
+            ("Edm.Double" | "Edm.Single", DbBackend::Postgres) => "DOUBLE PRECISION",
+            ("Edm.Double" | "Edm.Single", DbBackend::Sqlite) => "REAL",
+            ("Edm.Date", DbBackend::MySql | DbBackend::Postgres) => "DATE",
+            ("Edm.TimeOfDay", DbBackend::MySql | DbBackend::Postgres) => "TIME",
+            _ => return Err(unsupported(format!("'{}' on {:?}", expression(), self.backend))),
+        };
+
+        Ok(self.build_operand(operand)?.cast_as(Alias::new(sql_type)))
+    }
+
+    /// Extract a single component of a date or time value
+    fn date_part(&self, function: Function, value: SimpleExpr) -> ODataResult<SimpleExpr> {
+        let (part, sqlite_format) = match function {
+            Function::Year => ("YEAR", "%Y"),
+            Function::Month => ("MONTH", "%m"),
+            Function::Day => ("DAY", "%d"),
+            Function::Hour => ("HOUR", "%H"),
+            Function::Minute => ("MINUTE", "%M"),
+            Function::Second => ("SECOND", "%S"),
+            _ => return Err(unsupported(format!("'{}' as a date part", function.name()))),
+        };
+
+        let expression = match self.backend {
+            // EXTRACT(SECOND ...) includes the fractional seconds
+            DbBackend::Postgres if function == Function::Second => {
+                self.custom("FLOOR(EXTRACT(SECOND FROM $1))", [value])
+            }
+            DbBackend::Postgres => self.custom(&format!("EXTRACT({part} FROM $1)"), [value]),
+            DbBackend::MySql => Func::cust(Alias::new(part)).arg(value).into(),
+            DbBackend::Sqlite => self.custom(&format!("CAST(strftime('{sqlite_format}', $1) AS INTEGER)"), [value]),
+        };
+
+        Ok(expression)
+    }
+
+    fn date_time_literal(&self, value: &str) -> SimpleExpr {
+        match self.backend {
+            DbBackend::Postgres => Expr::val(format!("{value}+00")).cast_as(Alias::new("TIMESTAMPTZ")),
+            DbBackend::MySql => Expr::val(value).cast_as(Alias::new("DATETIME(6)")),
+            // SQLite stores date and time values as ISO 8601 text
+            DbBackend::Sqlite => Expr::val(value).into(),
+        }
+    }
+
+    fn argument(&self, arguments: &[Expression], pos: usize) -> ODataResult<SimpleExpr> {
+        self.build_operand(self.expression_argument(arguments, pos)?)
+    }
+
+    fn expression_argument<'a>(&self, arguments: &'a [Expression], pos: usize) -> ODataResult<&'a Expression> {
+        arguments
+            .get(pos)
+            .ok_or_else(|| ODataError::InvalidQuery(format!("argument {} of the function is missing", pos + 1)))
+    }
+
+    /// Concatenate strings; `||` is the logical OR operator in MySQL, so use CONCAT there
+    fn concat<const N: usize>(&self, values: [SimpleExpr; N]) -> SimpleExpr {
+        match self.backend {
+            DbBackend::MySql => Func::cust(Alias::new("CONCAT")).args(values).into(),
+            DbBackend::Postgres | DbBackend::Sqlite => values
+                .into_iter()
+                .reduce(|left, right| Expr::expr(left).binary(BinOper::Custom("||"), right))
+                .expect("at least one value to concatenate"),
+        }
+    }
+
+    /// Translate contains, startswith and endswith into a LIKE expression, with wildcards before and/or after the
+    /// searched value
+    fn like_opp(&self, arguments: &[Expression], leading: bool, trailing: bool) -> ODataResult<SimpleExpr> {
+        let value = Expr::expr(self.argument(arguments, 0)?);
+        let wildcard = |add: bool| if add { "%" } else { "" };
+
+        if let Expression::Literal(Value::String(search)) = self.expression_argument(arguments, 1)? {
+            let escaped = escape_like(search);
+            let pattern = format!("{}{escaped}{}", wildcard(leading), wildcard(trailing));
+            let like = if escaped.len() == search.len() {
+                LikeExpr::new(pattern)
+            } else {
+                LikeExpr::new(pattern).escape(LIKE_ESCAPE)
+            };
+            return Ok(value.like(like));
+        }
+
+        let search = self.argument(arguments, 1)?;
+        let pattern = match (leading, trailing) {
+            (true, true) => self.concat([Expr::val("%").into(), search, Expr::val("%").into()]),
+            (true, false) => self.concat([Expr::val("%").into(), search]),
+            (false, true) => self.concat([search, Expr::val("%").into()]),
+            (false, false) => search,
+        };
+
+        Ok(value.binary(BinOper::Like, pattern))
+    }
+}
+
+/// Escape the LIKE wildcards, so they are matched literally
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        if c == '%' || c == '_' || c == LIKE_ESCAPE {
+            escaped.push(LIKE_ESCAPE);
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
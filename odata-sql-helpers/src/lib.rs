@@ -1,19 +1,30 @@
+use apply::Aggregation;
+use expand::EntityList;
+use filter::{into_simple_expr, navigation_joins, FilterBuilder};
 use heck::ToSnakeCase;
-use odata_model::resource::{Chain, FieldFilter, FilterOperation, Filters, ODataResource, Value};
+use odata_model::apply::{Compute, Transformation};
+use odata_model::error::{ODataError, ODataResult};
+use odata_model::expression::Expression;
+use odata_model::resource::{Key, ODataResource};
 use odata_model::resource::{OrderBy, OrderByDirection};
+use odata_model::select::{selects_all, SelectItem};
 use sea_orm::entity::prelude::*;
 use sea_orm::entity::Iterable;
 use sea_orm::{
-    sea_query::{ColumnRef, Expr, Func, IntoCondition, SimpleExpr},
-    Condition, EntityTrait, QueryFilter, QueryOrder, Select,
+    sea_query::{Alias, Asterisk, ColumnRef, Expr, Func, IntoIden, SimpleExpr, TableRef},
+    Condition, DbBackend, EntityTrait, JoinType, QueryFilter, QueryOrder, Select,
 };
 use sea_orm::{IntoSimpleExpr, Order, QuerySelect};
 
+mod apply;
+pub mod expand;
+mod filter;
+mod functions;
 pub mod reflect;
 #[cfg(test)]
 mod tests;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct ColumnList {
     keys: Vec<String>,
     values: Vec<ColumnValue>,
@@ -77,13 +88,18 @@ impl ColumnList {
         self.values.get(pos)
     }
 
-    pub fn iter(&self) -> ColumnListIterator {
+    pub fn iter(&self) -> ColumnListIterator<'_> {
         ColumnListIterator { items: self, index: 0 }
     }
 
     pub fn keys(&self) -> Vec<&str> {
         self.keys.iter().map(|k| k.as_str()).collect()
     }
+
+    fn push(&mut self, key: String, value: ColumnValue) {
+        self.keys.push(key);
+        self.values.push(value);
+    }
 }
 
 impl FromIterator<(String, ColumnValue)> for ColumnList {
@@ -115,41 +131,124 @@ impl<'i> Iterator for ColumnListIterator<'i> {
     }
 }
 
-/// Apply the ODataResource filter to the SeaOrm query
+/// Apply the ODataResource filter to the SeaOrm query, in the SQL dialect of the database backend, as functions and
+/// literals differ between the backends
 /// ```ignore
 /// use odata_model::resource::ODataResource;
-/// use self::WithFilterExt;
+/// use self::WithODataExt;
 ///
-/// let filter = ODataResource::default();
-/// SomeEntity::find().with_odata_filter(&filter);
+/// let resource = ODataResource::default();
+/// SomeEntity::find().with_odata_resource(&resource, db.get_database_backend())?;
 /// ```
+///
+/// A $select restricts the selected columns, so the rows no longer fit the entity model; read them with `into_json()`.
+/// The same goes for $apply, which selects the grouping properties and the aggregated values by their alias; $filter and
+/// $orderby then apply to the aggregated rows. $compute adds the computed properties by their alias, unless $select
+/// leaves them out.
+///
+/// Paths through complex properties refer to the columns named after the path, e.g. `address_city` for `Address/City`.
+/// Paths through a to-one navigation property, e.g. `$orderby=Customer/Name`, left join the related table. Such paths,
+/// and the lambda operators `any` and `all`, refer to the properties of the related entities, which must be in the
+/// [`EntityList`] of [`WithODataExt::with_odata_resource_in`].
+///
+/// Paths without a column fail with [`ODataError::InvalidQuery`], and expressions without a SQL translation, e.g.
+/// `has`, with [`ODataError::Unsupported`]; the query is never widened by leaving a part of it out.
 pub trait WithODataExt<E>
 where
     E: EntityTrait,
 {
-    fn with_odata_resource(self, resource: &ODataResource) -> Self;
+    /// Apply the resource using the SQL dialect of the given database backend
+    fn with_odata_resource(self, resource: &ODataResource, backend: DbBackend) -> ODataResult<Self>
+    where
+        Self: Sized,
+    {
+        self.with_odata_resource_in(resource, backend, &EntityList::new())
+    }
+
+    /// Apply the resource, with the related entities that its navigation paths and lambdas refer to
+    fn with_odata_resource_in(
+        self,
+        resource: &ODataResource,
+        backend: DbBackend,
+        entities: &EntityList,
+    ) -> ODataResult<Self>
+    where
+        Self: Sized;
+
+    /// Count the entities of the resource, for `/$count` and `$count=true`. Only $filter and $search apply, so the
+    /// count is the total number of entities, regardless of $top and $skip. The count is selected as
+    /// [`COUNT_COLUMN`], e.g. `.into_tuple::<i64>().one(&db)`
+    fn with_odata_count(self, resource: &ODataResource, backend: DbBackend) -> ODataResult<Self>
+    where
+        Self: Sized,
+    {
+        self.with_odata_count_in(resource, backend, &EntityList::new())
+    }
+
+    /// Count the entities of the resource, with the related entities that its navigation paths and lambdas refer to
+    fn with_odata_count_in(
+        self,
+        resource: &ODataResource,
+        backend: DbBackend,
+        entities: &EntityList,
+    ) -> ODataResult<Self>
+    where
+        Self: Sized;
 }
 
+/// The name of the column selected by [`WithODataExt::with_odata_count`]
+pub const COUNT_COLUMN: &str = "count";
+
 impl<E> WithODataExt<E> for Select<E>
 where
     E: EntityTrait,
 {
-    fn with_odata_resource(self, resource: &ODataResource) -> Self {
-        let (_pkeys, columns) = get_column_names::<E>();
+    fn with_odata_resource_in(
+        self,
+        resource: &ODataResource,
+        backend: DbBackend,
+        entities: &EntityList,
+    ) -> ODataResult<Self> {
+        let (p_keys, columns) = get_column_names::<E>();
+        let relations = get_relations::<E>();
+        let joins = resource_joins(resource, &relations);
+        let builder = FilterBuilder::new(&columns, backend)
+            .with_relations(&relations)
+            .with_entities(entities)
+            .with_joins(joins.clone());
+        let computed = computed_columns(&resource.compute, &builder)?;
+        let builder = builder.with_computed(&computed);
+        let query = join_navigations(self, &joins, &relations);
+
+        let mut query = if resource.apply.is_empty() {
+            let mut query = query.filter(resource_condition(resource, &builder)?);
+
+            let select_all = selects_all(&resource.select);
+            if !select_all {
+                query = query
+                    .select_only()
+                    .columns(selected_columns::<E>(&resource.select, &p_keys));
+            }
 
-        let mut query = self.filter(condition_with_filter(resource, &columns));
+            for compute in &resource.compute {
+                let name = compute.alias.to_snake_case();
+                if let Some(column) = computed
+                    .get(&name)
+                    .filter(|_| select_all || is_selected(&resource.select, &name))
+                {
+                    query = query.column_as(column.column.clone(), compute.alias.as_str());
+                }
+            }
 
-        for order_by in &resource.order_by {
-            let OrderBy { field, direction } = order_by;
-            let field = field.to_snake_case();
-            let col = SimpleColumn(field).into_simple_expr();
-            let order = if direction == &OrderByDirection::Desc {
-                Order::Desc
-            } else {
-                Order::Asc
-            };
-            query = query.order_by(col, order)
-        }
+            for order_by in &resource.order_by {
+                let (col, order) = order_by_opp(&builder, order_by)?;
+                query = query.order_by(col, order)
+            }
+
+            query
+        } else {
+            apply_opp(query, resource, &builder)?
+        };
 
         // top and skip
         if let Some(skip) = resource.skip {
@@ -160,7 +259,28 @@ where
             query = query.limit(Some(top as u64));
         }
 
-        query
+        Ok(query)
+    }
+
+    fn with_odata_count_in(
+        self,
+        resource: &ODataResource,
+        backend: DbBackend,
+        entities: &EntityList,
+    ) -> ODataResult<Self> {
+        let (_p_keys, columns) = get_column_names::<E>();
+        let relations = get_relations::<E>();
+        let joins = resource_joins(resource, &relations);
+        let builder = FilterBuilder::new(&columns, backend)
+            .with_relations(&relations)
+            .with_entities(entities)
+            .with_joins(joins.clone());
+        let computed = computed_columns(&resource.compute, &builder)?;
+
+        Ok(join_navigations(self, &joins, &relations)
+            .filter(resource_condition(resource, &builder.with_computed(&computed))?)
+            .select_only()
+            .column_as(Expr::col(Asterisk).count(), COUNT_COLUMN))
     }
 }
 
@@ -179,6 +299,148 @@ impl IntoSimpleExpr for SimpleColumn {
     }
 }
 
+/// Group and aggregate the rows with the $apply transformations, then filter and sort the aggregated rows
+fn apply_opp<E: EntityTrait>(
+    query: Select<E>,
+    resource: &ODataResource,
+    builder: &FilterBuilder,
+) -> ODataResult<Select<E>> {
+    let mut query = match resource.search.as_deref() {
+        Some(search) => query.filter(search_and_filter_condition(Some(search), None, builder)?),
+        None => query,
+    };
+
+    let aggregation = Aggregation::new(query, builder).apply(&resource.apply)?;
+    query = aggregation.query;
+
+    // without grouping, the rows keep their columns
+    let Some(result) = aggregation.result else {
+        if let Some(filter) = &resource.filter {
+            query = query.filter(builder.build_condition(filter)?);
+        }
+        for order_by in &resource.order_by {
+            let (col, order) = order_by_opp(builder, order_by)?;
+            query = query.order_by(col, order);
+        }
+        return Ok(query);
+    };
+
+    let aggregated = FilterBuilder::new(&result, builder.backend);
+    if let Some(filter) = &resource.filter {
+        query = query.having(aggregated.build_condition(filter)?);
+    }
+    for OrderBy { expression, direction } in &resource.order_by {
+        query = query.order_by(aggregated.build_operand(expression)?, order_direction(direction));
+    }
+
+    Ok(query)
+}
+
+/// The to-one navigation properties of the $filter, $orderby, $compute and $apply paths, e.g. `Customer` of
+/// `$orderby=Customer/Name`
+fn resource_joins(resource: &ODataResource, relations: &RelationList) -> Vec<String> {
+    let mut applied = Vec::new();
+    transformation_expressions(&resource.apply, &mut applied);
+
+    let expressions = resource
+        .filter
+        .iter()
+        .chain(resource.order_by.iter().map(|order_by| &order_by.expression))
+        .chain(resource.compute.iter().map(|compute| &compute.expression))
+        .chain(applied.iter());
+
+    navigation_joins(expressions, relations)
+}
+
+/// The filters, grouping properties and aggregated expressions of the $apply transformations
+fn transformation_expressions(transformations: &[Transformation], expressions: &mut Vec<Expression>) {
+    for transformation in transformations {
+        match transformation {
+            Transformation::Filter(filter) => expressions.push(filter.clone()),
+            Transformation::GroupBy(properties, transformations) => {
+                expressions.extend(properties.iter().cloned().map(Expression::Member));
+                transformation_expressions(transformations, expressions);
+            }
+            Transformation::Aggregate(aggregates) => {
+                expressions.extend(aggregates.iter().filter_map(|aggregate| aggregate.expression.clone()));
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Left join the tables of the navigation properties by their snake cased name, so entities without a related entity
+/// are kept, e.g. `LEFT JOIN "users" AS "user"` for `User/FirstName`
+fn join_navigations<E: EntityTrait>(mut query: Select<E>, joins: &[String], relations: &RelationList) -> Select<E> {
+    for join in joins {
+        if let Some((table, condition)) = relations
+            .get(join)
+            .and_then(|relation| relations.related_table(relation, &relations.table, join))
+        {
+            QuerySelect::query(&mut query).join(JoinType::LeftJoin, table, condition);
+        }
+    }
+
+    query
+}
+
+/// The expression and direction to sort on; plain properties are sorted by name, other expressions are translated.
+/// With joined tables, properties are translated as well, as their names may be ambiguous.
+fn order_by_opp(builder: &FilterBuilder, order_by: &OrderBy) -> ODataResult<(SimpleExpr, Order)> {
+    let OrderBy { expression, direction } = order_by;
+    let col = match expression.member().and_then(|path| path.as_property()) {
+        Some(field)
+            if !builder.is_computed(field)
+                && !builder.has_joins()
+                && builder.table_columns.contains_key(&field.to_snake_case()) =>
+        {
+            SimpleColumn(field.to_snake_case()).into_simple_expr()
+        }
+        _ => builder.build_operand(expression)?,
+    };
+
+    Ok((col, order_direction(direction)))
+}
+
+fn order_direction(direction: &OrderByDirection) -> Order {
+    if direction == &OrderByDirection::Desc {
+        Order::Desc
+    } else {
+        Order::Asc
+    }
+}
+
+/// The columns of the $select items in the order of the entity; the primary keys are always included, so the
+/// selected rows can still be identified
+fn selected_columns<E: EntityTrait>(select: &[SelectItem], p_keys: &PrimaryKeys) -> Vec<E::Column> {
+    E::Column::iter()
+        .filter(|col| {
+            let name = col.to_string();
+            p_keys.iter().any(|key| key == name) || is_selected(select, &name)
+        })
+        .collect()
+}
+
+fn is_selected(select: &[SelectItem], column: &str) -> bool {
+    select
+        .iter()
+        .filter_map(|item| item.property())
+        .any(|property| property.to_snake_case() == column)
+}
+
+/// The expressions of $compute, by their snake cased alias
+fn computed_columns(compute: &[Compute], builder: &FilterBuilder) -> ODataResult<ColumnList> {
+    compute
+        .iter()
+        .map(|compute| {
+            Ok((
+                compute.alias.to_snake_case(),
+                builder.build_column(&compute.expression)?,
+            ))
+        })
+        .collect()
+}
+
 pub fn get_column_names<E: EntityTrait>() -> (PrimaryKeys, ColumnList) {
     let p_keys = E::PrimaryKey::iter()
         .map(|pkey| pkey.into_column().as_column_ref())
@@ -198,173 +460,135 @@ pub fn get_column_names<E: EntityTrait>() -> (PrimaryKeys, ColumnList) {
     (p_keys, column_list)
 }
 
-pub fn condition_with_filter(resource: &ODataResource, table_columns: &ColumnList) -> impl IntoCondition {
-    let mut condition = Condition::all();
-
-    if let Some(search) = &resource.search {
-        let mut search_condition = Condition::any();
-
-        for (_id, col) in table_columns.iter() {
-            search_condition = search_condition.add(like_opp(col.clone(), search));
-        }
+/// The relations of an entity, by the name of their `Relation` variant, e.g. `Orders`
+#[derive(Debug)]
+pub struct RelationList {
+    table: String,
+    relations: Vec<(String, RelationDef)>,
+}
 
-        condition = condition.add(search_condition);
+impl RelationList {
+    /// Find a relation by its navigation property name, e.g. `Orders` or `orders`
+    pub fn get(&self, name: &str) -> Option<&RelationDef> {
+        let snaked = name.to_snake_case();
+        self.relations
+            .iter()
+            .find(|(relation, _)| relation.to_snake_case() == snaked)
+            .map(|(_, def)| def)
     }
 
-    if !resource.filters.is_empty() {
-        let filters = &resource.filters;
-        let filter_condition = build_condition(filters, table_columns);
-        condition = condition.add(filter_condition);
+    pub fn names(&self) -> Vec<&str> {
+        self.relations.iter().map(|(name, _)| name.as_str()).collect()
     }
 
-    condition
-}
-
-fn build_condition(filters: &Filters, table_columns: &ColumnList) -> Condition {
-    let (mut condition, and_groups) = build_condition_from_chain(filters);
-    let mut grouped_condition: Option<Condition> = None;
-
-    for (pos, (field_filter, _chain)) in filters.iter().enumerate() {
-        match field_filter {
-            FieldFilter::Contents(c) => {
-                if and_groups.within_group(pos) {
-                    if grouped_condition.is_none() {
-                        grouped_condition = Some(Condition::all())
-                    }
-                } else if let Some(use_grouped_condition) = grouped_condition.take() {
-                    // add the previous grouped condition to the top level condition
-                    condition = condition.add(use_grouped_condition);
-                }
-
-                let snaked = c.field.to_snake_case();
-                if let Some(col) = table_columns.get(&snaked) {
-                    let col = &col.column;
-                    if let Some(use_grouped_condition) = grouped_condition.take() {
-                        grouped_condition =
-                            Some(use_grouped_condition.add(compare_opp(col.clone(), &c.operation, c.not)));
-                    } else {
-                        condition = condition.add(compare_opp(col.clone(), &c.operation, c.not));
-                    }
-                }
+    /// The related table of a relation by an alias, and the condition that matches its rows to the rows of the parent,
+    /// which is the table itself or the alias of a related table, e.g. of a range variable
+    pub(crate) fn related_table(
+        &self,
+        relation: &RelationDef,
+        parent: &str,
+        alias: &str,
+    ) -> Option<(TableRef, Condition)> {
+        let related_table = match &relation.to_tbl {
+            TableRef::Table(table) | TableRef::TableAlias(table, _) => {
+                TableRef::TableAlias(table.clone(), Alias::new(alias).into_iden())
             }
-            FieldFilter::Nested((not, filters)) => {
-                let mut contents_condition = build_condition(filters, table_columns);
-                if *not {
-                    contents_condition = contents_condition.not();
-                }
-                condition = condition.add(contents_condition);
+            TableRef::SchemaTable(schema, table) | TableRef::SchemaTableAlias(schema, table, _) => {
+                TableRef::SchemaTableAlias(schema.clone(), table.clone(), Alias::new(alias).into_iden())
             }
+            _ => return None,
+        };
+
+        let mut condition = Condition::all();
+        for (from, to) in relation.from_col.clone().into_iter().zip(relation.to_col.clone()) {
+            condition = condition.add(Expr::col((Alias::new(alias), to)).equals((Alias::new(parent), from)));
         }
-    }
 
-    if let Some(use_grouped_condition) = grouped_condition.take() {
-        condition = condition.add(use_grouped_condition);
+        Some((related_table, condition))
     }
-
-    condition
 }
 
-struct AndGroups(Vec<(usize, usize)>);
+pub fn get_relations<E: EntityTrait>() -> RelationList {
+    let relations = E::Relation::iter()
+        .map(|relation| (format!("{relation:?}"), relation.def()))
+        .collect();
 
-impl AndGroups {
-    /// Determine if the provided pos is within a group of ANDs
-    fn within_group(&self, pos: usize) -> bool {
-        self.0.iter().any(|(start, end)| pos >= *start && pos <= *end)
+    RelationList {
+        table: E::default().table_name().to_string(),
+        relations,
     }
 }
 
-fn build_condition_from_chain(filters: &Filters) -> (Condition, AndGroups) {
-    let mut top_level_condition = Condition::all();
-
-    let mut first_and_pos: Option<usize> = None;
-    let mut and_groups: Vec<(usize, usize)> = vec![];
+/// The condition that selects the entity with the given key, e.g. `OrderItems(OrderId=1,ItemNo=2)`; the key is
+/// validated against the primary key of the entity, where the names of the key properties are matched in snake case.
+/// ```ignore
+/// let condition = primary_key_condition::<order_item::Entity>(resource.entity.key.as_ref().unwrap())?;
+/// order_item::Entity::find().filter(condition).one(&db).await?;
+/// ```
+pub fn primary_key_condition<E: EntityTrait>(key: &Key) -> ODataResult<Condition> {
+    let entity_type = reflect::into_entity_type::<E>();
+    let (_p_keys, columns) = get_column_names::<E>();
 
-    for (pos, (_filter, chain)) in filters.0.iter().enumerate() {
-        if let Some(chain) = chain {
-            match chain {
-                Chain::And => {
-                    if first_and_pos.is_none() {
-                        first_and_pos = Some(pos);
-                    }
-                }
-                Chain::Or => {
-                    if let Some(use_first_and_pos) = first_and_pos {
-                        if use_first_and_pos != pos - 1 {
-                            and_groups.push((use_first_and_pos, pos - 1));
-                        }
-
-                        first_and_pos = None;
-                    }
-                    top_level_condition = Condition::any();
-                }
-            }
-        }
-    }
+    let key = Key(key
+        .0
+        .iter()
+        .map(|(name, value)| (name.as_deref().map(str::to_snake_case), value.clone()))
+        .collect());
 
-    // close the group if we have one
-    if let Some(use_first_and_pos) = first_and_pos {
-        let pos = filters.0.len() - 1;
-        if use_first_and_pos != pos {
-            and_groups.push((use_first_and_pos, pos));
-        }
+    let mut condition = Condition::all();
+    for (name, value) in key.named_values(&entity_type)? {
+        let (Some(column), Some(value)) = (columns.get(&name), into_simple_expr(&value)) else {
+            return Err(ODataError::InvalidKey(format!(
+                "unsupported value '{value}' for '{name}'"
+            )));
+        };
+        condition = condition.add(Expr::expr(column.column.clone()).eq(value));
     }
 
-    (top_level_condition, AndGroups(and_groups))
+    Ok(condition)
 }
 
-fn like_opp(column: ColumnValue, pattern: &str) -> SimpleExpr {
-    let column = column.column;
-    let like = format!("%{}%", pattern.to_lowercase());
-    Expr::expr(Func::lower(column)).like(like)
+/// The condition for the $search and $filter of the resource, in the SQL dialect of the given database backend
+pub fn condition_with_filter(
+    resource: &ODataResource,
+    table_columns: &ColumnList,
+    backend: DbBackend,
+) -> ODataResult<Condition> {
+    resource_condition(resource, &FilterBuilder::new(table_columns, backend))
 }
 
-fn into_simple_expr(v: &Value) -> SimpleExpr {
-    match v {
-        Value::String(s) => SimpleExpr::from(s),
-        Value::Integer(n) => (*n).into(),
-        Value::Decimal(d) => (*d).into(),
-        Value::Boolean(b) => (*b).into(),
-        _ => panic!("Not implemented"),
-    }
+fn resource_condition(resource: &ODataResource, builder: &FilterBuilder) -> ODataResult<Condition> {
+    search_and_filter_condition(resource.search.as_deref(), resource.filter.as_ref(), builder)
 }
 
-fn null_or(value: &Value, col: ColumnRef, expr: SimpleExpr) -> SimpleExpr {
-    match value {
-        Value::Null => Expr::col(col).is_null(),
-        _ => expr,
-    }
-}
+/// The condition for $search, which matches any column of the table, and $filter
+fn search_and_filter_condition(
+    search: Option<&str>,
+    filter: Option<&Expression>,
+    builder: &FilterBuilder,
+) -> ODataResult<Condition> {
+    let mut condition = Condition::all();
 
-fn not_null_or(value: &Value, col: ColumnRef, expr: SimpleExpr) -> SimpleExpr {
-    match value {
-        Value::Null => Expr::col(col).is_not_null(),
-        _ => expr,
-    }
-}
+    if let Some(search) = search {
+        let mut search_condition = Condition::any();
 
-fn compare_opp(column: SimpleExpr, operation: &FilterOperation, negate: bool) -> SimpleExpr {
-    let expression = if let SimpleExpr::Column(col) = column {
-        match operation {
-            FilterOperation::Eq(value) => null_or(value, col.clone(), Expr::col(col).eq(into_simple_expr(value))),
-            FilterOperation::Ne(value) => not_null_or(value, col.clone(), Expr::col(col).ne(into_simple_expr(value))),
-            FilterOperation::Gt(value) => Expr::col(col).gt(into_simple_expr(value)),
-            FilterOperation::Ge(value) => Expr::col(col).gte(into_simple_expr(value)),
-            FilterOperation::Lt(value) => Expr::col(col).lt(into_simple_expr(value)),
-            FilterOperation::Le(value) => Expr::col(col).lte(into_simple_expr(value)),
-            FilterOperation::In(values) => {
-                let values = values.iter().map(into_simple_expr);
-                Expr::col(col).is_in(values)
+        for (id, _col) in builder.table_columns.iter() {
+            if let Some(column) = builder.table_column(id) {
+                search_condition = search_condition.add(like_opp(column, search));
             }
-            FilterOperation::Has(_) => todo!(),
-            FilterOperation::Function(_) => todo!(),
         }
-    } else {
-        panic!("Not implemented for non-columns");
-    };
 
-    if negate {
-        expression.not()
-    } else {
-        expression
+        condition = condition.add(search_condition);
     }
+
+    if let Some(filter) = filter {
+        condition = condition.add(builder.build_condition(filter)?);
+    }
+
+    Ok(condition)
+}
+
+fn like_opp(column: SimpleExpr, pattern: &str) -> SimpleExpr {
+    let like = format!("%{}%", pattern.to_lowercase());
+    Expr::expr(Func::lower(column)).like(like)
 }
@@ -0,0 +1,97 @@
+use super::{build_query_for_backend, query_error_for_backend};
+use odata_model::error::ODataError;
+use sea_orm::DbBackend;
+
+#[test]
+fn can_generate_a_grouped_aggregate_query() {
+    let query = build_query_for_backend(
+        "orders?$apply=groupby((Category),aggregate(Price with sum as Total,$count as Count))",
+        DbBackend::Postgres,
+    );
+    assert_eq!(
+        r#"SELECT "category", SUM("price") AS "Total", COUNT(*) AS "Count" FROM "orders" GROUP BY "category""#,
+        query
+    );
+}
+
+#[test]
+fn can_generate_aggregates_without_grouping() {
+    let query = build_query_for_backend(
+        "orders?$apply=aggregate(Price with min as Cheapest,Price with max as Dearest,Quantity with average as Average,UserId with countdistinct as Customers)",
+        DbBackend::MySql,
+    );
+    assert_eq!(
+        "SELECT MIN(`price`) AS `Cheapest`, MAX(`price`) AS `Dearest`, AVG(`quantity`) AS `Average`, COUNT(DISTINCT `user_id`) AS `Customers` FROM `orders`",
+        query
+    );
+}
+
+#[test]
+fn can_filter_before_and_after_grouping() {
+    let query = build_query_for_backend(
+        "orders?$apply=filter(Quantity gt 1)/groupby((Category,UserId),filter(Price lt 1000)/aggregate(Price mul Quantity with sum as Total))/filter(Total ge 100)",
+        DbBackend::Postgres,
+    );
+    assert_eq!(
+        r#"SELECT "category", "user_id", SUM("price" * "quantity") AS "Total" FROM "orders" WHERE "quantity" > 1 AND "price" < 1000 GROUP BY "category", "user_id" HAVING SUM("price" * "quantity") >= 100"#,
+        query
+    );
+}
+
+#[test]
+fn can_filter_and_order_the_aggregated_rows() {
+    let query = build_query_for_backend(
+        "orders?$apply=groupby((Category),aggregate($count as Count))&$filter=Count gt 5&$orderby=Count desc,Category&$top=3",
+        DbBackend::Sqlite,
+    );
+    assert_eq!(
+        r#"SELECT "category", COUNT(*) AS "Count" FROM "orders" GROUP BY "category" HAVING COUNT(*) > 5 ORDER BY COUNT(*) DESC, "category" ASC LIMIT 3"#,
+        query
+    );
+}
+
+#[test]
+fn can_group_without_aggregating() {
+    let query = build_query_for_backend(
+        "orders?$apply=groupby((Category))/filter(Category ne null)&$select=Price",
+        DbBackend::Postgres,
+    );
+    assert_eq!(
+        r#"SELECT "category" FROM "orders" GROUP BY "category" HAVING "category" IS NOT NULL"#,
+        query
+    );
+}
+
+#[test]
+fn can_reject_untranslatable_transformations() {
+    let apply_error = |apply: &str| query_error_for_backend(&format!("orders?$apply={apply}"), DbBackend::Postgres);
+
+    let unsupported = [
+        ("identity/topcount(2,Price)/filter(Price gt 10)", "'topcount(2,Price)'"),
+        (
+            "concat(identity,aggregate($count as Count))",
+            "'concat(identity,aggregate($count as Count))'",
+        ),
+        ("compute(Price mul 2 as Double)", "'compute(Price mul 2 as Double)'"),
+        (
+            "aggregate(Price with Ns.median as Median,$count as Count)",
+            "'Price with Ns.median as Median'",
+        ),
+        (
+            "aggregate($count as Count)/groupby((Category))",
+            "'groupby((Category))'",
+        ),
+        ("filter(Category has Ns.Color'Red')", "'has'"),
+    ];
+    for (apply, transformation) in unsupported {
+        assert_eq!(
+            apply_error(apply).to_string(),
+            format!("unsupported OData query; {transformation} has no SQL translation"),
+            "{apply}"
+        );
+    }
+
+    let error = apply_error("groupby((Colour),aggregate($count as Count))");
+    assert!(matches!(error, ODataError::InvalidQuery(_)));
+    assert_eq!(error.to_string(), "invalid OData query; 'Colour' has no column");
+}
@@ -0,0 +1,35 @@
+use super::build_query_for_backend;
+use sea_orm::DbBackend;
+
+#[test]
+fn can_select_computed_properties() {
+    let query = build_query_for_backend("orders?$compute=Price mul Quantity as Total", DbBackend::Postgres);
+    assert_eq!(
+        r#"SELECT "orders"."id", "orders"."user_id", "orders"."category", "orders"."price", "orders"."quantity", "orders"."created_at", "price" * "quantity" AS "Total" FROM "orders" WHERE TRUE"#,
+        query
+    );
+}
+
+#[test]
+fn can_select_only_the_requested_computed_properties() {
+    let query = build_query_for_backend(
+        "orders?$compute=Price mul Quantity as Total,year(CreatedAt) as Year&$select=Category,Year",
+        DbBackend::MySql,
+    );
+    assert_eq!(
+        "SELECT `orders`.`id`, `orders`.`category`, YEAR(`created_at`) AS `Year` FROM `orders` WHERE TRUE",
+        query
+    );
+}
+
+#[test]
+fn can_filter_and_order_on_computed_properties() {
+    let query = build_query_for_backend(
+        "orders?$compute=Price mul Quantity as Total&$filter=Total gt 100&$orderby=Total desc,Category&$select=Total",
+        DbBackend::Sqlite,
+    );
+    assert_eq!(
+        r#"SELECT "orders"."id", "price" * "quantity" AS "Total" FROM "orders" WHERE "price" * "quantity" > 100 ORDER BY "price" * "quantity" DESC, "category" ASC"#,
+        query
+    );
+}
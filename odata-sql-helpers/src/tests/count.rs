@@ -0,0 +1,40 @@
+use super::test_model;
+use crate::WithODataExt;
+use odata_model::resource::ODataResource;
+use sea_orm::{DbBackend, EntityTrait, QueryTrait};
+
+fn build_count_query_for_backend(url: &str, backend: DbBackend) -> String {
+    let resource = ODataResource::try_from(url).expect("Failed to parse ODataResource");
+    test_model::Entity::find()
+        .with_odata_count(&resource, backend)
+        .expect("Failed to translate ODataResource")
+        .build(backend)
+        .to_string()
+}
+
+#[test]
+fn can_generate_a_count_query() {
+    let query = build_count_query_for_backend("users/$count", DbBackend::Postgres);
+    assert_eq!(r#"SELECT COUNT(*) AS "count" FROM "users" WHERE TRUE"#, query);
+}
+
+#[test]
+fn can_generate_a_count_query_with_filter_and_search() {
+    let query = build_count_query_for_backend(
+        "users?$filter=FirstName eq 'John'&$search=doe&$count=true",
+        DbBackend::MySql,
+    );
+    assert_eq!(
+        r#"SELECT COUNT(*) AS `count` FROM `users` WHERE (LOWER(`id`) LIKE '%doe%' OR LOWER(`first_name`) LIKE '%doe%' OR LOWER(`last_name`) LIKE '%doe%' OR LOWER(`doc`) LIKE '%doe%') AND `first_name` = 'John'"#,
+        query
+    );
+}
+
+#[test]
+fn can_ignore_paging_select_and_order_in_a_count_query() {
+    let query = build_count_query_for_backend(
+        "users?$count=true&$top=10&$skip=20&$orderby=LastName&$select=FirstName",
+        DbBackend::Sqlite,
+    );
+    assert_eq!(r#"SELECT COUNT(*) AS "count" FROM "users" WHERE TRUE"#, query);
+}
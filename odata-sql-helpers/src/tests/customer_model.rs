@@ -0,0 +1,17 @@
+use sea_orm::entity::prelude::*;
+
+/// A customer with the properties of its `Address` complex type mapped to columns, e.g. `address_city`
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+#[sea_orm(table_name = "customers")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub name: String,
+    pub address_street: String,
+    pub address_city: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,110 @@
+use super::{build_query_for_backend, mysql, pg, query_error_for_backend, sqlite};
+use sea_orm::DbBackend;
+
+#[test]
+fn can_generate_the_full_select_for_orders() {
+    let query = build_query_for_backend("orders?$filter=year(created_at) eq 2024", DbBackend::Postgres);
+    assert_eq!(
+        r#"SELECT "orders"."id", "orders"."user_id", "orders"."category", "orders"."price", "orders"."quantity", "orders"."created_at" FROM "orders" WHERE (EXTRACT(YEAR FROM "created_at")) = 2024"#,
+        query
+    );
+}
+
+#[test]
+fn can_generate_year_month_and_day() {
+    let url = "orders?$filter=year(CreatedAt) eq 2024 and month(CreatedAt) eq 2 and day(CreatedAt) eq 29";
+    assert_eq!(
+        pg(url),
+        r#"(EXTRACT(YEAR FROM "created_at")) = 2024 AND (EXTRACT(MONTH FROM "created_at")) = 2 AND (EXTRACT(DAY FROM "created_at")) = 29"#
+    );
+    assert_eq!(
+        mysql(url),
+        r#"YEAR(`created_at`) = 2024 AND MONTH(`created_at`) = 2 AND DAY(`created_at`) = 29"#
+    );
+    assert_eq!(
+        sqlite(url),
+        r#"(CAST(strftime('%Y', "created_at") AS INTEGER)) = 2024 AND (CAST(strftime('%m', "created_at") AS INTEGER)) = 2 AND (CAST(strftime('%d', "created_at") AS INTEGER)) = 29"#
+    );
+}
+
+#[test]
+fn can_generate_hour_minute_and_second() {
+    let url = "orders?$filter=hour(CreatedAt) ge 9 and minute(CreatedAt) lt 30 and second(CreatedAt) eq 0";
+    assert_eq!(
+        pg(url),
+        r#"(EXTRACT(HOUR FROM "created_at")) >= 9 AND (EXTRACT(MINUTE FROM "created_at")) < 30 AND (FLOOR(EXTRACT(SECOND FROM "created_at"))) = 0"#
+    );
+    assert_eq!(
+        mysql(url),
+        r#"HOUR(`created_at`) >= 9 AND MINUTE(`created_at`) < 30 AND SECOND(`created_at`) = 0"#
+    );
+    assert_eq!(
+        sqlite(url),
+        r#"(CAST(strftime('%H', "created_at") AS INTEGER)) >= 9 AND (CAST(strftime('%M', "created_at") AS INTEGER)) < 30 AND (CAST(strftime('%S', "created_at") AS INTEGER)) = 0"#
+    );
+}
+
+#[test]
+fn can_generate_fractional_seconds() {
+    let url = "orders?$filter=fractionalseconds(CreatedAt) gt 0.5";
+    assert_eq!(
+        pg(url),
+        r#"(EXTRACT(SECOND FROM "created_at") - FLOOR(EXTRACT(SECOND FROM "created_at"))) > 0.5"#
+    );
+    assert_eq!(mysql(url), r#"(MICROSECOND(`created_at`) / 1000000.0) > 0.5"#);
+    assert_eq!(
+        sqlite(url),
+        r#"(CAST(strftime('%f', "created_at") AS REAL) - CAST(strftime('%S', "created_at") AS INTEGER)) > 0.5"#
+    );
+}
+
+#[test]
+fn can_generate_date_and_time() {
+    let url = "orders?$filter=date(CreatedAt) eq date(now()) and time(CreatedAt) lt time(now())";
+    assert_eq!(
+        pg(url),
+        r#"CAST("created_at" AS DATE) = CAST(CURRENT_TIMESTAMP AS DATE) AND CAST("created_at" AS TIME) < CAST(CURRENT_TIMESTAMP AS TIME)"#
+    );
+    assert_eq!(
+        mysql(url),
+        r#"DATE(`created_at`) = DATE(CURRENT_TIMESTAMP) AND TIME(`created_at`) < TIME(CURRENT_TIMESTAMP)"#
+    );
+    assert_eq!(
+        sqlite(url),
+        r#"DATE("created_at") = DATE(CURRENT_TIMESTAMP) AND TIME("created_at") < TIME(CURRENT_TIMESTAMP)"#
+    );
+}
+
+#[test]
+fn can_generate_total_offset_minutes() {
+    let url = "orders?$filter=totaloffsetminutes(CreatedAt) eq 60";
+    assert_eq!(pg(url), r#"(EXTRACT(TIMEZONE FROM "created_at") / 60) = 60"#);
+    assert_eq!(
+        sqlite(url),
+        r#"(CAST(ROUND((julianday(substr("created_at", 1, 19)) - julianday("created_at")) * 1440) AS INTEGER)) = 60"#
+    );
+
+    // MySQL doesn't keep the offset of a date and time value
+    let error = query_error_for_backend(url, DbBackend::MySql);
+    assert_eq!(
+        error.to_string(),
+        "unsupported OData query; 'totaloffsetminutes' on MySql has no SQL translation"
+    );
+}
+
+#[test]
+fn can_generate_min_and_max_date_time() {
+    let url = "orders?$filter=CreatedAt gt mindatetime() and CreatedAt lt maxdatetime()";
+    assert_eq!(
+        pg(url),
+        r#""created_at" > CAST('0001-01-01 00:00:00+00' AS TIMESTAMPTZ) AND "created_at" < CAST('9999-12-31 23:59:59.999999+00' AS TIMESTAMPTZ)"#
+    );
+    assert_eq!(
+        mysql(url),
+        r#"`created_at` > CAST('0001-01-01 00:00:00' AS DATETIME(6)) AND `created_at` < CAST('9999-12-31 23:59:59.999999' AS DATETIME(6))"#
+    );
+    assert_eq!(
+        sqlite(url),
+        r#""created_at" > '0001-01-01 00:00:00' AND "created_at" < '9999-12-31 23:59:59.999999'"#
+    );
+}
@@ -0,0 +1,23 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "documents")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: Uuid,
+    pub title: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::revision_model::Entity")]
+    Revisions,
+}
+
+impl Related<super::revision_model::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Revisions.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,309 @@
+use crate::expand::{expand_json, EntityList, ExpandError};
+use crate::tests::{document_model, order_model, revision_model, test_model};
+use odata_model::error::ODataError;
+use odata_model::expand::parse_expand;
+use sea_orm::entity::prelude::*;
+use sea_orm::{DatabaseConnection, DbBackend, JsonValue, MockDatabase, Transaction};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// A row as the database returns it for the selected columns
+fn row<const N: usize>(values: [(&'static str, Value); N]) -> BTreeMap<&'static str, Value> {
+    BTreeMap::from(values)
+}
+
+fn users() -> Vec<JsonValue> {
+    vec![
+        json!({ "id": 1, "first_name": "John" }),
+        json!({ "id": 2, "first_name": "Jane" }),
+        json!({ "id": 3, "first_name": "Joe" }),
+    ]
+}
+
+fn entities() -> EntityList {
+    EntityList::new()
+        .with_entity::<test_model::Entity>()
+        .with_entity::<order_model::Entity>()
+}
+
+async fn expand_users(db: &DatabaseConnection, expand: &str) -> Vec<JsonValue> {
+    let expand = parse_expand(expand).expect("Failed to parse $expand");
+    expand_json::<test_model::Entity, _>(users(), &expand, &entities(), db)
+        .await
+        .expect("Failed to expand")
+}
+
+#[tokio::test]
+async fn can_expand_a_to_many_relation_with_one_query() {
+    let db = MockDatabase::new(DbBackend::Postgres)
+        .append_query_results([vec![
+            row([("id", 10.into()), ("category", "Books".into()), ("user_id", 1.into())]),
+            row([("id", 11.into()), ("category", "Books".into()), ("user_id", 2.into())]),
+            row([("id", 12.into()), ("category", "Games".into()), ("user_id", 1.into())]),
+        ]])
+        .into_connection();
+
+    let rows = expand_users(&db, "Orders($select=Category;$filter=Quantity gt 0;$orderby=Id desc)").await;
+
+    assert_eq!(
+        rows,
+        vec![
+            json!({ "id": 1, "first_name": "John", "Orders": [
+                { "id": 10, "category": "Books" },
+                { "id": 12, "category": "Games" },
+            ] }),
+            json!({ "id": 2, "first_name": "Jane", "Orders": [{ "id": 11, "category": "Books" }] }),
+            json!({ "id": 3, "first_name": "Joe", "Orders": [] }),
+        ]
+    );
+    assert_eq!(
+        db.into_transaction_log(),
+        vec![Transaction::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT "id", "category", "user_id" FROM "orders" WHERE "user_id" IN ($1, $2, $3) AND "quantity" > $4 ORDER BY "id" DESC"#,
+            [1i64.into(), 2i64.into(), 3i64.into(), 0i32.into()]
+        )]
+    );
+}
+
+#[tokio::test]
+async fn can_apply_top_skip_and_count_per_parent() {
+    let db = MockDatabase::new(DbBackend::Postgres)
+        .append_query_results([vec![
+            row([("user_id", 1.into()), ("$count", 3i64.into())]),
+            row([("user_id", 2.into()), ("$count", 1i64.into())]),
+        ]])
+        .append_query_results([vec![row([
+            ("id", 11.into()),
+            ("quantity", 3.into()),
+            ("user_id", 1.into()),
+            ("$row_number", 2i64.into()),
+        ])]])
+        .into_connection();
+
+    let rows = expand_users(&db, "Orders($select=Quantity;$top=1;$skip=1;$count=true)").await;
+
+    assert_eq!(rows[0]["Orders@odata.count"], json!(3));
+    assert_eq!(rows[0]["Orders"], json!([{ "id": 11, "quantity": 3 }]));
+    assert_eq!(rows[1]["Orders@odata.count"], json!(1));
+    assert_eq!(rows[1]["Orders"], json!([]));
+    assert_eq!(rows[2]["Orders@odata.count"], json!(0));
+    assert_eq!(
+        db.into_transaction_log(),
+        vec![
+            Transaction::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"SELECT "user_id", COUNT(*) AS "$count" FROM "orders" WHERE "user_id" IN ($1, $2, $3) GROUP BY "user_id""#,
+                [1i64.into(), 2i64.into(), 3i64.into()]
+            ),
+            Transaction::from_sql_and_values(
+                DbBackend::Postgres,
+                r#"SELECT * FROM (SELECT "id", "quantity", "user_id", ROW_NUMBER() OVER ( PARTITION BY "user_id" ORDER BY "id" ASC ) AS "$row_number" FROM "orders" WHERE "user_id" IN ($1, $2, $3)) AS "numbered" WHERE "$row_number" > $4 AND "$row_number" <= $5 ORDER BY "$row_number" ASC"#,
+                [1i64.into(), 2i64.into(), 3i64.into(), 1u64.into(), 2u64.into()]
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn can_filter_a_to_one_relation() {
+    let db = MockDatabase::new(DbBackend::Postgres)
+        .append_query_results([Vec::<BTreeMap<&str, Value>>::new()])
+        .into_connection();
+
+    let orders = vec![json!({ "id": 10, "user_id": 1 })];
+    let expand = parse_expand("User($filter=LastName eq 'Doe';$orderby=FirstName)").unwrap();
+    let rows = expand_json::<order_model::Entity, _>(orders, &expand, &entities(), &db)
+        .await
+        .expect("Failed to expand");
+
+    assert_eq!(rows, vec![json!({ "id": 10, "user_id": 1, "User": null })]);
+    assert_eq!(
+        db.into_transaction_log(),
+        vec![Transaction::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT "orders"."id" AS "$parent.id", "User"."id" AS "id", "User"."first_name" AS "first_name", "User"."last_name" AS "last_name", "User"."doc" AS "doc" FROM "orders" INNER JOIN "users" AS "User" ON "User"."id" = "orders"."user_id" WHERE "orders"."id" IN ($1) AND "User"."id" IN (SELECT "id" FROM "users" WHERE "last_name" = $2)"#,
+            [10i64.into(), "Doe".into()]
+        )]
+    );
+}
+
+#[tokio::test]
+async fn can_reject_invalid_options_of_a_to_one_relation() {
+    let db = MockDatabase::new(DbBackend::Postgres).into_connection();
+
+    let orders = vec![json!({ "id": 10, "user_id": 1 })];
+    let expand = parse_expand("User($orderby=Budget)").unwrap();
+    let error = expand_json::<order_model::Entity, _>(orders, &expand, &entities(), &db)
+        .await
+        .expect_err("Expected the $orderby to be rejected");
+
+    assert!(matches!(error, ExpandError::Query(ODataError::InvalidQuery(_))));
+    assert!(db.into_transaction_log().is_empty());
+}
+
+#[tokio::test]
+async fn can_expand_a_to_one_relation_with_a_join() {
+    let db = MockDatabase::new(DbBackend::Postgres)
+        .append_query_results([vec![row([
+            ("$parent.id", 10.into()),
+            ("id", 1.into()),
+            ("last_name", "Doe".into()),
+        ])]])
+        .into_connection();
+
+    let orders = vec![json!({ "id": 10, "user_id": 1 }), json!({ "id": 11, "user_id": null })];
+    let expand = parse_expand("User($select=LastName)").unwrap();
+    let rows = expand_json::<order_model::Entity, _>(orders, &expand, &entities(), &db)
+        .await
+        .expect("Failed to expand");
+
+    assert_eq!(
+        rows,
+        vec![
+            json!({ "id": 10, "user_id": 1, "User": { "id": 1, "last_name": "Doe" } }),
+            json!({ "id": 11, "user_id": null, "User": null }),
+        ]
+    );
+    assert_eq!(
+        db.into_transaction_log(),
+        vec![Transaction::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT "orders"."id" AS "$parent.id", "User"."id" AS "id", "User"."last_name" AS "last_name" FROM "orders" INNER JOIN "users" AS "User" ON "User"."id" = "orders"."user_id" WHERE "orders"."id" IN ($1, $2)"#,
+            [10i64.into(), 11i64.into()]
+        )]
+    );
+}
+
+#[tokio::test]
+async fn can_expand_nested_relations() {
+    let db = MockDatabase::new(DbBackend::Postgres)
+        .append_query_results([vec![row([
+            ("id", 10.into()),
+            ("category", "Books".into()),
+            ("user_id", 1.into()),
+        ])]])
+        .append_query_results([vec![row([
+            ("$parent.id", 10.into()),
+            ("id", 1.into()),
+            ("last_name", "Doe".into()),
+        ])]])
+        .into_connection();
+
+    let rows = expand_users(&db, "Orders($select=Category;$expand=User($select=LastName))").await;
+
+    assert_eq!(
+        rows[0]["Orders"],
+        json!([{ "id": 10, "category": "Books", "User": { "id": 1, "last_name": "Doe" } }])
+    );
+    assert_eq!(db.into_transaction_log().len(), 2);
+}
+
+#[tokio::test]
+async fn can_bind_the_keys_of_a_to_many_relation_as_their_column_types() {
+    let id = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+    let other = "b1c8e6ee-6f0e-4b5b-8f4a-0c6a3e3f1a2d";
+    let db = MockDatabase::new(DbBackend::Postgres)
+        .append_query_results([vec![row([
+            ("document_id", Uuid::parse_str(id).unwrap().into()),
+            ("created_at", "2023-05-01T10:00:00+00:00".into()),
+        ])]])
+        .into_connection();
+
+    // the parents with the same key are queried once
+    let documents = vec![json!({ "id": id }), json!({ "id": other }), json!({ "id": id })];
+    let expand = parse_expand("Revisions($select=CreatedAt)").unwrap();
+    let entities = EntityList::new()
+        .with_entity::<document_model::Entity>()
+        .with_entity::<revision_model::Entity>();
+    let rows = expand_json::<document_model::Entity, _>(documents, &expand, &entities, &db)
+        .await
+        .expect("Failed to expand");
+
+    assert_eq!(
+        rows[0]["Revisions"],
+        json!([{ "document_id": id, "created_at": "2023-05-01T10:00:00+00:00" }])
+    );
+    assert_eq!(rows[1]["Revisions"], json!([]));
+    assert_eq!(rows[2]["Revisions"], rows[0]["Revisions"]);
+    assert_eq!(
+        db.into_transaction_log(),
+        vec![Transaction::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT "document_id", "created_at" FROM "revisions" WHERE "document_id" IN ($1, $2)"#,
+            [
+                Uuid::parse_str(id).unwrap().into(),
+                Uuid::parse_str(other).unwrap().into()
+            ]
+        )]
+    );
+}
+
+#[tokio::test]
+async fn can_bind_the_keys_of_a_to_one_relation_as_their_column_types() {
+    let id = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+    let db = MockDatabase::new(DbBackend::Postgres)
+        .append_query_results([vec![row([
+            ("$parent.document_id", Uuid::parse_str(id).unwrap().into()),
+            ("$parent.created_at", "2023-05-01T10:00:00Z".into()),
+            ("id", Uuid::parse_str(id).unwrap().into()),
+            ("title", "Notes".into()),
+        ])]])
+        .into_connection();
+
+    let revisions = vec![json!({ "document_id": id, "created_at": "2023-05-01T10:00:00Z", "size": "10.50" })];
+    let expand = parse_expand("Document").unwrap();
+    let entities = EntityList::new()
+        .with_entity::<document_model::Entity>()
+        .with_entity::<revision_model::Entity>();
+    let rows = expand_json::<revision_model::Entity, _>(revisions, &expand, &entities, &db)
+        .await
+        .expect("Failed to expand");
+
+    assert_eq!(rows[0]["Document"], json!({ "id": id, "title": "Notes" }));
+    let created_at = DateTimeWithTimeZone::parse_from_rfc3339("2023-05-01T10:00:00Z").unwrap();
+    assert_eq!(
+        db.into_transaction_log(),
+        vec![Transaction::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT "revisions"."document_id" AS "$parent.document_id", "revisions"."created_at" AS "$parent.created_at", "Document"."id" AS "id", "Document"."title" AS "title" FROM "revisions" INNER JOIN "documents" AS "Document" ON "Document"."id" = "revisions"."document_id" WHERE ("revisions"."document_id", "revisions"."created_at") IN (($1, $2))"#,
+            [Uuid::parse_str(id).unwrap().into(), created_at.into()]
+        )]
+    );
+}
+
+#[tokio::test]
+async fn can_expand_references_and_wildcards() {
+    let db = MockDatabase::new(DbBackend::Postgres)
+        .append_query_results([vec![
+            row([("id", 10.into()), ("user_id", 1.into())]),
+            row([("id", 11.into()), ("user_id", 2.into())]),
+        ]])
+        .into_connection();
+
+    let rows = expand_users(&db, "*/$ref").await;
+
+    assert_eq!(rows[0]["Orders"], json!([{ "@odata.id": "orders(10)" }]));
+    assert_eq!(rows[1]["Orders"], json!([{ "@odata.id": "orders(11)" }]));
+    assert_eq!(
+        db.into_transaction_log(),
+        vec![Transaction::from_sql_and_values(
+            DbBackend::Postgres,
+            r#"SELECT "id", "user_id" FROM "orders" WHERE "user_id" IN ($1, $2, $3)"#,
+            [1i64.into(), 2i64.into(), 3i64.into()]
+        )]
+    );
+}
+
+#[tokio::test]
+async fn can_leave_out_unknown_relations() {
+    let db = MockDatabase::new(DbBackend::Postgres).into_connection();
+
+    let expand = parse_expand("Orders,Trips").unwrap();
+    let rows = expand_json::<test_model::Entity, _>(users(), &expand, &EntityList::new(), &db)
+        .await
+        .expect("Failed to expand");
+
+    assert_eq!(rows, users());
+    assert!(db.into_transaction_log().is_empty());
+}
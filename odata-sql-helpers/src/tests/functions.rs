@@ -0,0 +1,139 @@
+use super::{mysql, pg, query_error_for_backend, sqlite};
+use odata_model::error::ODataError;
+use sea_orm::DbBackend;
+
+#[test]
+fn can_generate_contains_startswith_and_endswith() {
+    let url = "users?$filter=contains(first_name,'oh') and startswith(last_name,'D') and not endswith(first_name,'n')";
+    assert_eq!(
+        pg(url),
+        r#""first_name" LIKE '%oh%' AND "last_name" LIKE 'D%' AND (NOT ("first_name" LIKE '%n'))"#
+    );
+    assert_eq!(
+        mysql(url),
+        r#"`first_name` LIKE '%oh%' AND `last_name` LIKE 'D%' AND (NOT (`first_name` LIKE '%n'))"#
+    );
+}
+
+#[test]
+fn can_escape_like_wildcards() {
+    let url = "users?$filter=contains(first_name,'100%_')";
+    assert_eq!(pg(url), r#""first_name" LIKE E'%100\\%\\_%' ESCAPE E'\\'"#);
+}
+
+#[test]
+fn can_generate_contains_with_a_column() {
+    let url = "users?$filter=contains(first_name,last_name)";
+    assert_eq!(pg(url), r#""first_name" LIKE (('%' || "last_name") || '%')"#);
+    assert_eq!(mysql(url), r#"`first_name` LIKE CONCAT('%', `last_name`, '%')"#);
+}
+
+#[test]
+fn can_generate_length() {
+    let url = "users?$filter=length(first_name) gt 5";
+    assert_eq!(pg(url), r#"CHAR_LENGTH("first_name") > 5"#);
+    assert_eq!(mysql(url), r#"CHAR_LENGTH(`first_name`) > 5"#);
+    assert_eq!(sqlite(url), r#"LENGTH("first_name") > 5"#);
+}
+
+#[test]
+fn can_generate_indexof() {
+    let url = "users?$filter=indexof(first_name,'oh') eq 1";
+    assert_eq!(pg(url), r#"STRPOS("first_name", 'oh') - 1 = 1"#);
+    assert_eq!(mysql(url), r#"LOCATE('oh', `first_name`) - 1 = 1"#);
+    assert_eq!(sqlite(url), r#"INSTR("first_name", 'oh') - 1 = 1"#);
+}
+
+#[test]
+fn can_generate_substring() {
+    let url = "users?$filter=substring(first_name,1) eq 'ohn' and substring(last_name,id,2) eq 'oe'";
+    assert_eq!(
+        pg(url),
+        r#"SUBSTR("first_name", 2) = 'ohn' AND SUBSTR("last_name", "id" + 1, 2) = 'oe'"#
+    );
+    assert_eq!(
+        pg("users?$filter=substring(first_name,2147483647) eq ''"),
+        r#"SUBSTR("first_name", 2147483648) = ''"#
+    );
+}
+
+#[test]
+fn can_generate_case_and_trim_functions() {
+    let url = "users?$filter=tolower(first_name) eq 'john' and toupper(trim(last_name)) eq 'DOE'";
+    assert_eq!(
+        pg(url),
+        r#"LOWER("first_name") = 'john' AND UPPER(TRIM("last_name")) = 'DOE'"#
+    );
+}
+
+#[test]
+fn can_generate_concat() {
+    let url = "users?$filter=concat(concat(first_name,' '),last_name) eq 'John Doe'";
+    assert_eq!(pg(url), r#"(("first_name" || ' ') || "last_name") = 'John Doe'"#);
+    assert_eq!(
+        mysql(url),
+        r#"CONCAT(CONCAT(`first_name`, ' '), `last_name`) = 'John Doe'"#
+    );
+    assert_eq!(sqlite(url), r#"(("first_name" || ' ') || "last_name") = 'John Doe'"#);
+}
+
+#[test]
+fn can_generate_matches_pattern() {
+    let url = "users?$filter=matchesPattern(first_name,'^J.*n$')";
+    assert_eq!(pg(url), r#""first_name" ~ '^J.*n$'"#);
+    assert_eq!(mysql(url), r#"`first_name` REGEXP '^J.*n$'"#);
+
+    // SQLite has no REGEXP function unless the application defines one
+    let error = query_error_for_backend(url, DbBackend::Sqlite);
+    assert_eq!(
+        error.to_string(),
+        "unsupported OData query; 'matchesPattern' on Sqlite has no SQL translation"
+    );
+}
+
+#[test]
+fn can_generate_casts_to_primitive_types() {
+    let url = "users?$filter=cast(id,Edm.String) eq '1' and cast(first_name,Edm.Int32) gt 2";
+    assert_eq!(
+        pg(url),
+        r#"CAST("id" AS TEXT) = '1' AND CAST("first_name" AS INTEGER) > 2"#
+    );
+    assert_eq!(
+        mysql(url),
+        r#"CAST(`id` AS CHAR) = '1' AND CAST(`first_name` AS SIGNED) > 2"#
+    );
+    assert_eq!(
+        sqlite(url),
+        r#"CAST("id" AS TEXT) = '1' AND CAST("first_name" AS INTEGER) > 2"#
+    );
+}
+
+#[test]
+fn can_reject_type_checks_and_casts_to_structured_types() {
+    let rejected = [
+        ("users?$filter=isof(Ns.Admin) and id eq 1", "'isof(Ns.Admin)'"),
+        (
+            "users?$filter=id eq 1 or isof(first_name,Edm.String)",
+            "'isof(first_name,Edm.String)'",
+        ),
+        ("users?$filter=cast(Ns.Admin) ne null", "'cast(Ns.Admin)'"),
+        ("users?$filter=cast(doc,Ns.Address) eq null", "'cast(doc,Ns.Address)'"),
+    ];
+    for (url, expression) in rejected {
+        let error = query_error_for_backend(url, DbBackend::Postgres);
+        assert!(matches!(error, ODataError::Unsupported(_)), "{url}");
+        assert_eq!(
+            error.to_string(),
+            format!("unsupported OData query; {expression} has no SQL translation")
+        );
+    }
+
+    let error = query_error_for_backend(
+        "users?$filter=cast(first_name,Edm.Date) eq 2024-01-01",
+        DbBackend::Sqlite,
+    );
+    assert_eq!(
+        error.to_string(),
+        "unsupported OData query; 'cast(first_name,Edm.Date)' on Sqlite has no SQL translation"
+    );
+}
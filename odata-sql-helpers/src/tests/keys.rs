@@ -0,0 +1,54 @@
+use super::{order_item_model, order_model};
+use crate::primary_key_condition;
+use odata_model::error::ODataError;
+use odata_model::resource::ODataResource;
+use sea_orm::{DbBackend, EntityTrait, QueryFilter, QueryTrait};
+
+fn build_key_query<E: EntityTrait>(url: &str) -> Result<String, ODataError> {
+    let resource = ODataResource::try_from(url).expect("Failed to parse ODataResource");
+    let key = resource.entity.key.expect("key");
+    let condition = primary_key_condition::<E>(&key)?;
+    Ok(E::find().filter(condition).build(DbBackend::Postgres).to_string())
+}
+
+#[test]
+fn can_select_an_entity_by_its_key() {
+    let query = build_key_query::<order_model::Entity>("orders(42)").expect("Failed to build the key condition");
+    assert!(query.ends_with(r#"FROM "orders" WHERE "id" = 42"#), "{query}");
+
+    let query = build_key_query::<order_model::Entity>("orders(Id=42)").expect("Failed to build the key condition");
+    assert!(query.ends_with(r#"FROM "orders" WHERE "id" = 42"#), "{query}");
+}
+
+#[test]
+fn can_select_an_entity_by_a_composite_key() {
+    let query = build_key_query::<order_item_model::Entity>("order_items(ItemNo=2,OrderId=3000000000)")
+        .expect("Failed to build the key condition");
+    assert_eq!(
+        r#"SELECT "order_items"."order_id", "order_items"."item_no", "order_items"."product" FROM "order_items" WHERE "order_id" = 3000000000 AND "item_no" = 2"#,
+        query
+    );
+}
+
+#[test]
+fn can_detect_keys_that_dont_match_the_primary_key() {
+    for url in [
+        "order_items(1)",
+        "order_items(OrderId=1)",
+        "order_items(OrderId=1,ItemNo='2')",
+        "order_items(OrderId=1,ItemNo=2,Product='x')",
+    ] {
+        assert!(
+            matches!(
+                build_key_query::<order_item_model::Entity>(url),
+                Err(ODataError::InvalidKey(_))
+            ),
+            "{url}"
+        );
+    }
+
+    assert!(matches!(
+        build_key_query::<order_model::Entity>("orders('42')"),
+        Err(ODataError::InvalidKey(_))
+    ));
+}
@@ -0,0 +1,162 @@
+use super::{build_query_for_backend, pg, query_error_for_backend, where_clause};
+use crate::get_relations;
+use crate::tests::test_model;
+use crate::WithODataExt;
+use odata_model::error::ODataError;
+use odata_model::resource::ODataResource;
+use sea_orm::{DbBackend, EntityTrait};
+
+#[test]
+fn can_get_relations_from_entity() {
+    let relations = get_relations::<test_model::Entity>();
+    assert_eq!(vec!["Orders"], relations.names());
+    assert!(relations.get("Orders").is_some());
+    assert!(relations.get("orders").is_some());
+    assert!(relations.get("Trips").is_none());
+}
+
+#[test]
+fn can_generate_exists_for_any() {
+    assert_eq!(
+        pg("users?$filter=Orders/any(o: o/Price gt 100)"),
+        r#"EXISTS(SELECT 1 FROM "orders" AS "orders_1" WHERE "orders_1"."user_id" = "users"."id" AND "orders_1"."price" > 100)"#
+    );
+}
+
+#[test]
+fn can_generate_exists_for_any_without_predicate() {
+    assert_eq!(
+        pg("users?$filter=not Orders/any()"),
+        r#"NOT (EXISTS(SELECT 1 FROM "orders" AS "orders_1" WHERE "orders_1"."user_id" = "users"."id"))"#
+    );
+}
+
+#[test]
+fn can_generate_not_exists_for_all() {
+    assert_eq!(
+        pg("users?$filter=Orders/all(o: o/Category eq 'Books' or o/Quantity lt 2)"),
+        r#"NOT EXISTS(SELECT 1 FROM "orders" AS "orders_1" WHERE "orders_1"."user_id" = "users"."id" AND (CASE WHEN ("orders_1"."category" = 'Books' OR "orders_1"."quantity" < 2) THEN FALSE ELSE TRUE END))"#
+    );
+}
+
+#[test]
+fn can_refer_to_the_outer_resource_from_a_lambda() {
+    assert_eq!(
+        pg("users?$filter=FirstName eq 'John' and Orders/any(o: contains(o/Category, $it/LastName))"),
+        r#""first_name" = 'John' AND EXISTS(SELECT 1 FROM "orders" AS "orders_1" WHERE "orders_1"."user_id" = "users"."id" AND "orders_1"."category" LIKE (('%' || "users"."last_name") || '%'))"#
+    );
+}
+
+#[test]
+fn can_generate_nested_lambdas() {
+    assert_eq!(
+        pg("users?$filter=Orders/any(o: o/Category eq 'Books' and $it/Orders/all(p: p/Price le o/Price))"),
+        r#"EXISTS(SELECT 1 FROM "orders" AS "orders_1" WHERE "orders_1"."user_id" = "users"."id" AND ("orders_1"."category" = 'Books' AND NOT EXISTS(SELECT 1 FROM "orders" AS "orders_2" WHERE "orders_2"."user_id" = "users"."id" AND (CASE WHEN ("orders_2"."price" <= "orders_1"."price") THEN FALSE ELSE TRUE END))))"#
+    );
+}
+
+#[test]
+fn can_generate_lambdas_for_every_backend() {
+    let url = "users?$filter=Orders/any(o: year(o/CreatedAt) eq 2024)";
+    assert_eq!(
+        where_clause(&build_query_for_backend(url, DbBackend::MySql)),
+        r#"EXISTS(SELECT 1 FROM `orders` AS `orders_1` WHERE `orders_1`.`user_id` = `users`.`id` AND YEAR(`orders_1`.`created_at`) = 2024)"#
+    );
+    assert_eq!(
+        where_clause(&build_query_for_backend(url, DbBackend::Sqlite)),
+        r#"EXISTS(SELECT 1 FROM "orders" AS "orders_1" WHERE "orders_1"."user_id" = "users"."id" AND (CAST(strftime('%Y', "orders_1"."created_at") AS INTEGER)) = 2024)"#
+    );
+}
+
+#[test]
+fn can_generate_lambdas_on_a_to_one_relation() {
+    assert_eq!(
+        where_clause(&build_query_for_backend(
+            "orders?$filter=User/any(u: u/LastName eq 'Doe')",
+            DbBackend::Postgres
+        )),
+        r#"EXISTS(SELECT 1 FROM "users" AS "user_1" WHERE "user_1"."id" = "orders"."user_id" AND "user_1"."last_name" = 'Doe')"#
+    );
+}
+
+#[test]
+fn can_reject_lambdas_on_unknown_relations() {
+    let error = query_error_for_backend(
+        "users?$filter=Trips/any(t: t/Budget gt 1000) and FirstName eq 'John'",
+        DbBackend::Postgres,
+    );
+    assert_eq!(
+        error.to_string(),
+        "invalid OData query; 'Trips' is not a navigation property"
+    );
+}
+
+#[test]
+fn can_alias_related_tables_apart_from_range_variables() {
+    assert_eq!(
+        pg("users?$filter=Orders/any(users: users/Price gt 100 and $it/FirstName eq 'John')"),
+        r#"EXISTS(SELECT 1 FROM "orders" AS "orders_1" WHERE "orders_1"."user_id" = "users"."id" AND ("orders_1"."price" > 100 AND "users"."first_name" = 'John'))"#
+    );
+}
+
+#[test]
+fn can_reject_unknown_properties_of_related_entities() {
+    let error = query_error_for_backend("users?$filter=Orders/any(o: o/Budget gt 1000)", DbBackend::Postgres);
+    assert_eq!(error.to_string(), "invalid OData query; 'o/Budget' has no column");
+}
+
+#[test]
+fn can_count_rows_where_the_predicate_is_null_as_counterexamples_of_all() {
+    // an order without a category makes the comparison NULL, which must not pass for TRUE
+    assert_eq!(
+        pg("users?$filter=Orders/all(o: o/Category eq 'Books')"),
+        r#"NOT EXISTS(SELECT 1 FROM "orders" AS "orders_1" WHERE "orders_1"."user_id" = "users"."id" AND (CASE WHEN ("orders_1"."category" = 'Books') THEN FALSE ELSE TRUE END))"#
+    );
+}
+
+#[test]
+fn can_generate_lambdas_from_range_variables() {
+    assert_eq!(
+        pg("users?$filter=Orders/any(o: o/Items/any(i: i/Product eq 'Pen') and o/Items/all(i: i/ItemNo lt 10))"),
+        r#"EXISTS(SELECT 1 FROM "orders" AS "orders_1" WHERE "orders_1"."user_id" = "users"."id" AND (EXISTS(SELECT 1 FROM "order_items" AS "items_2" WHERE "items_2"."order_id" = "orders_1"."id" AND "items_2"."product" = 'Pen') AND NOT EXISTS(SELECT 1 FROM "order_items" AS "items_2" WHERE "items_2"."order_id" = "orders_1"."id" AND (CASE WHEN ("items_2"."item_no" < 10) THEN FALSE ELSE TRUE END))))"#
+    );
+}
+
+#[test]
+fn can_generate_lambdas_over_collections_of_primitive_values() {
+    let url = "users?$filter=Doc/any(s: endswith(s,'contoso.com'))";
+    assert_eq!(
+        pg(url),
+        r#"EXISTS(SELECT 1 FROM jsonb_array_elements_text(CAST("users"."doc" AS jsonb)) AS "doc_1" WHERE "doc_1"."value" LIKE '%contoso.com')"#
+    );
+    assert_eq!(
+        where_clause(&build_query_for_backend(url, DbBackend::Sqlite)),
+        r#"EXISTS(SELECT 1 FROM json_each("users"."doc") AS "doc_1" WHERE "doc_1"."value" LIKE '%contoso.com')"#
+    );
+    assert!(matches!(
+        query_error_for_backend(url, DbBackend::MySql),
+        ODataError::Unsupported(_)
+    ));
+}
+
+#[test]
+fn can_reject_lambdas_over_single_values_and_bare_range_variables() {
+    let error = query_error_for_backend("users?$filter=LastName/any(s: s eq 'Doe')", DbBackend::Postgres);
+    assert_eq!(error.to_string(), "invalid OData query; 'LastName' is not a collection");
+
+    let error = query_error_for_backend("users?$filter=Doc/any(s: s/City eq 'Oslo')", DbBackend::Postgres);
+    assert!(matches!(error, ODataError::InvalidQuery(_)));
+
+    let error = query_error_for_backend("users?$filter=Orders/any(o: o eq null)", DbBackend::Postgres);
+    assert!(matches!(error, ODataError::Unsupported(_)));
+}
+
+#[test]
+fn can_reject_lambdas_on_entities_that_arent_in_the_entity_list() {
+    let resource =
+        ODataResource::try_from("users?$filter=Orders/any(o: o/Price gt 100)").expect("Failed to parse ODataResource");
+    let error = test_model::Entity::find()
+        .with_odata_resource(&resource, DbBackend::Postgres)
+        .expect_err("Expected the lambda to be rejected");
+    assert!(matches!(error, ODataError::Unsupported(_)));
+}
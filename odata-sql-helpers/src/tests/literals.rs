@@ -0,0 +1,78 @@
+use super::{build_query_for_backend, query_error_for_backend, where_clause};
+use sea_orm::DbBackend;
+
+#[test]
+fn can_compare_with_date_and_time_literals() {
+    let query = build_query_for_backend(
+        "orders?$filter=created_at ge 2024-01-15T10:30:00Z and date(created_at) lt 2024-02-01 and time(created_at) gt 08:00:00",
+        DbBackend::MySql,
+    );
+    assert_eq!(
+        "`created_at` >= '2024-01-15 10:30:00 +00:00' AND DATE(`created_at`) < '2024-02-01' AND TIME(`created_at`) > '08:00:00'",
+        where_clause(&query)
+    );
+}
+
+#[test]
+fn can_compare_with_numeric_literals() {
+    let query = build_query_for_backend(
+        "orders?$filter=quantity lt 3000000000 and price le 1.5e3 and price lt INF",
+        DbBackend::Postgres,
+    );
+    assert_eq!(
+        r#""quantity" < 3000000000 AND "price" <= 1500 AND "price" < CAST('Infinity' AS DOUBLE PRECISION)"#,
+        where_clause(&query)
+    );
+
+    let query = build_query_for_backend("orders?$filter=price gt -INF", DbBackend::Sqlite);
+    assert_eq!(r#""price" > (-9e999)"#, where_clause(&query));
+}
+
+#[test]
+fn can_compare_with_guid_binary_enum_and_json_literals() {
+    let query = build_query_for_backend(
+        "users?$filter=first_name eq 01234567-89ab-cdef-0123-456789abcdef or doc eq binary'T0RhdGE' or last_name eq Ns.Name'Doe' or doc eq {\"a\":1}",
+        DbBackend::Sqlite,
+    );
+    assert_eq!(
+        r#""first_name" = '01234567-89ab-cdef-0123-456789abcdef' OR "doc" = x'4F44617461' OR "last_name" = 'Doe' OR "doc" = '{"a":1}'"#,
+        where_clause(&query)
+    );
+}
+
+#[test]
+fn can_compare_with_duration_literals() {
+    let query = build_query_for_backend(
+        "orders?$filter=created_at sub created_at lt duration'P1DT1H'",
+        DbBackend::Postgres,
+    );
+    assert_eq!(
+        r#""created_at" - "created_at" < CAST('90000000 milliseconds' AS INTERVAL)"#,
+        where_clause(&query)
+    );
+}
+
+#[test]
+fn can_reject_literals_without_a_value_in_the_backend() {
+    let order_error = |url: &str, backend: DbBackend| query_error_for_backend(url, backend).to_string();
+
+    assert_eq!(
+        order_error("orders?$filter=price ne NaN or quantity gt 1", DbBackend::MySql),
+        "unsupported OData query; NaN on MySql has no SQL translation"
+    );
+    assert_eq!(
+        order_error("orders?$filter=not (price eq NaN)", DbBackend::Sqlite),
+        "unsupported OData query; NaN on Sqlite has no SQL translation"
+    );
+    assert_eq!(
+        order_error("orders?$filter=quantity lt duration'PT1.5S'", DbBackend::MySql),
+        "unsupported OData query; duration'PT1.5S' on MySql has no SQL translation"
+    );
+    assert_eq!(
+        order_error(
+            "orders?$filter=created_at sub created_at lt duration'P1D'",
+            DbBackend::Sqlite
+        ),
+        "unsupported OData query; duration'P1D' on Sqlite has no SQL translation"
+    );
+}
@@ -1,8 +1,27 @@
+use crate::expand::EntityList;
 use crate::tests::test_model::Model;
 use crate::{get_column_names, WithODataExt};
+use odata_model::error::{ODataError, ODataResult};
+use odata_model::expression::{Expression, MemberPath};
 use odata_model::resource::{ODataResource, OrderBy, OrderByDirection};
 use sea_orm::{DbBackend, EntityTrait, ModelTrait, QueryTrait};
 
+mod apply;
+mod compute;
+mod count;
+pub mod customer_model;
+mod date_functions;
+pub mod document_model;
+mod expand;
+mod functions;
+mod keys;
+mod lambdas;
+mod literals;
+pub mod order_item_model;
+pub mod order_model;
+mod paths;
+pub mod revision_model;
+mod select;
 pub mod test_model;
 
 #[test]
@@ -12,13 +31,71 @@ fn can_get_column_names_from_entity() {
     assert_eq!(vec!["id"], p_keys.keys());
 }
 
+/// The entities that navigation paths and lambdas of the test queries lead to
+fn entities() -> EntityList {
+    EntityList::new()
+        .with_entity::<test_model::Entity>()
+        .with_entity::<order_model::Entity>()
+        .with_entity::<order_item_model::Entity>()
+        .with_entity::<customer_model::Entity>()
+}
+
 fn build_query_with_filter(resource: &ODataResource) -> String {
     test_model::Entity::find()
-        .with_odata_resource(resource)
+        .with_odata_resource_in(resource, DbBackend::Postgres, &entities())
+        .expect("Failed to translate ODataResource")
         .build(DbBackend::Postgres)
         .to_string()
 }
 
+/// Translate the resource of a URL into a query for the users, orders or customers, by the entity set of the URL
+fn translate_for_backend(url: &str, backend: DbBackend) -> ODataResult<String> {
+    let resource = ODataResource::try_from(url).expect("Failed to parse ODataResource");
+    let entities = entities();
+    let query = match url.split(['?', '/', '(']).next() {
+        Some("orders") => order_model::Entity::find()
+            .with_odata_resource_in(&resource, backend, &entities)?
+            .build(backend),
+        Some("customers") => customer_model::Entity::find()
+            .with_odata_resource_in(&resource, backend, &entities)?
+            .build(backend),
+        _ => test_model::Entity::find()
+            .with_odata_resource_in(&resource, backend, &entities)?
+            .build(backend),
+    };
+
+    Ok(query.to_string())
+}
+
+fn build_query_for_backend(url: &str, backend: DbBackend) -> String {
+    translate_for_backend(url, backend).expect("Failed to translate ODataResource")
+}
+
+/// The error of a resource that can't be translated into a query
+fn query_error_for_backend(url: &str, backend: DbBackend) -> ODataError {
+    translate_for_backend(url, backend).expect_err("Expected the resource to be rejected")
+}
+
+/// The condition of a query, i.e. everything after `WHERE`
+fn where_clause(query: &str) -> &str {
+    query
+        .split_once(" WHERE ")
+        .map(|(_, condition)| condition)
+        .unwrap_or_default()
+}
+
+fn pg(url: &str) -> String {
+    where_clause(&build_query_for_backend(url, DbBackend::Postgres)).to_string()
+}
+
+fn mysql(url: &str) -> String {
+    where_clause(&build_query_for_backend(url, DbBackend::MySql)).to_string()
+}
+
+fn sqlite(url: &str) -> String {
+    where_clause(&build_query_for_backend(url, DbBackend::Sqlite)).to_string()
+}
+
 #[test]
 fn can_generate_a_search_query() {
     let resource = ODataResource {
@@ -38,12 +115,12 @@ fn can_generate_order_by_query() {
     let resource = ODataResource {
         order_by: vec![
             OrderBy {
-                field: "first_name".to_string(),
+                expression: Expression::Member(MemberPath(vec!["first_name".to_string()])),
                 direction: OrderByDirection::Desc,
             },
             OrderBy {
-                field: "last_name".to_string(),
-                ..Default::default()
+                expression: Expression::Member(MemberPath(vec!["last_name".to_string()])),
+                direction: OrderByDirection::default(),
             },
         ],
         ..Default::default()
@@ -125,7 +202,7 @@ fn can_withstand_sql_injection() {
     // todo: write proper SQL-injection test
 
     let resource =
-        ODataResource::try_from("users?$filter=first_name eq 'John\"; INSERT INTO \"users\" values ('illegal');'")
+        ODataResource::try_from("users?$filter=first_name eq 'John\"; INSERT INTO \"users\" values (''illegal'');'")
             .expect("Failed to parse ODataResource");
 
     let query = build_query_with_filter(&resource);
@@ -145,3 +222,104 @@ fn can_generate_a_query_with_top_and_skip() {
         query
     );
 }
+
+#[test]
+fn can_generate_a_query_with_nested_groups_and_not() {
+    let resource = ODataResource::try_from(
+        "users?$filter=not (first_name eq 'John' or (first_name eq 'Bill'))and last_name ne null",
+    )
+    .expect("Failed to parse ODataResource");
+
+    let query = build_query_with_filter(&resource);
+    assert_eq!(
+        r#"SELECT "users"."id", "users"."first_name", "users"."last_name", "users"."doc" FROM "users" WHERE (NOT ("first_name" = 'John' OR "first_name" = 'Bill')) AND "last_name" IS NOT NULL"#,
+        query
+    );
+}
+
+#[test]
+fn can_generate_an_in_query() {
+    let resource = ODataResource::try_from("users?$filter=id in (1, 2, 3)").expect("Failed to parse ODataResource");
+
+    let query = build_query_with_filter(&resource);
+    assert_eq!(
+        r#"SELECT "users"."id", "users"."first_name", "users"."last_name", "users"."doc" FROM "users" WHERE "id" IN (1, 2, 3)"#,
+        query
+    );
+}
+
+#[test]
+fn can_generate_a_query_with_arithmetic() {
+    let resource = ODataResource::try_from("users?$filter=id mul 2 add 1 gt 10 and id mod 3 eq -id divby 2")
+        .expect("Failed to parse ODataResource");
+
+    let query = build_query_with_filter(&resource);
+    assert_eq!(
+        r#"SELECT "users"."id", "users"."first_name", "users"."last_name", "users"."doc" FROM "users" WHERE ("id" * 2) + 1 > 10 AND "id" % 3 = ((-("id")) * (1.0)) / 2"#,
+        query
+    );
+}
+
+#[test]
+fn can_divide_integers_without_a_remainder() {
+    let url = "orders?$filter=quantity div 2 eq 1 and price div 2 lt 10 and year(created_at) div 10 eq 202";
+
+    let query = build_query_for_backend(url, DbBackend::MySql);
+    assert!(
+        query.ends_with("WHERE (`quantity` DIV 2) = 1 AND `price` / 2 < 10 AND (YEAR(`created_at`) DIV 10) = 202"),
+        "{query}"
+    );
+
+    let query = build_query_for_backend(url, DbBackend::Postgres);
+    assert!(
+        query.ends_with(
+            r#"WHERE "quantity" / 2 = 1 AND "price" / 2 < 10 AND CAST((EXTRACT(YEAR FROM "created_at")) AS BIGINT) / 10 = 202"#
+        ),
+        "{query}"
+    );
+}
+
+#[test]
+fn can_generate_a_query_with_arithmetic_grouping() {
+    let resource =
+        ODataResource::try_from("users?$filter=(id add 1) mul 2 le 10").expect("Failed to parse ODataResource");
+
+    let query = build_query_with_filter(&resource);
+    assert_eq!(
+        r#"SELECT "users"."id", "users"."first_name", "users"."last_name", "users"."doc" FROM "users" WHERE ("id" + 1) * 2 <= 10"#,
+        query
+    );
+}
+
+#[test]
+fn can_generate_order_by_an_arithmetic_expression() {
+    let resource =
+        ODataResource::try_from("users?$orderby=id sub 1 desc,last_name").expect("Failed to parse ODataResource");
+
+    let query = build_query_with_filter(&resource);
+    assert_eq!(
+        r#"SELECT "users"."id", "users"."first_name", "users"."last_name", "users"."doc" FROM "users" WHERE TRUE ORDER BY "id" - 1 DESC, "last_name" ASC"#,
+        query
+    );
+}
+
+#[test]
+fn can_reject_untranslatable_operands_anywhere_in_a_filter() {
+    let error = query_error_for_backend(
+        "users?$filter=first_name eq 'John' or doc has Sales.Pattern'Yellow'",
+        DbBackend::Postgres,
+    );
+    assert_eq!(
+        error.to_string(),
+        "unsupported OData query; 'has' has no SQL translation"
+    );
+
+    let error = query_error_for_backend(
+        "users?$filter=not (age gt 30) and last_name eq 'Doe'",
+        DbBackend::Postgres,
+    );
+    assert_eq!(error.to_string(), "invalid OData query; 'age' has no column");
+
+    let error = query_error_for_backend("users?$orderby=age desc", DbBackend::Postgres);
+    assert_eq!(error.to_string(), "invalid OData query; 'age' has no column");
+}
@@ -0,0 +1,96 @@
+use super::{build_query_for_backend, entities, query_error_for_backend};
+use crate::WithODataExt;
+use odata_model::error::ODataError;
+use odata_model::resource::ODataResource;
+use sea_orm::{DbBackend, EntityTrait, QueryTrait};
+
+#[test]
+fn can_map_complex_paths_to_columns() {
+    assert_eq!(
+        build_query_for_backend(
+            "customers?$filter=Address/City eq 'Berlin' and $it/Address/Street ne ''&$orderby=Address/Street",
+            DbBackend::Postgres
+        ),
+        r#"SELECT "customers"."id", "customers"."name", "customers"."address_street", "customers"."address_city" FROM "customers" WHERE "address_city" = 'Berlin' AND "address_street" <> '' ORDER BY "address_street" ASC"#
+    );
+}
+
+#[test]
+fn can_reject_unknown_complex_paths() {
+    let error = query_error_for_backend(
+        "customers?$filter=Address/Country eq 'DE' and Name eq 'Contoso'",
+        DbBackend::Postgres,
+    );
+    assert_eq!(
+        error.to_string(),
+        "invalid OData query; 'Address/Country' has no column"
+    );
+}
+
+#[test]
+fn can_reject_unknown_properties_of_joined_entities() {
+    let error = query_error_for_backend("orders?$orderby=User/Budget desc", DbBackend::Postgres);
+    assert_eq!(error.to_string(), "invalid OData query; 'User/Budget' has no column");
+}
+
+#[test]
+fn can_join_to_one_navigation_paths() {
+    assert_eq!(
+        build_query_for_backend(
+            "orders?$filter=User/FirstName eq 'John' and Quantity gt 1&$orderby=User/LastName desc,Price",
+            DbBackend::Postgres
+        ),
+        r#"SELECT "orders"."id", "orders"."user_id", "orders"."category", "orders"."price", "orders"."quantity", "orders"."created_at" FROM "orders" LEFT JOIN "users" AS "user" ON "user"."id" = "orders"."user_id" WHERE "user"."first_name" = 'John' AND "orders"."quantity" > 1 ORDER BY "user"."last_name" DESC, "orders"."price" ASC"#
+    );
+}
+
+#[test]
+fn can_join_a_navigation_property_once() {
+    assert_eq!(
+        build_query_for_backend(
+            "orders?$filter=User/FirstName eq 'John' or startswith($it/User/LastName,'D')&$search=books",
+            DbBackend::Sqlite
+        ),
+        r#"SELECT "orders"."id", "orders"."user_id", "orders"."category", "orders"."price", "orders"."quantity", "orders"."created_at" FROM "orders" LEFT JOIN "users" AS "user" ON "user"."id" = "orders"."user_id" WHERE (LOWER("orders"."id") LIKE '%books%' OR LOWER("orders"."user_id") LIKE '%books%' OR LOWER("orders"."category") LIKE '%books%' OR LOWER("orders"."price") LIKE '%books%' OR LOWER("orders"."quantity") LIKE '%books%' OR LOWER("orders"."created_at") LIKE '%books%') AND ("user"."first_name" = 'John' OR "user"."last_name" LIKE 'D%')"#
+    );
+}
+
+#[test]
+fn can_count_through_a_navigation_path() {
+    let resource = ODataResource::try_from("orders/$count?$filter=User/LastName eq 'Doe'").expect("Failed to parse");
+    let query = super::order_model::Entity::find()
+        .with_odata_count_in(&resource, DbBackend::Postgres, &entities())
+        .expect("Failed to translate ODataResource")
+        .build(DbBackend::Postgres)
+        .to_string();
+    assert_eq!(
+        query,
+        r#"SELECT COUNT(*) AS "count" FROM "orders" LEFT JOIN "users" AS "user" ON "user"."id" = "orders"."user_id" WHERE "user"."last_name" = 'Doe'"#
+    );
+}
+
+#[test]
+fn can_reject_paths_through_collections() {
+    // a collection can only be navigated with any or all
+    let error = query_error_for_backend("users?$filter=Orders/Price gt 100", DbBackend::Postgres);
+    assert!(matches!(error, ODataError::InvalidQuery(_)));
+    assert_eq!(
+        error.to_string(),
+        "invalid OData query; 'Orders/Price' goes through the collection 'Orders', which can only be navigated with any or all"
+    );
+
+    // the relations of the related entity aren't known, so the collection can't be navigated from there
+    let error = query_error_for_backend("orders?$filter=User/Orders/any(o: o/Price gt 100)", DbBackend::Postgres);
+    assert!(matches!(error, ODataError::Unsupported(_)));
+}
+
+#[test]
+fn can_group_by_joined_navigation_paths() {
+    assert_eq!(
+        build_query_for_backend(
+            "orders?$apply=filter(User/FirstName eq 'John')/groupby((User/LastName,Category),aggregate(Price with sum as Total))&$orderby=Total desc",
+            DbBackend::Postgres
+        ),
+        r#"SELECT "user"."last_name", "orders"."category", SUM("orders"."price") AS "Total" FROM "orders" LEFT JOIN "users" AS "user" ON "user"."id" = "orders"."user_id" WHERE "user"."first_name" = 'John' GROUP BY "user"."last_name", "orders"."category" ORDER BY SUM("orders"."price") DESC"#
+    );
+}
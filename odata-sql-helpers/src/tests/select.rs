@@ -0,0 +1,44 @@
+use super::build_query_for_backend;
+use sea_orm::DbBackend;
+
+#[test]
+fn can_generate_a_select_with_the_requested_columns() {
+    let query = build_query_for_backend("users?$select=LastName,FirstName", DbBackend::Postgres);
+    assert_eq!(
+        r#"SELECT "users"."id", "users"."first_name", "users"."last_name" FROM "users" WHERE TRUE"#,
+        query
+    );
+}
+
+#[test]
+fn can_always_select_the_primary_key() {
+    let query = build_query_for_backend("users?$select=Doc&$filter=FirstName eq 'John'", DbBackend::MySql);
+    assert_eq!(
+        r#"SELECT `users`.`id`, `users`.`doc` FROM `users` WHERE `first_name` = 'John'"#,
+        query
+    );
+}
+
+#[test]
+fn can_select_all_columns_with_a_wildcard() {
+    let query = build_query_for_backend("users?$select=FirstName,*", DbBackend::Postgres);
+    assert_eq!(
+        r#"SELECT "users"."id", "users"."first_name", "users"."last_name", "users"."doc" FROM "users" WHERE TRUE"#,
+        query
+    );
+}
+
+#[test]
+fn can_select_the_column_of_a_nested_path() {
+    let query = build_query_for_backend(
+        "users?$select=Doc/Street,Doc($select=City),Unknown",
+        DbBackend::Postgres,
+    );
+    assert_eq!(r#"SELECT "users"."id", "users"."doc" FROM "users" WHERE TRUE"#, query);
+}
+
+#[test]
+fn can_select_only_the_keys_for_operations() {
+    let query = build_query_for_backend("orders?$select=Sales.*&$top=5", DbBackend::Sqlite);
+    assert_eq!(r#"SELECT "orders"."id" FROM "orders" WHERE TRUE LIMIT 5"#, query);
+}
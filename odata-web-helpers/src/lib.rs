@@ -6,11 +6,14 @@ use axum::{
     response::IntoResponse,
 };
 use http::{request::Parts, StatusCode};
-use odata_model::{model::ODataModel, resource::ODataResource};
+use odata_model::{binding::BoundResource, model::ODataModel, resource::ODataResource};
+use response::ODataErrorResponse;
 
 pub mod response;
 
-/// Extracts a [`ODataResource`] from the request.
+/// Extracts a [`ODataResource`] from the request, whose path starts with the resource or is below the root of the
+/// TripPin sample service, like [`ODataResource::try_from`]. Works with any state; a request that isn't a valid OData
+/// resource is rejected with a 400 response describing the problem.
 pub struct ExtractODataResource(pub ODataResource);
 
 #[async_trait]
@@ -18,15 +21,50 @@ impl<S> FromRequestParts<S> for ExtractODataResource
 where
     S: Send + Sync,
 {
-    type Rejection = (StatusCode, &'static str);
+    type Rejection = ODataErrorResponse;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        let uri = &parts.uri;
-        if let Ok(odata_resource) = ODataResource::try_from(uri) {
-            Ok(ExtractODataResource(odata_resource))
-        } else {
-            Err((StatusCode::BAD_REQUEST, "request is not a valid OData resource"))
-        }
+        let resource = ODataResource::try_from(&parts.uri)?;
+
+        Ok(ExtractODataResource(resource))
+    }
+}
+
+/// Extracts a [`ODataResource`] from the request, relative to the service root of the [`ODataModel`] of the state.
+/// Requests outside the service root are rejected like invalid resources.
+pub struct ExtractServiceODataResource(pub ODataResource);
+
+#[async_trait]
+impl<S> FromRequestParts<Arc<S>> for ExtractServiceODataResource
+where
+    S: WithODataModelExt + Send + Sync,
+{
+    type Rejection = ODataErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<S>) -> Result<Self, Self::Rejection> {
+        let resource = state.odata_model().parse_resource(&parts.uri.to_string())?;
+
+        Ok(ExtractServiceODataResource(resource))
+    }
+}
+
+/// Extracts a [`ODataResource`] from the request and binds it to the [`ODataModel`] of the state, so that requests
+/// referring to unknown entity sets or properties are rejected with a 400 response listing the problems.
+pub struct ExtractBoundODataResource(pub BoundResource);
+
+#[async_trait]
+impl<S> FromRequestParts<Arc<S>> for ExtractBoundODataResource
+where
+    S: WithODataModelExt + Send + Sync,
+{
+    type Rejection = ODataErrorResponse;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<S>) -> Result<Self, Self::Rejection> {
+        let odata_model = state.odata_model();
+        let resource = odata_model.parse_resource(&parts.uri.to_string())?;
+        let bound = odata_model.bind(resource)?;
+
+        Ok(ExtractBoundODataResource(bound))
     }
 }
 
@@ -34,6 +72,12 @@ pub trait WithODataModelExt {
     fn odata_model(&self) -> &ODataModel;
 }
 
+impl WithODataModelExt for ODataModel {
+    fn odata_model(&self) -> &ODataModel {
+        self
+    }
+}
+
 pub async fn serve_edm<S>(State(state): State<Arc<S>>) -> impl IntoResponse
 where
     S: WithODataModelExt,
@@ -48,3 +92,59 @@ where
         .body(xml)
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use odata_model::error::ODataError;
+
+    use super::*;
+
+    fn request_parts(uri: &str) -> Parts {
+        http::Request::builder().uri(uri).body(()).unwrap().into_parts().0
+    }
+
+    async fn extract(state: &Arc<ODataModel>, uri: &str) -> Result<ODataResource, ODataErrorResponse> {
+        ExtractServiceODataResource::from_request_parts(&mut request_parts(uri), state)
+            .await
+            .map(|ExtractServiceODataResource(resource)| resource)
+    }
+
+    #[tokio::test]
+    async fn can_extract_a_resource_without_state() {
+        let ExtractODataResource(resource) =
+            ExtractODataResource::from_request_parts(&mut request_parts("/V4/TripPinService/People?$top=2"), &())
+                .await
+                .expect("Failed to extract the resource");
+        assert_eq!(resource.entity.name, "People");
+        assert_eq!(resource.top, Some(2));
+
+        let rejection = ExtractODataResource::from_request_parts(&mut request_parts("/People?$top=-1"), &())
+            .await
+            .map(|ExtractODataResource(resource)| resource)
+            .expect_err("Expected the invalid $top to be rejected");
+        assert!(matches!(rejection.0, ODataError::Syntax(_)));
+        assert_eq!(rejection.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn can_extract_a_resource_below_the_service_root_of_the_state() {
+        let state = Arc::new(ODataModel::new("https://example.com/api/odata/v4/tenant"));
+
+        let resource = extract(&state, "/api/odata/v4/tenant/People?$top=2").await.unwrap();
+        assert_eq!(resource.entity.name, "People");
+        assert_eq!(resource.top, Some(2));
+
+        let resource = extract(&state, "https://example.com/api/odata/v4/tenant/People")
+            .await
+            .unwrap();
+        assert_eq!(resource.entity.name, "People");
+
+        for uri in [
+            "https://example.org/api/odata/v4/tenant/People",
+            "/V4/TripPinService/People",
+        ] {
+            let rejection = extract(&state, uri).await.expect_err("Expected the URL to be rejected");
+            assert!(matches!(rejection.0, ODataError::OutsideServiceRoot(_, _)), "{uri}");
+        }
+    }
+}
@@ -4,9 +4,10 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
-use odata_model::model::ODataModel;
+use http::StatusCode;
+use odata_model::{error::ODataError, model::ODataModel};
 use serde::Serialize;
-use serde_json::{Map, Value};
+use serde_json::{json, Map, Value};
 
 const ODATA_VERSION_HEADER: &str = "OData-Version";
 const ETAG_HEADER: &str = "ETag";
@@ -20,6 +21,7 @@ where
     body: T,
     e_tag: Option<String>,
     context: Option<String>,
+    count: Option<u64>,
 }
 
 impl<T> ODataResponse<T>
@@ -33,6 +35,7 @@ where
             body,
             e_tag: None,
             context,
+            count: None,
         }
     }
 
@@ -40,9 +43,93 @@ where
         self.e_tag = Some(e_tag);
         self
     }
+
+    /// Add the total number of entities as `@odata.count`, when the request asked for it with `$count=true`
+    pub fn with_count(mut self, count: u64) -> Self {
+        self.count = Some(count);
+        self
+    }
+}
+
+/// The plain text response for the `/$count` path segment
+pub struct ODataCountResponse(pub u64);
+
+impl IntoResponse for ODataCountResponse {
+    fn into_response(self) -> Response {
+        let mut res = self.0.to_string().into_response();
+        let headers = res.headers_mut();
+        headers.insert(ODATA_VERSION_HEADER, ODATA_VERSION.parse().unwrap());
+
+        res
+    }
+}
+
+/// The error response for a request that isn't a valid OData request, e.g. one with a `$filter` on an unknown property.
+/// The body is an OData error, with a detail for each problem the EDM of the service revealed.
+#[derive(Debug)]
+pub struct ODataErrorResponse(pub ODataError);
+
+impl From<ODataError> for ODataErrorResponse {
+    fn from(error: ODataError) -> Self {
+        Self(error)
+    }
+}
+
+/// The status of the error response; queries the data source can't answer aren't the fault of the client
+fn error_status(error: &ODataError) -> StatusCode {
+    match error {
+        ODataError::Unsupported(_) => StatusCode::NOT_IMPLEMENTED,
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+fn build_error_body(error: &ODataError) -> Value {
+    let code = match error_status(error) {
+        StatusCode::NOT_IMPLEMENTED => "NotImplemented",
+        _ => "BadRequest",
+    };
+    let mut body = json!({
+        "code": code,
+        "message": error.to_string(),
+    });
+
+    match error {
+        ODataError::Syntax(syntax) => {
+            if let Some(option) = &syntax.option {
+                body["target"] = Value::from(option.as_str());
+            }
+        }
+        ODataError::Binding(diagnostics) => {
+            let details: Vec<Value> = diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    json!({
+                        "code": diagnostic.kind.code(),
+                        "target": diagnostic.target,
+                        "message": diagnostic.to_string(),
+                    })
+                })
+                .collect();
+            body["details"] = Value::from(details);
+        }
+        _ => (),
+    }
+
+    json!({ "error": body })
 }
 
-fn build_odata_body<T>(body: T, context: Option<String>) -> Json<Value>
+impl IntoResponse for ODataErrorResponse {
+    fn into_response(self) -> Response {
+        let body = Json(build_error_body(&self.0));
+        let mut res = (error_status(&self.0), body).into_response();
+        let headers = res.headers_mut();
+        headers.insert(ODATA_VERSION_HEADER, ODATA_VERSION.parse().unwrap());
+
+        res
+    }
+}
+
+fn build_odata_body<T>(body: T, context: Option<String>, count: Option<u64>) -> Json<Value>
 where
     T: Serialize,
 {
@@ -61,6 +148,9 @@ where
         if let Some(context) = context {
             response.insert("@odata.context".to_string(), Value::String(context));
         }
+        if let Some(count) = count {
+            response.insert("@odata.count".to_string(), Value::from(count));
+        }
         response.insert("value".to_string(), body);
     }
 
@@ -73,7 +163,7 @@ where
     T: Serialize,
 {
     fn into_response(self) -> Response {
-        let body = build_odata_body(self.body, self.context);
+        let body = build_odata_body(self.body, self.context, self.count);
         let mut res = body.into_response();
         let headers = res.headers_mut();
         headers.insert(ODATA_VERSION_HEADER, ODATA_VERSION.parse().unwrap());
@@ -96,10 +186,70 @@ mod tests {
             "foo": "bar"
         });
 
-        let body = build_odata_body(json, Some("Foo".to_string()));
+        let body = build_odata_body(json, Some("Foo".to_string()), None);
         let body = body.0;
         assert!(body.is_object());
         let body = body.as_object().unwrap();
         assert!(body.contains_key("@odata.context"));
     }
+
+    #[test]
+    fn can_add_count_to_a_collection() {
+        let json = serde_json::json!([{ "foo": "bar" }]);
+
+        let body = build_odata_body(json, Some("Foo".to_string()), Some(42)).0;
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "@odata.context": "Foo",
+                "@odata.count": 42,
+                "value": [{ "foo": "bar" }]
+            })
+        );
+    }
+
+    #[test]
+    fn can_report_the_diagnostics_of_a_request() {
+        let mut person = odata_edm::edm::EntityType::new("Person".to_string());
+        person.add_property("Name".to_string(), "Edm.String".to_string());
+        let model = ODataModel::default().with_entity_type(person);
+
+        let resource = model
+            .parse_resource("Person?$filter=Age gt 30&$orderby=Name")
+            .expect("Failed to parse the resource");
+        let error = model
+            .bind(resource)
+            .expect_err("Expected the unknown property to be reported");
+
+        let body = build_error_body(&error);
+        assert_eq!(body["error"]["code"], "BadRequest");
+        assert_eq!(
+            body["error"]["details"],
+            serde_json::json!([{
+                "code": "UnknownProperty",
+                "target": "Age",
+                "message": "$filter: 'Age' is not a property of 'Person'"
+            }])
+        );
+    }
+
+    #[test]
+    fn can_report_an_unsupported_query_as_not_implemented() {
+        let error = ODataError::Unsupported("'has' has no SQL translation".to_string());
+
+        let body = build_error_body(&error);
+        assert_eq!(body["error"]["code"], "NotImplemented");
+        let res = ODataErrorResponse(error).into_response();
+        assert_eq!(res.status(), StatusCode::NOT_IMPLEMENTED);
+    }
+
+    #[tokio::test]
+    async fn can_respond_with_a_plain_text_count() {
+        let res = ODataCountResponse(42).into_response();
+        assert_eq!(res.headers()["content-type"], "text/plain; charset=utf-8");
+        assert_eq!(res.headers()[ODATA_VERSION_HEADER], ODATA_VERSION);
+
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"42");
+    }
 }
@@ -1,12 +1,19 @@
+use std::sync::Arc;
+
 use axum::{routing::get, Json, Router};
-use odata_web_helpers::ExtractODataResource;
+use odata_parser::odata_model::model::ODataModel;
+use odata_web_helpers::ExtractServiceODataResource;
 use serde_json::{json, Value};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // build our application with a single route
     // try with something like: http://localhost:8080/V4/TripPinService/Products?$filter=Name eq 'Milk' and Price lt 2.55
-    let app = Router::new().route("/V4/TripPinService/Products", get(parse_odata_request_handler));
+    // the paths of the requests are parsed relative to the root of the service
+    let model = Arc::new(ODataModel::new("/V4/TripPinService"));
+    let app = Router::new()
+        .route("/V4/TripPinService/Products", get(parse_odata_request_handler))
+        .with_state(model);
 
     // run it with hyper on localhost:8080
     axum::Server::bind(&"0.0.0.0:8080".parse().unwrap())
@@ -17,7 +24,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-async fn parse_odata_request_handler(ExtractODataResource(odata_resource): ExtractODataResource) -> Json<Value> {
+async fn parse_odata_request_handler(
+    ExtractServiceODataResource(odata_resource): ExtractServiceODataResource,
+) -> Json<Value> {
     let response = format!("{:?}", odata_resource);
     eprintln!("Parsed the resource: {response}");
     Json(json!({ "parsed resource": response }))